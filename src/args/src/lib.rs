@@ -1,17 +1,86 @@
 // External libraries
 use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::str::FromStr;
 
 extern crate getopts;
 use getopts::Options;
 
+#[macro_use]
+extern crate serde_json;
+use serde_json::Value;
+
 // Internal libraries
 extern crate gr;
-use gr::GI;
+use gr::{GI, InputKind};
 
 extern crate function;
-use function::FuncObj;
+use function::{FuncObj, FuncDef, CompilePolicy, Format};
+use function::{plugin, fpcore, smtlib};
+use function::format::bits_to_hex;
+
+extern crate solver;
+use solver::{Solver, SolveResult, Extremum, Strategy, Tolerances, Mode};
+use solver::candidates::{Candidate, cluster};
+use solver::certificate::Certificate;
+use solver::checkpoint::Checkpoint;
+use solver::events::EventLog;
 
 // Datatypes
+
+// How the result is printed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Output {
+    // The text read by the gelpia frontend.
+    Text,
+    // A single JSON object, see Args::report_json.
+    Json,
+}
+
+impl Output {
+    // The format asked for with --output, or text, for reporting errors in
+    // the arguments before they could be processed.
+    pub fn requested() -> Output {
+        let args: Vec<String> = env::args().collect();
+        for (i, arg) in args.iter().enumerate() {
+            let value = if arg == "--output" {
+                args.get(i + 1).map(|v| v.as_str())
+            } else {
+                arg.strip_prefix("--output=")
+            };
+            if let Some(Ok(output)) = value.map(|v| v.parse::<Output>()) {
+                return output;
+            }
+        }
+        Output::Text
+    }
+
+    // Reports a failure, as 'error' in the text form.
+    pub fn report_error(self, msg: &str) {
+        match self {
+            Output::Text => {
+                let _ = writeln!(&mut std::io::stderr(), "{}", msg);
+                println!("error")
+            }
+            Output::Json => println!("{}", json!({"error": msg})),
+        }
+    }
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Output, String> {
+        match s {
+            "text" => Ok(Output::Text),
+            "json" => Ok(Output::Json),
+            _ => Err(format!("Unknown output format '{}', expected text or json", s)),
+        }
+    }
+}
+
 pub struct Args {
     pub domain: Vec<GI>,
     pub function: FuncObj,
@@ -22,9 +91,29 @@ pub struct Args {
     pub update_interval: u32,
     pub iters: u32,
     pub names: Vec<String>,
+    pub param_names: Vec<String>,
     pub func_suffix: String,
-    pub logging: bool,
+    // Where to write progress events, if anywhere.
+    pub log: Option<EventLog>,
     pub seed: u32,
+    pub mode: Mode,
+    pub export_smtlib: Option<String>,
+    // Whether to report every box that may hold the extremum, and whether to
+    // merge adjacent ones into clusters.
+    pub candidates: bool,
+    pub cluster: bool,
+    // Where to write the certificate proving the reported bounds, if anywhere.
+    pub certificate: Option<String>,
+    // Where to save checkpoints and the seconds between them, and the
+    // checkpoint to resume from.
+    pub checkpoint: Option<String>,
+    pub checkpoint_interval: u32,
+    pub resume: Option<Checkpoint>,
+    pub output: Output,
+    // Whether results must not depend on thread timing, and the number of
+    // workers of the parallel branch and bound.
+    pub deterministic: bool,
+    pub workers: usize,
 }
 
 
@@ -33,15 +122,15 @@ pub struct Args {
 // Transforms the standardized string representation of the constants list
 //     into a vector of intervals
 fn parse_constants(consts: &String) 
-               -> Vec<GI> {
+               -> Result<Vec<GI>, String> {
     let mut result = vec![];
     for inst in consts.split('|') {
         if inst == "" {
             continue;
         }
-        result.push(GI::new_c(inst).unwrap());
+        result.push(GI::new_c(inst)?);
     }
-    result
+    Ok(result)
 }
 
 
@@ -62,87 +151,522 @@ fn parse_names(names: &String)
 
 
 
+impl Args {
+    // Collects the function, its inputs, parameters and constraints into a
+    // definition that can be saved and later loaded with --function-file.
+    pub fn definition(&self) -> FuncDef {
+        FuncDef{instructions: self.function.tape(),
+                constants: self.function.constants().clone(),
+                names: self.names.clone(),
+                domain: self.domain.clone(),
+                kinds: self.function.input_kinds().to_vec(),
+                param_names: self.param_names.clone(),
+                params: self.function.user_vars().clone(),
+                constraints: self.function.constraint_tapes(),
+                equalities: self.function.equality_tapes(),
+                metadata: Default::default()}
+    }
+
+    // A solver for the function over the domain, set up with the mode,
+    // tolerances, limits, seed, event log, checkpoints and determinism given on
+    // the command line.
+    pub fn solver(&self, strategy: Strategy) -> Solver {
+        let mut solver = Solver::new(self.function.clone(), self.domain.clone())
+            .tolerances(Tolerances{x: self.x_error, y: self.y_error,
+                                   y_rel: self.y_error_rel})
+            .timeout(self.timeout)
+            .update_interval(self.update_interval)
+            .max_iters(self.iters)
+            .seed(self.seed)
+            .strategy(strategy)
+            .mode(self.mode)
+            .candidates(self.candidates || self.cluster)
+            .certificate(self.certificate.is_some())
+            .deterministic(self.deterministic);
+        if let Some(ref log) = self.log {
+            solver = solver.log(log.clone());
+        }
+        if let Some(ref path) = self.checkpoint {
+            solver = solver.checkpoint(path, self.checkpoint_interval);
+        }
+        if let Some(ref checkpoint) = self.resume {
+            solver = solver.resume(checkpoint.clone());
+        }
+        solver
+    }
+
+    // Writes any SMT-LIB script requested with --export-smtlib and any
+    // certificate requested with --certificate, then prints the result in the
+    // form chosen with --output. If a file cannot be written, that is reported
+    // with report_error instead of the result. In the text form read by the
+    // gelpia frontend the minimum is printed first in the range mode, then the
+    // maximum.
+    pub fn report(&self, result: &SolveResult) {
+        let lower = result.min.as_ref().map(|e| e.lower);
+        let upper = result.max.as_ref().map(|e| e.upper);
+        if let Err(msg) = self.export_smtlib(lower, upper)
+            .and_then(|_| self.save_certificate(result)) {
+            return self.report_error(&msg);
+        }
+        match self.output {
+            Output::Text => {
+                for e in result.min.iter().chain(result.max.iter()) {
+                    self.report_extremum(e, result.feasibility_tolerance);
+                }
+            }
+            Output::Json => self.report_json(result),
+        }
+    }
+
+    fn report_extremum(&self, e: &Extremum, tolerance: Option<f64>) {
+        let status = self.function.compile_status();
+        let mut fields = vec![format!("'$compile_status' : '{}'", status)];
+        // Quoted as a JSON string, which the frontend also reads as a Python
+        // string whatever the compiler printed
+        if let Some(msg) = status.error() {
+            fields.push(format!("'$compile_error' : {}", json!(msg)));
+        }
+        if let Some(eps) = tolerance {
+            fields.push(format!("'$feasibility_tolerance' : {}", eps));
+        }
+        if let Some(Err(ref msg)) = e.proof {
+            fields.push(format!("'$proof_error' : {}", json!(msg)));
+        }
+        if self.domain.len() == 0 {
+            println!("[[{},{}], {{{}}}]", e.lower, e.upper, fields.join(", "));
+        } else {
+            println!("[[{},{}], {{", e.lower, e.upper);
+            for i in 0..self.names.len() {
+                println!("'{}' : {},", self.names[i], e.at[i].to_string());
+            }
+            for field in &fields {
+                println!("{},", field);
+            }
+            if self.candidates || self.cluster {
+                self.report_candidates(e);
+            }
+            println!("}}]");
+        }
+    }
+
+    // Reports a failed search, as 'error' in the text form.
+    pub fn report_error(&self, msg: &str) {
+        self.output.report_error(msg)
+    }
+
+    // Prints the result as one JSON object:
+    //   {"max": {"lower": 0.25, "lower_hex": "3fd0000000000000", "upper": ...,
+    //            "upper_hex": ..., "at": {"x": {"lower": ..., ...}}},
+    //    "min": null, "termination": "converged", "iterations": 523,
+    //    "evaluations": 1046, "feasibility_tolerance": null,
+    //    "compile_status": "disabled", "compile_error": null}
+    // Every bound is given in decimal, null if infinite, and as the hex digits
+    // of its bits. The extrema that were not searched for are null. Candidate
+    // boxes are listed under "candidates" in each extremum when requested.
+    // "compile_error" holds the reason and captured stderr when compilation
+    // failed, and "proof_error" in an extremum why its bound could not be
+    // proved for the certificate.
+    fn report_json(&self, result: &SolveResult) {
+        let extremum = |e: &Extremum| {
+            let mut v = bounds_json(e.lower, e.upper);
+            v["at"] = self.box_json(&e.at);
+            if let Some(Err(ref msg)) = e.proof {
+                v["proof_error"] = json!(msg);
+            }
+            if self.candidates || self.cluster {
+                v["candidates"] = Value::Array(self.candidates(e).iter().map(|c| {
+                    let mut v = bounds_json(c.value.lower(), c.value.upper());
+                    v["at"] = self.box_json(&c.at);
+                    v["boxes"] = json!(c.boxes);
+                    v
+                }).collect());
+            }
+            v
+        };
+        let status = self.function.compile_status();
+        println!("{}", json!({
+            "max": result.max.as_ref().map(&extremum),
+            "min": result.min.as_ref().map(&extremum),
+            "termination": result.termination.to_string(),
+            "iterations": result.iterations,
+            "evaluations": result.evaluations,
+            "feasibility_tolerance": result.feasibility_tolerance,
+            "compile_status": status.to_string(),
+            "compile_error": status.error(),
+        }));
+    }
+
+    // The box as an object from input names to their bounds.
+    fn box_json(&self, x: &[GI]) -> Value {
+        Value::Object(self.names.iter().zip(x.iter())
+                      .map(|(n, xi)| (n.clone(), bounds_json(xi.lower(), xi.upper())))
+                      .collect())
+    }
+
+    // The boxes that may hold the extremum, clustered if requested.
+    fn candidates(&self, e: &Extremum) -> Vec<Candidate> {
+        if self.cluster {
+            cluster(e.candidates.clone(), self.function.input_kinds())
+        } else {
+            e.candidates.clone()
+        }
+    }
+
+    // Lists the boxes that may hold the extremum as '$candidates', each with
+    // the enclosure of the function over it and the number of boxes merged
+    // into it.
+    fn report_candidates(&self, e: &Extremum) {
+        let candidates = self.candidates(e);
+        println!("'$candidates' : [");
+        for c in &candidates {
+            let inputs = self.names.iter().zip(c.at.iter())
+                .map(|(n, x)| format!("'{}' : {}", n, x.to_string()))
+                .collect::<Vec<_>>();
+            println!("[[{},{}], {{{}, '$boxes' : {}}}],", c.value.lower(), c.value.upper(),
+                     inputs.join(", "), c.boxes);
+        }
+        println!("],");
+    }
+
+    // Renders the function with the input and parameter names, optionally
+    // annotating every operation with its enclosure over the whole domain.
+    pub fn render(&self, format: Format, annotate: bool) -> String {
+        let domain = if annotate { Some(&self.domain[..]) } else { None };
+        self.function.render(format, &self.names, &self.param_names, domain)
+    }
+
+    // Writes the SMT-LIB script checking the given lower and upper bounds of
+    // the function over the domain, if one was requested with --export-smtlib.
+    pub fn export_smtlib(&self, lower: Option<f64>, upper: Option<f64>)
+                         -> Result<(), String> {
+        let path = match self.export_smtlib {
+            Some(ref p) => p,
+            None => return Ok(()),
+        };
+        let script = self.function.smtlib(&self.names, &self.param_names,
+                                          &self.domain, lower, upper)?;
+        let mut file = File::create(path)
+            .map_err(|e| format!("Unable to create {}: {}", path, e))?;
+        writeln!(file, "{}", script)
+            .map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+
+    // Writes the proofs of the bounds in the result along with the function
+    // definition, if a certificate was requested with --certificate. Bounds
+    // that could not be proved are left out.
+    pub fn save_certificate(&self, result: &SolveResult) -> Result<(), String> {
+        let path = match self.certificate {
+            Some(ref p) => p,
+            None => return Ok(()),
+        };
+        let proofs = result.min.iter().chain(result.max.iter())
+            .filter_map(|e| e.proof.clone().and_then(|p| p.ok()))
+            .collect();
+        Certificate{function: self.definition(),
+                    eq_tolerance: self.function.equality_tolerance(),
+                    proofs: proofs}.save(path)
+    }
+
+    // Sets the named user variable of the function, so a family of problems
+    // that only differ in parameter values can be solved without rebuilding.
+    pub fn set_param(&mut self, name: &str, value: GI) -> Result<(), String> {
+        match self.param_names.iter().position(|n| n == name) {
+            Some(i) => self.function.set_user_var(i, value),
+            None => Err(format!("Unknown parameter '{}'", name))
+        }
+    }
+}
+
+
+// The bounds in decimal, null if infinite, and as the hex digits of their
+// bits.
+fn bounds_json(lower: f64, upper: f64) -> Value {
+    json!({"lower": lower, "lower_hex": bits_to_hex(lower),
+           "upper": upper, "upper_hex": bits_to_hex(upper)})
+}
+
+
 // Processes the arguments given our executable
-pub fn process_args() -> Args {
+pub fn process_args() -> Result<Args, String> {
     let mut opts = Options::new();
 
     // Required
     opts.reqopt("S", "func-suffix", "", "");
-    opts.reqopt("c", "constants", "", "");
-    opts.reqopt("f", "function", "", "");
-    opts.reqopt("i", "input", "", "");
     opts.reqopt("x", "x_epsilon", "", "");
     opts.reqopt("y", "y_epsilon", "", "");
-    opts.reqopt("n", "names", "", "");
     opts.reqopt("r", "y_epsilon_relative", "", "");
+
+    // Required unless the function is read from a file
+    opts.optopt("c", "constants", "", "");
+    opts.optopt("f", "function", "", "");
+    opts.optopt("i", "input", "", "");
+    opts.optopt("n", "names", "", "");
     
     // Optional
     opts.optopt("t", "time_out", "", "");
-    opts.optopt("M", "max_iters", "", "");
+    opts.optopt("M", "max_iters",
+                "Number of box evaluations after which the search stops, 0 for no limit", "");
     opts.optopt("u", "update", "", "");
     opts.optflag("d", "debug", "Enable debugging");
-    opts.optflag("L", "logging", "Enable maximum logging to stderr");
+    opts.optflag("L", "logging",
+                 "Write progress events as JSON lines to stderr");
+    opts.optopt("", "log-file",
+                "Write the progress events to this path instead of stderr", "");
+    opts.optopt("", "log-interval",
+                "Seconds between progress statistics, 0 for none (default 1)", "");
+    opts.optopt("", "mode",
+                "Search for the max (default), the min or the range of the \
+                 function", "");
     opts.optopt("s", "seed", "Seed to use for random number generators", "");
+    opts.optopt("p", "params", "Values of the user variables, '|' separated", "");
+    opts.optopt("P", "param-names", "Names of the user variables, ',' separated", "");
+    opts.optopt("C", "compile",
+                "Compilation policy: background, wait, interp or require", "");
+    opts.optopt("F", "function-file",
+                "Read the function, constants, inputs and parameters from a \
+                 JSON or binary function definition", "");
+    opts.optopt("", "fpcore",
+                "Read the function and input domain from an FPCore file, \
+                 inputs are bounded by :pre", "");
+    opts.optopt("", "fpcore-name",
+                "Name of the FPCore to read when the file holds several, \
+                 the first one is used otherwise", "");
+    opts.optopt("", "smtlib",
+                "Read the function and input domain from an SMT-LIB script \
+                 asserting bounds on the inputs and (> objective bound)", "");
+    opts.optopt("", "export-smtlib",
+                "Write an SMT-LIB script to this path that checks the computed \
+                 bounds with an SMT solver", "");
+    opts.optopt("", "save-function",
+                "Write the function definition to this path, JSON if it ends \
+                 in .json and binary otherwise", "");
+    opts.optmulti("", "constraint",
+                  "Tape of a constraint g(x) <= 0 over the same constants, inputs \
+                   and parameters as the function, may be given several times", "");
+    opts.optmulti("", "equality",
+                  "Tape of an equality constraint h(x) = 0 over the same constants, \
+                   inputs and parameters as the function, may be given several times", "");
+    opts.optopt("", "eq-tolerance",
+                "Count points with |h(x)| <= this as satisfying the equality \
+                 constraints (default 0)", "");
+    opts.optopt("", "integer",
+                "Names of the inputs that only take integer values, ',' separated", "");
+    opts.optmulti("", "values",
+                  "An input that only takes the listed values, as \
+                   <name>=<value>,<value>,..., may be given several times", "");
+    opts.optmulti("", "plugin",
+                  "Load the interval functions exported by this plugin \
+                   library, may be given several times", "");
+    opts.optopt("", "render-function",
+                "Print the function to stderr as infix, latex or dot", "");
+    opts.optflag("", "candidates",
+                 "Report every box that may still hold the extremum");
+    opts.optflag("", "cluster",
+                 "Report the candidate boxes with adjacent ones merged");
+    opts.optopt("", "certificate",
+                "Write a certificate proving the reported bounds to this path, \
+                 to be checked with the verify binary", "");
+    opts.optopt("", "checkpoint",
+                "Save the state of the search to this path periodically and \
+                 when it ends", "");
+    opts.optopt("", "checkpoint-interval",
+                "Seconds between checkpoints (default 60)", "");
+    opts.optopt("", "resume",
+                "Continue the search saved in this checkpoint", "");
+    opts.optflag("", "deterministic",
+                 "Give bit-identical results on every run and for any number of \
+                  workers, unless the search times out");
+    opts.optopt("", "workers",
+                "Number of worker threads of cooperative-mt (default 11)", "");
+    opts.optopt("", "output",
+                "Print the result as text (default) or json", "");
+    opts.optflag("", "render-enclosures",
+                 "Annotate the printed function with enclosures over the domain");
     
     // Check that the args are there
     let args: Vec<String> = env::args().collect();
-    let matches = match opts.parse(&args[1..]) {
-        Ok(m) => m,
-        Err(f) => {panic!(f.to_string())}
-    };
+    let matches = opts.parse(&args[1..]).map_err(|f| f.to_string())?;
 
-    // Grab out the required arguments
-    let input_string = matches.opt_str("i").unwrap();
-    let x_0 = parse_constants(&input_string);
+    // Plugins have to be registered before the function using them is built
+    for path in matches.opt_strs("plugin") {
+        plugin::load(Path::new(&path))?;
+    }
 
-    let names_string = matches.opt_str("n").unwrap();
-    let names = parse_names(&names_string);
+    // Grab out the function definition, either from a file or from the
+    // individual arguments
+    let fpcore_name = matches.opt_str("fpcore-name");
+    let mut def = match (matches.opt_str("F"), matches.opt_str("fpcore"),
+                     matches.opt_str("smtlib")) {
+        (Some(path), _, _) => FuncDef::load(&path)?,
+        (None, Some(path), _) => fpcore::load(&path, fpcore_name.as_ref()
+                                              .map(|s| s.as_str()))
+            .map_err(|msg| format!("{}: {}", path, msg))?,
+        (None, None, Some(path)) => smtlib::load(&path)
+            .map_err(|msg| format!("{}: {}", path, msg))?,
+        (None, None, None) => {
+            let required = |name: &str| matches.opt_str(name)
+                .ok_or(format!("Required option '{}' missing", name));
+            let x_0 = parse_constants(&required("i")?)?;
+            let names = parse_names(&required("n")?);
+            let consts = parse_constants(&required("c")?)?;
+            let func_string = required("f")?;
 
-    let const_string = matches.opt_str("c").unwrap();
-    let consts = parse_constants(&const_string);
+            let params = match matches.opt_str("p") {
+                Some(p) => parse_constants(&p)?,
+                None => vec![]
+            };
+            // Unnamed parameters are named after their instruction
+            let param_names = match matches.opt_str("P") {
+                Some(n) => parse_names(&n),
+                None => (0..params.len()).map(|i| format!("v{}", i)).collect()
+            };
+            if param_names.len() != params.len() {
+                return Err(format!("Got {} parameter names for {} parameters",
+                                   param_names.len(), params.len()));
+            }
+            FuncDef{instructions: func_string, constants: consts,
+                    names: names, domain: x_0, kinds: vec![],
+                    param_names: param_names, params: params,
+                    constraints: vec![], equalities: vec![],
+                    metadata: Default::default()}
+        }
+    };
+
+    def.constraints.extend(matches.opt_strs("constraint"));
+    def.equalities.extend(matches.opt_strs("equality"));
+    if matches.opt_present("integer") || matches.opt_present("values") {
+        if def.kinds.is_empty() {
+            def.kinds = vec![InputKind::Real; def.names.len()];
+        }
+        let names = def.names.clone();
+        let input = |name: &str| names.iter().position(|n| n == name)
+            .ok_or(format!("Unknown input '{}'", name));
+        for name in matches.opt_str("integer").iter().flat_map(|n| parse_names(n)) {
+            let i = input(&name)?;
+            def.kinds[i] = InputKind::Integer;
+        }
+        for spec in matches.opt_strs("values") {
+            let (name, values) = match spec.find('=') {
+                Some(pos) => (&spec[..pos], &spec[pos + 1..]),
+                None => return Err(format!("Expected <name>=<values> for --values, got '{}'",
+                                           spec))
+            };
+            let values = values.split(',').map(|v| v.trim().parse::<f64>()
+                .map_err(|_| format!("Invalid value '{}' for input '{}'", v, name)))
+                .collect::<Result<Vec<f64>, String>>()?;
+            let i = input(name.trim())?;
+            def.kinds[i] = InputKind::values(values)?;
+        }
+    }
 
     let func_suffix = matches.opt_str("S").unwrap();
-    let func_string = matches.opt_str("f").unwrap();
     let debug = matches.opt_present("d");
-    let logging = matches.opt_present("L");
-    let fo = FuncObj::new(&consts, &func_string, debug, func_suffix.clone());
+    let policy = if matches.opt_present("C") {
+        matches.opt_str("C").unwrap().parse::<CompilePolicy>()?
+    } else {
+        CompilePolicy::Background
+    };
+    let mut fo = FuncObj::new(&def.constants, &def.params, &def.instructions,
+                              def.domain.len(), debug, func_suffix.clone(), policy)?;
+    for c in &def.constraints {
+        fo.add_constraint(c, def.domain.len())?;
+    }
+    for h in &def.equalities {
+        fo.add_equality(h, def.domain.len())?;
+    }
+    fo.set_input_kinds(def.kinds.clone());
+    if let Some(eps) = matches.opt_str("eq-tolerance") {
+        let eps = eps.parse::<f64>()
+            .map_err(|_| format!("Invalid equality tolerance '{}'", eps))?;
+        fo.set_equality_tolerance(eps)?;
+    }
+    if let Some(path) = matches.opt_str("save-function") {
+        def.save(&path)?;
+    }
 
     // Grab out optional arguments
-    let to = if matches.opt_present("t") { 
-        matches.opt_str("t").unwrap().parse::<u32>().unwrap() 
-    } else { 
-        0 as u32 
+    let count = |name: &str, what: &str| match matches.opt_str(name) {
+        Some(s) => s.parse::<u32>().map_err(|_| format!("Invalid {} '{}'", what, s)),
+        None => Ok(0)
+    };
+    let to = count("t", "timeout")?;
+    let seed = count("s", "seed")?;
+    let ui = count("u", "update interval")?;
+    let a_iters = count("M", "maximum number of iterations")?;
+
+    let checkpoint_interval = match matches.opt_str("checkpoint-interval") {
+        Some(s) => s.parse::<u32>()
+            .map_err(|_| format!("Invalid checkpoint interval '{}'", s))?,
+        None => 60
+    };
+    let log_interval = match matches.opt_str("log-interval") {
+        Some(s) => s.parse::<u32>()
+            .map_err(|_| format!("Invalid log interval '{}'", s))?,
+        None => 1
+    };
+    let log = match matches.opt_str("log-file") {
+        Some(path) => Some(EventLog::file(&path, log_interval)?),
+        None if matches.opt_present("L") => Some(EventLog::stderr(log_interval)),
+        None => None
+    };
+    let resume = match matches.opt_str("resume") {
+        Some(path) => Some(Checkpoint::load(&path)?),
+        None => None
     };
 
-    let seed = if matches.opt_present("s") {
-        matches.opt_str("seed").unwrap().parse::<u32>().unwrap() 
-    } else { 
-        0 as u32 
+    let output = match matches.opt_str("output") {
+        Some(o) => o.parse::<Output>()?,
+        None => Output::Text
     };
 
-    let ui = if matches.opt_present("u") {
-        matches.opt_str("u").unwrap().parse::<u32>().unwrap()
-    } else {
-        0 as u32
+    let workers = match matches.opt_str("workers") {
+        Some(s) => match s.parse::<usize>() {
+            Ok(n) if n > 0 => n,
+            _ => return Err(format!("Invalid number of workers '{}'", s))
+        },
+        None => 11
     };
-    
-    let a_iters = if matches.opt_present("M") {
-        matches.opt_str("M").unwrap().parse::<u32>().unwrap()
-    } else {
-        0 as u32
+
+    let mode = match matches.opt_str("mode") {
+        Some(m) => m.parse::<Mode>()?,
+        None => Mode::Max
+    };
+
+    let tolerance = |name: &str| {
+        let s = matches.opt_str(name).unwrap();
+        s.parse::<f64>().map_err(|_| format!("Invalid tolerance '{}'", s))
     };
 
     // Return parsed information in a struct
-    Args{domain: x_0, 
-         function: fo, 
-         x_error: matches.opt_str("x").unwrap().parse::<f64>().unwrap(),
-         y_error: matches.opt_str("y").unwrap().parse::<f64>().unwrap(), 
-         y_error_rel: matches.opt_str("r").unwrap().parse::<f64>().unwrap(), 
-         timeout: to, 
-         iters: a_iters,
-         names: names, 
-         update_interval: ui, 
-         func_suffix: func_suffix,
-         logging: logging,
-         seed: seed}
+    let args = Args{domain: def.domain, 
+                    function: fo, 
+                    x_error: tolerance("x")?,
+                    y_error: tolerance("y")?,
+                    y_error_rel: tolerance("r")?,
+                    timeout: to, 
+                    iters: a_iters,
+                    names: def.names, 
+                    param_names: def.param_names,
+                    update_interval: ui, 
+                    func_suffix: func_suffix,
+                    log: log,
+                    seed: seed,
+                    mode: mode,
+                    export_smtlib: matches.opt_str("export-smtlib"),
+                    candidates: matches.opt_present("candidates"),
+                    cluster: matches.opt_present("cluster"),
+                    certificate: matches.opt_str("certificate"),
+                    checkpoint: matches.opt_str("checkpoint"),
+                    checkpoint_interval: checkpoint_interval,
+                    resume: resume,
+                    output: output,
+                    deterministic: matches.opt_present("deterministic"),
+                    workers: workers};
+
+    if let Some(f) = matches.opt_str("render-function") {
+        let format = f.parse::<Format>()?;
+        let _ = writeln!(&mut std::io::stderr(), "{}",
+                         args.render(format, matches.opt_present("render-enclosures")));
+    }
+    Ok(args)
 }
@@ -0,0 +1,44 @@
+// Checks the proofs of a certificate written with --certificate, evaluating
+// every leaf again with the interpreter
+use std::env;
+use std::io::Write;
+use std::process;
+
+extern crate function;
+use function::CompilePolicy;
+
+extern crate solver;
+use solver::Mode;
+use solver::certificate::{Certificate, check};
+
+fn verify(path: &str) -> Result<(), String> {
+    let cert = Certificate::load(path)?;
+    let mut f = cert.function.build(false, "verify".to_string(), CompilePolicy::Interpret)?;
+    f.set_equality_tolerance(cert.eq_tolerance)?;
+    if cert.proofs.is_empty() {
+        return Err("The certificate holds no proofs".to_string());
+    }
+    for proof in &cert.proofs {
+        let claim = match proof.mode {
+            Mode::Min => format!("min >= {}", proof.bound),
+            _ => format!("max <= {}", proof.bound),
+        };
+        let leaves = check(&f, &cert.function.domain, proof)
+            .map_err(|msg| format!("{}: {}", claim, msg))?;
+        println!("{}: verified, {} boxes with {} leaves", claim, proof.nodes.len(), leaves);
+    }
+    Ok(())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        let _ = writeln!(&mut std::io::stderr(), "Usage: {} <certificate>", args[0]);
+        process::exit(2);
+    }
+    if let Err(msg) = verify(&args[1]) {
+        let _ = writeln!(&mut std::io::stderr(), "{}", msg);
+        println!("invalid");
+        process::exit(1);
+    }
+}
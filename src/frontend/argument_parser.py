@@ -192,8 +192,8 @@ def create_arg_parser():
                             " (default {})".format(defaults.timeout))
     arg_parser.add_argument("-M", "--max-iters",
                             type=int,
-                            help="Maximum number of iterations for the IBBA"
-                            " algorithm."
+                            help="Maximum number of boxes the IBBA"
+                            " algorithm evaluates, reported as 'evaluations'."
                             " (default {})".format(defaults.max_iters))
     arg_parser.add_argument("-i", "--input-epsilon",
                             type=float,
@@ -2,7 +2,7 @@
 
 import sys
 
-from pass_utils import BINOPS, UNOPS, ATOMS
+from pass_utils import TERNOPS, BINOPS, UNOPS, ATOMS
 try:
     import gelpia_logging as logging
     import color_printing as color
@@ -50,6 +50,8 @@ def expand_error(work_stack, count, exp):
 
 
 default_walk_expand_func_dict = dict()
+default_walk_expand_func_dict.update(
+    zip(TERNOPS, [expand_many for _ in TERNOPS]))
 default_walk_expand_func_dict.update(
     zip(BINOPS, [expand_two for _ in BINOPS]))
 default_walk_expand_func_dict.update(
@@ -59,6 +61,7 @@ default_walk_expand_func_dict.update(
 default_walk_expand_func_dict["Box"] = expand_many
 default_walk_expand_func_dict["Const"] = expand_atom
 default_walk_expand_func_dict["Input"] = expand_atom
+default_walk_expand_func_dict["Param"] = expand_atom
 default_walk_expand_func_dict["Tuple"] = expand_two
 default_walk_expand_func_dict["Return"] = expand_one
 
@@ -82,6 +85,8 @@ def contract_error(work_stack, count, args):
 
 
 default_walk_contract_func_dict = dict()
+default_walk_contract_func_dict.update(
+    zip(TERNOPS, [contract_many for _ in TERNOPS]))
 default_walk_contract_func_dict.update(
     zip(BINOPS, [contract_many for _ in BINOPS]))
 default_walk_contract_func_dict.update(
@@ -89,6 +94,7 @@ default_walk_contract_func_dict.update(
 default_walk_contract_func_dict["Box"] = contract_many
 default_walk_contract_func_dict["Const"] = contract_many
 default_walk_contract_func_dict["Input"] = contract_many
+default_walk_contract_func_dict["Param"] = contract_many
 default_walk_contract_func_dict["Tuple"] = contract_many
 default_walk_contract_func_dict["Return"] = contract_return
 
@@ -117,6 +123,8 @@ def constant_expand_many(work_stack, count, exp):
 
 
 constant_walk_expand_func_dict = dict()
+constant_walk_expand_func_dict.update(
+    zip(TERNOPS, [constant_expand_many for _ in TERNOPS]))
 constant_walk_expand_func_dict.update(
     zip(BINOPS, [constant_expand_two for _ in BINOPS]))
 constant_walk_expand_func_dict.update(
@@ -126,6 +134,7 @@ constant_walk_expand_func_dict.update(
 constant_walk_expand_func_dict["Box"] = constant_expand_many
 constant_walk_expand_func_dict["Const"] = constant_expand_atom
 constant_walk_expand_func_dict["Input"] = constant_expand_atom
+constant_walk_expand_func_dict["Param"] = constant_expand_atom
 constant_walk_expand_func_dict["Tuple"] = constant_expand_two
 constant_walk_expand_func_dict["Return"] = constant_expand_one
 
@@ -23,8 +23,10 @@ class GelpiaLexer(Lexer):
     tokens = [
         # Variables
         "NAME",
+        "PARAM",
 
         # Prefix operators
+        "TERNOP",
         "BINOP",
         "UNOP",
 
@@ -40,6 +42,12 @@ class GelpiaLexer(Lexer):
         "DIVIDE",
         "INFIX_POW",
 
+        # Comparisons
+        "LE",
+        "LT",
+        "GE",
+        "GT",
+
         # Assignment
         "EQUALS",
 
@@ -53,6 +61,7 @@ class GelpiaLexer(Lexer):
     ]
 
     # Sets of special symbols
+    TERNOPS = {r"ite"}
     BINOPS = {r"pow", r"sub2"}
     UNOPS = {r"abs", r"acos", r"acosh", r"asin", r"asinh", r"atan", r"atanh",
              r"cos", r"cosh", r"exp", r"log", r"sin", r"sinh", r"sqrt",
@@ -87,6 +96,7 @@ class GelpiaLexer(Lexer):
     ignore_labels = r"({})".format(r")|(".join([r"cost:", r"var:"]))
 
     # Prefix operators
+    TERNOP = r"({})".format(r")|(".join(TERNOPS))
     BINOP = r"({})".format(r")|(".join(BINOPS))
     UNOP = r"({})".format(r")|(".join(UNOPS))
 
@@ -117,6 +127,12 @@ class GelpiaLexer(Lexer):
     NAME = r"([a-zA-Z]|\_)([a-zA-Z]|\_|\d)*"
 
     def NAME(self, t):
+        if t.value == "param":
+            t.type = "PARAM"
+            return t
+        if t.value in self.TERNOPS:
+            t.type = TERNOP
+            return t
         if t.value in self.BINOPS:
             t.type = BINOP
             return t
@@ -136,6 +152,12 @@ class GelpiaLexer(Lexer):
     DIVIDE = r"/"
     INFIX_POW = r"\^"
 
+    # Comparisons, longest match first
+    LE = r"<="
+    LT = r"<"
+    GE = r">="
+    GT = r">"
+
     # Assignment
     EQUALS = r"="
 
@@ -24,7 +24,8 @@ from function_to_lexed import GelpiaLexer
 class GelpiaParser(Parser):
     tokens = GelpiaLexer.tokens
 
-    precedence = (("left", "PLUS", "MINUS"),
+    precedence = (("nonassoc", "LT", "LE", "GT", "GE"),
+                  ("left", "PLUS", "MINUS"),
                   ("left", "TIMES", "DIVIDE"),
                   ("right", "UMINUS"),
                   ("right", "INFIX_POW"),)
@@ -54,6 +55,15 @@ class GelpiaParser(Parser):
         logger.warning("Dropping assign to symbolic constant '{}'", p[1][1])
         return p.function
 
+    @_("PARAM variable EQUALS interval SEMICOLON function",
+       "PARAM variable EQUALS negconst SEMICOLON function")
+    def function(self, p):
+        assert(logger("function: PARAM variable EQUALS {} SEMICOLON function",
+                      p._slice[-3].type))
+        assert(logger("          PARAM {} EQUALS {} SEMICOLON {}",
+                      p.variable, p[3], p.function))
+        return (("Param", p.variable, p[3]), p.function)
+
     @_("expression_star")
     def function(self, p):
         assert(logger("function: expression_star"))
@@ -91,6 +101,16 @@ class GelpiaParser(Parser):
                       p.expression0, p._slice[-2].type, p.expression1))
         return (p[1], p.expression0, p.expression1)
 
+    @_("expression LT expression",
+       "expression LE expression",
+       "expression GT expression",
+       "expression GE expression")
+    def expression(self, p):
+        assert(logger("expression: expression {} expression", p._slice[-2].type))
+        assert(logger("            {} {} {}",
+                      p.expression0, p._slice[-2].type, p.expression1))
+        return (p[1], p.expression0, p.expression1)
+
     @_("expression INFIX_POW expression")
     def expression(self, p):
         assert(logger("expression: expression INFIX_POW expression"))
@@ -203,6 +223,14 @@ class GelpiaParser(Parser):
         return p.expression
 
     # func
+    @_("TERNOP LPAREN expression COMMA expression COMMA expression RPAREN")
+    def func(self, p):
+        assert(logger("func: TERNOP LPAREN expression COMMA expression COMMA "
+                      "expression RPAREN"))
+        assert(logger("      TERNOP LPAREN {} COMMA {} COMMA {} RPAREN",
+                      p.expression0, p.expression1, p.expression2))
+        return (p[0], p.expression0, p.expression1, p.expression2)
+
     @_("BINOP LPAREN expression COMMA expression RPAREN")
     def func(self, p):
         assert(logger("func: BINOP LPAREN expression COMMA expression RPAREN"))
@@ -3,7 +3,7 @@
 import sys
 
 from expression_walker import walk
-from pass_utils import INFIX, BINOPS, UNOPS, SYMBOLIC_CONSTS
+from pass_utils import INFIX, BINOPS, UNOPS, SYMBOLIC_CONSTS, COMPARISONS
 try:
     import gelpia_logging as logging
     import color_printing as color
@@ -30,6 +30,12 @@ def output_flatten(exp, inputs=None, consts=None, assigns=None):
         assert(len(exp) == 2)
         work_stack.append((True, count, [exp[1]]))
 
+    def _param(work_stack, count, exp):
+        assert(logger("expand_param: {}", exp))
+        assert(exp[0] == "Param")
+        assert(len(exp) == 2)
+        work_stack.append((True, count, [exp[1]]))
+
     def _const(work_stack, count, exp):
         assert(logger("expand_const: {}", exp))
         assert(exp[0] == "Const")
@@ -69,6 +75,7 @@ def output_flatten(exp, inputs=None, consts=None, assigns=None):
         work_stack.append((True, count, ["[", exp[1][1], ", ", exp[2][1], "]"]))
 
     my_expand_dict = {"Input": _input,
+                      "Param": _param,
                       "Const": _const,
                       "Float": _float,
                       "Integer": _integer,
@@ -79,7 +86,7 @@ def output_flatten(exp, inputs=None, consts=None, assigns=None):
 
     def _infix(work_stack, count, args):
         assert(logger("infix: {}", args))
-        assert(args[0] in INFIX)
+        assert(args[0] in INFIX or args[0] in COMPARISONS)
         assert(len(args) == 3)
         op = args[0]
         left = args[1]
@@ -97,6 +104,14 @@ def output_flatten(exp, inputs=None, consts=None, assigns=None):
         ret = [op, "("] + first + [", "] + secon + [")"]
         work_stack.append((True, count, ret))
 
+    def _ite(work_stack, count, args):
+        assert(logger("ite: {}", args))
+        assert(args[0] == "ite")
+        assert(len(args) == 4)
+        ret = (["ite("] + args[1] + [", "] + args[2] + [", "] + args[3]
+               + [")"])
+        work_stack.append((True, count, ret))
+
     def _pow(work_stack, count, args):
         assert(logger("pow: {}", args))
         assert(args[0] == "pow")
@@ -153,7 +168,10 @@ def output_flatten(exp, inputs=None, consts=None, assigns=None):
                                 [_unop for _ in UNOPS]))
     my_contract_dict.update(zip(INFIX,
                                 [_infix for _ in INFIX]))
+    my_contract_dict.update(zip(COMPARISONS,
+                                [_infix for _ in COMPARISONS]))
     my_contract_dict["pow"] = _pow
+    my_contract_dict["ite"] = _ite
     my_contract_dict["neg"] = _neg
     my_contract_dict["Box"] = _box
     my_contract_dict["Tuple"] = _tuple
@@ -185,7 +203,7 @@ def main(argv):
 
         tokens = function_to_lexed(data)
         tree = lexed_to_parsed(tokens)
-        exp, inputs = pass_lift_inputs_and_inline_assigns(tree)
+        exp, inputs, params = pass_lift_inputs_and_inline_assigns(tree)
         exp = pass_simplify(exp, inputs)
         d, diff_exp = pass_reverse_diff(exp, inputs)
         diff_exp = pass_simplify(diff_exp, inputs)
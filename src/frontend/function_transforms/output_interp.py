@@ -3,7 +3,7 @@
 import sys
 
 from expression_walker import walk
-from pass_utils import INFIX, UNOPS
+from pass_utils import INFIX, UNOPS, COMPARISONS
 try:
     import gelpia_logging as logging
     import color_printing as color
@@ -15,10 +15,11 @@ logger = logging.make_module_logger(color.cyan("output_interp"),
                                     logging.HIGH)
 
 
-def output_interp(exp, inputs, consts):
+def output_interp(exp, inputs, consts, params):
 
     input_mapping = {name: str(i) for name, i in zip(inputs, range(len(inputs)))}
     const_mapping = {name: str(i) for name, i in zip(consts, range(len(consts)))}
+    param_mapping = {name: str(i) for name, i in zip(params, range(len(params)))}
 
     def _const(work_stack, count, exp):
         assert(exp[0] == "Const")
@@ -30,8 +31,14 @@ def output_interp(exp, inputs, consts):
         assert(len(exp) == 2)
         work_stack.append((True, count,  ['i' + str(input_mapping[exp[1]])]))
 
+    def _param(work_stack, count, exp):
+        assert(exp[0] == "Param")
+        assert(len(exp) == 2)
+        work_stack.append((True, count,  ['v' + str(param_mapping[exp[1]])]))
+
     my_expand_dict = {"Const": _const,
-                      "Input": _input}
+                      "Input": _input,
+                      "Param": _param}
 
     def _pow(work_stack, count, args):
         assert(args[0] == "pow")
@@ -61,6 +68,20 @@ def output_interp(exp, inputs, consts):
         assert(type(args[2]) is list)
         work_stack.append((True, count, args[1] + args[2] + ["o" + args[0]]))
 
+    def _compare(work_stack, count, args):
+        assert(args[0] in COMPARISONS)
+        assert(len(args) == 3)
+        assert(type(args[1]) is list)
+        assert(type(args[2]) is list)
+        work_stack.append((True, count, args[1] + args[2] + ["o" + args[0]]))
+
+    def _ite(work_stack, count, args):
+        assert(args[0] == "ite")
+        assert(len(args) == 4)
+        assert(all(type(a) is list for a in args[1:]))
+        work_stack.append((True, count,
+                           args[1] + args[2] + args[3] + ["oite"]))
+
     def _unops(work_stack, count, args):
         assert(args[0] in UNOPS)
         assert(len(args) == 2)
@@ -75,8 +96,11 @@ def output_interp(exp, inputs, consts):
     my_contract_dict = dict()
     my_contract_dict.update(zip(INFIX, [_infix for _ in INFIX]))
     my_contract_dict.update(zip(UNOPS, [_unops for _ in UNOPS]))
+    my_contract_dict.update(zip(COMPARISONS,
+                                [_compare for _ in COMPARISONS]))
     my_contract_dict["pow"] = _pow
     my_contract_dict["powi"] = _powi
+    my_contract_dict["ite"] = _ite
     my_contract_dict["sub2"] = _sub2
     my_contract_dict["Return"] = _return
 
@@ -103,7 +127,7 @@ def main(argv):
 
         tokens = function_to_lexed(data)
         tree = lexed_to_parsed(tokens)
-        exp, inputs = pass_lift_inputs_and_inline_assigns(tree)
+        exp, inputs, params = pass_lift_inputs_and_inline_assigns(tree)
         exp = pass_simplify(exp, inputs)
         d, diff_exp = pass_reverse_diff(exp, inputs)
         diff_exp = pass_simplify(diff_exp, inputs)
@@ -121,7 +145,7 @@ def main(argv):
         logger("expression:")
         logger("  {}", diff_exp)
 
-        interp_function = output_interp(exp, inputs, consts)
+        interp_function = output_interp(exp, inputs, consts, params)
 
         logger("interp_function: \n{}", interp_function)
 
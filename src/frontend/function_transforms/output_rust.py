@@ -3,7 +3,7 @@
 import sys
 
 from expression_walker import walk
-from pass_utils import INFIX, BINOPS, UNOPS
+from pass_utils import INFIX, BINOPS, UNOPS, COMPARISONS
 try:
     import gelpia_logging as logging
     import color_printing as color
@@ -15,7 +15,7 @@ logger = logging.make_module_logger(color.cyan("output_rust"),
                                     logging.HIGH)
 
 
-def output_rust(exp, inputs, consts, assigns):
+def output_rust(exp, inputs, consts, assigns, params):
 
     DIFF_DECL = [
         "extern crate gr;\n"
@@ -24,7 +24,7 @@ def output_rust(exp, inputs, consts, assigns):
         "#[allow(unused_parens)]\n"
         "#[no_mangle]\n"
         "pub extern \"C\"\n"
-        "fn gelpia_func(_x: &Vec<GI>, _c: &Vec<GI>) -> (GI, Option<Vec<GI>>) {\n"
+        "fn gelpia_func(_x: &Vec<GI>, _c: &Vec<GI>, _v: &Vec<GI>) -> (GI, Option<Vec<GI>>) {\n"
     ]
     DECL = [
         "extern crate gr;\n"
@@ -32,13 +32,20 @@ def output_rust(exp, inputs, consts, assigns):
         "\n"
         "#[no_mangle]\n"
         "pub extern \"C\"\n"
-        "fn gelpia_func(_x: &Vec<GI>, _c: &Vec<GI>) -> (GI) {\n"
+        "fn gelpia_func(_x: &Vec<GI>, _c: &Vec<GI>, _v: &Vec<GI>) -> (GI) {\n"
     ]
     START = DECL
     input_mapping = {name: str(i) for name, i in zip(inputs, range(len(inputs)))}
     const_mapping = {name: str(i) for name, i in zip(consts, range(len(consts)))}
+    param_mapping = {name: str(i) for name, i in zip(params, range(len(params)))}
     seen_assigns = set()
     body = list()
+    # Whether the body being emitted evaluates a batch of _n points, each
+    # operation then loops over them and yields a vector
+    batch = False
+
+    def _ref(name):
+        return [name, "[_j]"] if batch else [name]
 
     def _e_variable(work_stack, count, exp):
         assert(logger("expand_variable: {}", exp))
@@ -46,7 +53,7 @@ def output_rust(exp, inputs, consts, assigns):
         assert(len(exp) == 2)
         assert(exp[1] in assigns)
         if exp[1] in seen_assigns:
-            work_stack.append((True, count, [exp[1]]))
+            work_stack.append((True, count, _ref(exp[1])))
             return
         seen_assigns.add(exp[1])
         work_stack.append((True,  count, exp[0]))
@@ -58,7 +65,10 @@ def output_rust(exp, inputs, consts, assigns):
         assert(exp[0] == "Input")
         assert(len(exp) == 2)
         index = input_mapping[exp[1]]
-        work_stack.append((True, count, ["_x[", index, "]"]))
+        if batch:
+            work_stack.append((True, count, ["_xs[", index, "][_j]"]))
+        else:
+            work_stack.append((True, count, ["_x[", index, "]"]))
 
     def _const(work_stack, count, exp):
         assert(logger("expand_const: {}", exp))
@@ -67,8 +77,16 @@ def output_rust(exp, inputs, consts, assigns):
         index = const_mapping[exp[1]]
         work_stack.append((True, count, ["_c[", index, "]"]))
 
+    def _param(work_stack, count, exp):
+        assert(logger("expand_param: {}", exp))
+        assert(exp[0] == "Param")
+        assert(len(exp) == 2)
+        index = param_mapping[exp[1]]
+        work_stack.append((True, count, ["_v[", index, "]"]))
+
     my_expand_dict = {"Input": _input,
                       "Const": _const,
+                      "Param": _param,
                       "Variable": _e_variable}
 
     def _c_variable(work_stack, count, args):
@@ -78,8 +96,12 @@ def output_rust(exp, inputs, consts, assigns):
         assert(len(args) == 3)
         name = args[1]
         value = args[2]
-        body += ["    let ", name, " = "] + value + [";\n"]
-        work_stack.append((True, count, [name]))
+        if batch:
+            body += (["    let ", name, ": Vec<GI> = (0.._n).map(|_j| "] + value
+                     + [").collect();\n"])
+        else:
+            body += ["    let ", name, " = "] + value + [";\n"]
+        work_stack.append((True, count, _ref(name)))
 
     def _infix(work_stack, count, args):
         assert(logger("infix: {}", args))
@@ -100,6 +122,24 @@ def output_rust(exp, inputs, consts, assigns):
         ret = [op, "("] + first + [", "] + secon + [")"]
         work_stack.append((True, count, ret))
 
+    COMPARE_NAMES = {"<": "lt", "<=": "le", ">": "gt", ">=": "ge"}
+
+    def _compare(work_stack, count, args):
+        assert(logger("compare: {}", args))
+        assert(args[0] in COMPARISONS)
+        assert(len(args) == 3)
+        op = COMPARE_NAMES[args[0]]
+        ret = [op, "("] + args[1] + [", "] + args[2] + [")"]
+        work_stack.append((True, count, ret))
+
+    def _ite(work_stack, count, args):
+        assert(logger("ite: {}", args))
+        assert(args[0] == "ite")
+        assert(len(args) == 4)
+        ret = (["ite("] + args[1] + [", "] + args[2] + [", "] + args[3]
+               + [")"])
+        work_stack.append((True, count, ret))
+
     def _pow(work_stack, count, args):
         assert(logger("pow: {}", args))
         assert(args[0] == "pow")
@@ -143,6 +183,8 @@ def output_rust(exp, inputs, consts, assigns):
         assert(logger("Return: {}", args))
         assert(args[0] == "Return")
         assert(len(args) == 2)
+        if batch:
+            return ["    _out.extend((0.._n).map(|_j| "] + args[1] + ["));"]
         return ["    "] + args[1]
 
     def _neg(work_stack, count, args):
@@ -158,16 +200,73 @@ def output_rust(exp, inputs, consts, assigns):
                                 [_unop for _ in UNOPS]))
     my_contract_dict.update(zip(INFIX,
                                 [_infix for _ in INFIX]))
+    my_contract_dict.update(zip(COMPARISONS,
+                                [_compare for _ in COMPARISONS]))
     my_contract_dict["pow"] = _pow
+    my_contract_dict["ite"] = _ite
     my_contract_dict["Variable"] = _c_variable
     my_contract_dict["Box"] = _box
     my_contract_dict["neg"] = _neg
     my_contract_dict["Tuple"] = _tuple
     my_contract_dict["Return"] = _return
 
-    retval = walk(my_expand_dict, my_contract_dict, exp, assigns)
+    def _emit(exp, in_batch):
+        nonlocal body, batch
+        seen_assigns.clear()
+        body = list()
+        batch = in_batch
+        retval = walk(my_expand_dict, my_contract_dict, exp, assigns)
+        return body, retval
+
+    start_body, retval = _emit(exp, False)
+
+    # The batch and point entry points only need the value, not the gradient
+    assert(exp[0] == "Return")
+    value = exp[1][1] if exp[1][0] == "Tuple" else exp[1]
+    point_body, point_retval = _emit(("Return", value), False)
+    batch_body, batch_retval = _emit(("Return", value), True)
+
+    # Batch entry point, evaluates _n points given in structure of arrays
+    # layout one operation at a time over all of them.
+    BATCH = [
+        "\n"
+        "\n"
+        "#[allow(unused_parens)]\n"
+        "#[no_mangle]\n"
+        "pub extern \"C\"\n"
+        "fn gelpia_func_batch(_n: usize, _xs: &Vec<Vec<GI>>, _c: &Vec<GI>, _v: &Vec<GI>,\n"
+        "                     _out: &mut Vec<GI>) {\n"
+    ] + batch_body + batch_retval + ["\n}\n"]
+
+    # Point entry point, the value evaluated with error bounded points from
+    # gr::point, returning a rigorous lower bound for each point.
+    POINT = [
+        "\n"
+        "#[allow(unused_parens, unused_variables)]\n"
+        "mod point {\n"
+        "use gr::point::*;\n"
+        "\n"
+        "pub fn gelpia_func(_x: &Vec<EP>, _c: &Vec<EP>, _v: &Vec<EP>) -> EP {\n"
+    ] + point_body + point_retval + [
+        "\n}\n"
+        "}\n"
+        "\n"
+        "#[no_mangle]\n"
+        "pub extern \"C\"\n"
+        "fn gelpia_func_point_batch(_n: usize, _xs: &Vec<Vec<f64>>, _c: &Vec<GI>, _v: &Vec<GI>,\n"
+        "                           _out: &mut Vec<f64>) {\n"
+        "    let _pc = _c.iter().map(|&_ci| gr::point::EP::from_gi(_ci)).collect();\n"
+        "    let _pv = _v.iter().map(|&_vi| gr::point::EP::from_gi(_vi)).collect();\n"
+        "    let mut _x = Vec::with_capacity(_xs.len());\n"
+        "    for _j in 0.._n {\n"
+        "        _x.clear();\n"
+        "        _x.extend(_xs.iter().map(|_xi| gr::point::EP::new(_xi[_j])));\n"
+        "        _out.push(point::gelpia_func(&_x, &_pc, &_pv).lower());\n"
+        "    }\n"
+        "}\n"
+    ]
 
-    return "".join(START + body + retval + ["\n}"])
+    return "".join(START + start_body + retval + ["\n}"] + BATCH + POINT)
 
 
 def main(argv):
@@ -189,7 +288,7 @@ def main(argv):
 
         tokens = function_to_lexed(data)
         tree = lexed_to_parsed(tokens)
-        exp, inputs = pass_lift_inputs_and_inline_assigns(tree)
+        exp, inputs, params = pass_lift_inputs_and_inline_assigns(tree)
         exp = pass_simplify(exp, inputs)
         d, diff_exp = pass_reverse_diff(exp, inputs)
         diff_exp = pass_simplify(diff_exp, inputs)
@@ -210,7 +309,7 @@ def main(argv):
         logger("expression:")
         logger("  {}", diff_exp)
 
-        rust_function = output_rust(diff_exp, inputs, consts, assigns)
+        rust_function = output_rust(diff_exp, inputs, consts, assigns, params)
 
         logger("rust_function: \n{}", rust_function)
 
@@ -3,7 +3,7 @@
 import sys
 
 from expression_walker import walk
-from pass_utils import BINOPS, UNOPS
+from pass_utils import TERNOPS, BINOPS, UNOPS, COMPARISONS
 try:
     import gelpia_logging as logging
     import color_printing as color
@@ -54,6 +54,7 @@ def pass_lift_consts(exp, inputs):
     my_expand_dict = dict()
     my_expand_dict.update(zip(CONST, [_expand_positive_atom for _ in CONST]))
     my_expand_dict["Input"] = _expand_negative_atom
+    my_expand_dict["Param"] = _expand_negative_atom
 
     def _pow(work_stack, count, args):
         assert(args[0] == "pow")
@@ -98,6 +99,27 @@ def pass_lift_consts(exp, inputs):
 
         work_stack.append((True, count, (op, left, right, status)))
 
+    def _bad_two_item(work_stack, count, args):
+        assert(len(args) == 3)
+        l, left = args[1][-1], args[1][:-1]
+        r, right = args[2][-1], args[2][:-1]
+        op = args[0]
+        if l:
+            left = make_constant(left)
+        if r:
+            right = make_constant(right)
+        work_stack.append((True, count, (op, left, right, False)))
+
+    def _bad_many_item(work_stack, count, args):
+        new = [args[0]]
+        for sub in args[1:]:
+            p, part = sub[-1], sub[:-1]
+            if p:
+                part = make_constant(part)
+            new.append(part)
+        new.append(False)
+        work_stack.append((True, count, tuple(new)))
+
     def _tuple(work_stack, count, args):
         assert(args[0] == "Tuple")
         assert(len(args) == 3)
@@ -157,6 +179,10 @@ def pass_lift_consts(exp, inputs):
                                 [_one_item for _ in UNOPS]))
     my_contract_dict.update(zip(NON_CONST_UNOPS,
                                 [_bad_one_item for _ in NON_CONST_UNOPS]))
+    my_contract_dict.update(zip(COMPARISONS,
+                                [_bad_two_item for _ in COMPARISONS]))
+    my_contract_dict.update(zip(TERNOPS,
+                                [_bad_many_item for _ in TERNOPS]))
     my_contract_dict["Box"] = _box
     my_contract_dict["Tuple"] = _tuple
     my_contract_dict["pow"] = _pow
@@ -16,13 +16,17 @@ logger = logging.make_module_logger(color.cyan("lift_inputs_and_inline_assigns")
 
 
 def pass_lift_inputs_and_inline_assigns(exp):
-    """ Extracts input variables from an expression and inlines assignments"""
+    """
+    Extracts input variables and parameters from an expression and inlines
+    assignments
+    """
 
     # Function local variables
     assigns = dict()         # name -> expression
     used_assigns = set()          # assignments seen in the main exp
     inputs = OrderedDict()  # name -> input range
     used_inputs = set()          # inputs seen in the main exp
+    params = OrderedDict()  # name -> initial value
 
     def _input_interval(work_stack, count, exp):
         assert(exp[0] == "InputInterval")
@@ -42,6 +46,10 @@ def pass_lift_inputs_and_inline_assigns(exp):
             work_stack.append((True, count, ret))
             return
 
+        if exp[1] in params:
+            work_stack.append((True, count, ("Param", exp[1])))
+            return
+
         if exp[1] in assigns:
             assert(exp[1] not in inputs)
             used_assigns.add(exp[1])
@@ -53,19 +61,22 @@ def pass_lift_inputs_and_inline_assigns(exp):
         logger.error("Use of undeclared name: {}", exp[1])
         sys.exit(-1)
 
-    # A leading tuple must be an assign
+    # A leading tuple must be an assign or a parameter declaration
     while type(exp[0]) is tuple:
         assignment = exp[0]
-        assert(assignment[0] == "Assign")
+        assert(assignment[0] in {"Assign", "Param"})
         name = assignment[1]
         assert(name[0] == "Name")
         val = assignment[2]
 
-        if name[1] in inputs or name[1] in assigns:
+        if name[1] in inputs or name[1] in assigns or name[1] in params:
             logger.error("Variable assigned to twice: {}", name[1])
             sys.exit(-1)
 
-        if val[0] == "InputInterval":
+        if assignment[0] == "Param":
+            params[name[1]] = val
+            assert(logger("Found parameter {} = {}", name[1], val))
+        elif val[0] == "InputInterval":
             inputs[name[1]] = val
             assert(logger("Found input {} = {}", name[1], val))
         else:
@@ -80,7 +91,7 @@ def pass_lift_inputs_and_inline_assigns(exp):
 
     new_exp = walk(my_expand_dict, dict(), exp, assigns)
 
-    return new_exp, inputs
+    return new_exp, inputs, params
 
 
 def main(argv):
@@ -99,11 +110,14 @@ def main(argv):
 
         logging.set_log_level(logging.HIGH)
         logger("raw: \n{}\n", data)
-        exp, inputs = pass_lift_inputs_and_inline_assigns(tree)
+        exp, inputs, params = pass_lift_inputs_and_inline_assigns(tree)
 
         logger("inputs:")
         for name, interval in inputs.items():
             logger("  {} = {}", name, interval)
+        logger("params:")
+        for name, value in params.items():
+            logger("  {} = {}", name, value)
         logger("expression:\n{}\n", exp)
 
         return 0
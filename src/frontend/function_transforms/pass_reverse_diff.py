@@ -183,7 +183,8 @@ def pass_reverse_diff(exp, inputs):
 
     def _undiff(work_stack, count, exp):
         nonlocal seen_undiff
-        assert(exp[0] in {"floor_power2", "sym_interval", "sub2", "sub2_I"})
+        assert(exp[0] in {"floor_power2", "sym_interval", "sub2", "sub2_I",
+                          "ite", "<", "<=", ">", ">="})
         seen_undiff = True
         work_stack.append((True, 0, "Return"))
         work_stack.append((True, 1, "Now"))
@@ -202,6 +203,11 @@ def pass_reverse_diff(exp, inputs):
                       "cosh":         _cosh,
                       "exp":          _exp,
                       "floor_power2": _undiff,
+                      "ite":          _undiff,
+                      "<":            _undiff,
+                      "<=":           _undiff,
+                      ">":            _undiff,
+                      ">=":           _undiff,
                       "log":          _log,
                       "neg":          _neg,
                       "pow":          _pow,
@@ -241,7 +247,7 @@ def main(argv):
         logging.set_log_level(logging.NONE)
         tokens = function_to_lexed(data)
         tree = lexed_to_parsed(tokens)
-        exp, inputs = pass_lift_inputs_and_inline_assigns(tree)
+        exp, inputs, params = pass_lift_inputs_and_inline_assigns(tree)
         exp = pass_simplify(exp, inputs)
 
         logging.set_log_level(logging.HIGH)
@@ -602,7 +602,7 @@ def main(argv):
         logging.set_log_level(logging.NONE)
         tokens = function_to_lexed(data)
         tree = lexed_to_parsed(tokens)
-        exp, inputs = pass_lift_inputs_and_inline_assigns(tree)
+        exp, inputs, params = pass_lift_inputs_and_inline_assigns(tree)
 
         logging.set_log_level(logging.HIGH)
         logger("raw: \n{}\n", data)
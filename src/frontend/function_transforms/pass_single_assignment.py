@@ -3,7 +3,7 @@
 import sys
 
 from expression_walker import walk
-from pass_utils import BINOPS, UNOPS
+from pass_utils import TERNOPS, BINOPS, UNOPS
 try:
     import gelpia_logging as logging
     import color_printing as color
@@ -24,6 +24,7 @@ def pass_single_assignment(exp, inputs):
         "Float",
         "Input",
         "Integer",
+        "Param",
     }
     UNCACHED = PASSTHROUGH.union({"Tuple", "Variable"})
 
@@ -55,6 +56,7 @@ def pass_single_assignment(exp, inputs):
         work_stack.append((True, count, tuple(args)))
 
     my_contract_dict = dict()
+    my_contract_dict.update(zip(TERNOPS, [_many_items for _ in TERNOPS]))
     my_contract_dict.update(zip(BINOPS, [_two_items for _ in BINOPS]))
     my_contract_dict.update(zip(UNOPS,  [_one_item for _ in UNOPS]))
     my_contract_dict["Tuple"] = _two_items
@@ -84,7 +86,7 @@ def main(argv):
 
         tokens = function_to_lexed(data)
         tree = lexed_to_parsed(tokens)
-        exp, inputs = pass_lift_inputs_and_inline_assigns(tree)
+        exp, inputs, params = pass_lift_inputs_and_inline_assigns(tree)
         exp = pass_simplify(exp, inputs)
         d, diff_exp = pass_reverse_diff(exp, inputs)
         diff_exp = pass_simplify(diff_exp, inputs)
@@ -5,6 +5,7 @@ import sys
 import function_to_lexed
 
 
+TERNOPS = function_to_lexed.GelpiaLexer.TERNOPS
 BINOPS = function_to_lexed.GelpiaLexer.BINOPS
 UNOPS = function_to_lexed.GelpiaLexer.UNOPS
 SYMBOLIC_CONSTS = function_to_lexed.GelpiaLexer.SYMBOLIC_CONSTS
@@ -14,6 +15,8 @@ UNOPS.update({"dabs", "datanh", "neg"})
 ATOMS = {"ConstantInterval", "Float", "InputInterval", "Integer",
          "SymbolicConst"}
 INFIX = {"+", "-", "*", "/"}
+COMPARISONS = {"<", "<=", ">", ">="}
+BINOPS.update(COMPARISONS)
 ASSOC = {"+", "*"}
 
 
@@ -28,7 +28,7 @@ logger = logging.make_module_logger(color.cyan("process_function"),
 def process_function(data, invert=False):
     tokens = function_to_lexed(data)
     tree = lexed_to_parsed(tokens)
-    exp, inputs = pass_lift_inputs_and_inline_assigns(tree)
+    exp, inputs, params = pass_lift_inputs_and_inline_assigns(tree)
     if invert:
         exp = ("Return", ("neg", exp[1]))
     exp = pass_simplify(exp, inputs)
@@ -36,16 +36,19 @@ def process_function(data, invert=False):
     diff_exp = pass_simplify(diff_exp, inputs)
     c, diff_exp, consts = pass_lift_consts(diff_exp, inputs)
     sa_exp, assigns = pass_single_assignment(diff_exp, inputs)
-    rust_function = output_rust(sa_exp, inputs, consts, assigns)
+    rust_function = output_rust(sa_exp, inputs, consts, assigns, params)
     exp = extract_exp_from_diff(diff_exp)
-    interp_function = output_interp(exp, inputs, consts)
+    interp_function = output_interp(exp, inputs, consts, params)
     flat_consts = collections.OrderedDict()
     for name, const in consts.items():
         flat_consts[name] = output_flatten(("Return", const))
     flat_inputs = collections.OrderedDict()
     for name, input in inputs.items():
         flat_inputs[name] = output_flatten(("Return", input))
-    return flat_inputs, flat_consts, rust_function, interp_function
+    flat_params = collections.OrderedDict()
+    for name, value in params.items():
+        flat_params[name] = output_flatten(("Return", value))
+    return flat_inputs, flat_params, flat_consts, rust_function, interp_function
 
 
 def main(argv):
@@ -54,12 +57,16 @@ def main(argv):
     try:
         from pass_utils import get_runmain_input
         data = get_runmain_input(argv)
-        inputs, consts, rust_function, interp_function = process_function(data)
+        inputs, params, consts, rust_function, interp_function = \
+            process_function(data)
 
         logger("raw: \n{}\n", data)
         logger("inputs:")
         for name, interval in inputs.items():
             logger("  {} = {}", name, interval)
+        logger("params:")
+        for name, value in params.items():
+            logger("  {} = {}", name, value)
         logger("consts:")
         for name, val in consts.items():
             logger("  {} = {}", name, val)
@@ -6,9 +6,9 @@ import gelpia_logging as logging
 import ian_utils as iu
 from process_function import process_function
 
+import json
 import os
 import os.path as path
-import re
 import sys
 import time
 from multiprocessing import Process, Value
@@ -112,7 +112,7 @@ def write_rust_function(rust_function, src_dir):
     return file_id
 
 
-def _find_max(inputs, consts, rust_function,
+def _find_max(inputs, params, consts, rust_function,
               interp_function, file_id, epsilons, timeout,
               grace, update, iters, seed, debug, src_dir,
               executable):
@@ -131,6 +131,9 @@ def _find_max(inputs, consts, rust_function,
                        "--seed", str(seed),
                        "-d" if debug else "",
                        "-L" if logging.get_log_level() >= logging.HIGH else ""]
+    if len(params) != 0:
+        executable_args += ["-p", "|".join(params.values()),
+                            "-P", ",".join(params.keys())]
 
     assert(logger(logging.MEDIUM, "calling '{} {}'", executable, executable_args))
     answer_lines = []
@@ -142,10 +145,13 @@ def _find_max(inputs, consts, rust_function,
         timeout += grace
     for line in iu.run_async(executable, executable_args, timeout):
         logger(logging.HIGH, "rust_solver_output: '{}'", line)
-        if line.startswith("lb:"):
-            match = re.match(r"lb: ([^,]*), possible ub: ([^,]*), guaranteed ub: ([^,]*)", line)
-            max_lower = match.groups(1)
-            max_upper = match.groups(3)
+        if line.startswith('{"'):
+            # Progress events from -L, null bounds are infinite
+            event = json.loads(line)
+            if event["lower"] is not None:
+                max_lower = event["lower"]
+            if event["upper"] is not None:
+                max_upper = event["upper"]
         else:
             answer_lines.append(line.strip())
 
@@ -191,10 +197,10 @@ def _find_max(inputs, consts, rust_function,
 
 def find_max(function, epsilons, timeout, grace, update, iters, seed, debug,
              src_dir, executable, max_lower=None, max_upper=None):
-    inputs, consts, rust_function, interp_function = process_function(function)
+    inputs, params, consts, rust_function, interp_function = process_function(function)
     file_id = write_rust_function(rust_function, src_dir)
 
-    my_max_lower, my_max_upper, domain = _find_max(inputs, consts, rust_function,
+    my_max_lower, my_max_upper, domain = _find_max(inputs, params, consts, rust_function,
                                                    interp_function, file_id, epsilons, timeout,
                                                    grace, update, iters, seed, debug, src_dir,
                                                    executable)
@@ -207,10 +213,11 @@ def find_max(function, epsilons, timeout, grace, update, iters, seed, debug,
 
 def find_min(function, epsilons, timeout, grace, update, iters, seed, debug,
              src_dir, executable):
-    inputs, consts, rust_function, interp_function = process_function(function, invert=True)
+    inputs, params, consts, rust_function, interp_function = \
+        process_function(function, invert=True)
     file_id = write_rust_function(rust_function, src_dir)
 
-    max_lower, max_upper, domain = _find_max(inputs, consts, rust_function,
+    max_lower, max_upper, domain = _find_max(inputs, params, consts, rust_function,
                                              interp_function, file_id, epsilons, timeout,
                                              grace, update, iters, seed, debug, src_dir,
                                              executable)
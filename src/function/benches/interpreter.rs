@@ -0,0 +1,49 @@
+// Compares the register based interpreter used by FuncObj::call against the
+// original stack interpreter on the same tape, and shows what the tape
+// optimizations add on top. Run with `cargo bench --features bench` from
+// src/function.
+extern crate function;
+use function::{FuncObj, CompilePolicy};
+
+extern crate gr;
+use gr::GI;
+
+use std::time::Instant;
+
+const CALLS: u32 = 1000000;
+
+fn time_calls<F: Fn() -> GI>(f: F) -> (f64, GI) {
+    let mut last = f();
+    let start = Instant::now();
+    for _ in 0..CALLS {
+        last = f();
+    }
+    let elapsed = start.elapsed();
+    let nanos = elapsed.as_secs() as f64 * 1e9 + elapsed.subsec_nanos() as f64;
+    (nanos / CALLS as f64, last)
+}
+
+fn main() {
+    // Shekel style term plus some transcendental noise over three inputs.
+    let consts = vec![GI::new_c("4").unwrap(), GI::new_c("0.1").unwrap(),
+                      GI::new_c("1").unwrap(), GI::new_c("[0.5, 0.5]").unwrap()];
+    let tape = "i0, c0, o-, p2, i1, c0, o-, p2, o+, i2, c0, o-, p2, o+, c1, o+, \
+                c2, c3, o/, o/, i0, i1, o*, fsin, o+, i2, fexp, fneg, o+".to_string();
+    let f = FuncObj::new(&consts, &vec![], &tape, 3, false, "bench".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+
+    let boxes = vec![GI::new_d(0.0, 10.0), GI::new_d(3.0, 4.0), GI::new_d(-1.0, 1.0)];
+    let points = vec![GI::new_p(1.5), GI::new_p(3.25), GI::new_p(0.5)];
+
+    let g = f.unoptimized();
+    for &(name, ref x) in [("box", &boxes), ("point", &points)].iter() {
+        let (reference, r_val) = time_calls(|| f.call_reference(x));
+        let (register, v_val) = time_calls(|| g.call(x).0);
+        let (optimized, _) = time_calls(|| f.call(x).0);
+        assert!(r_val.to_string() == v_val.to_string());
+        println!("{:>5}: stack {:8.1} ns/call, register {:8.1} ns/call, speedup {:.2}x, \
+                  optimized {:8.1} ns/call, speedup {:.2}x",
+                 name, reference, register, reference / register,
+                 optimized, reference / optimized);
+    }
+}
@@ -0,0 +1,122 @@
+// Tapes as directed acyclic graphs.
+//
+// A tape is read into a DAG where structurally equal subterms are the same
+// node, then written back out. Every operation used more than once is computed
+// the first time it is needed, kept with "s<n>" and reused with "l<n>", so it
+// is evaluated once per call. Constants, inputs and user variables are cheap to
+// push and are never stored.
+
+use std::collections::HashMap;
+
+use super::OpType;
+
+pub struct Node {
+    pub op: OpType,
+    pub args: Vec<usize>,
+}
+
+pub struct Dag {
+    pub nodes: Vec<Node>,
+    seen: HashMap<(String, Vec<usize>), usize>,
+}
+
+impl Dag {
+    pub fn new() -> Dag {
+        Dag{nodes: Vec::new(), seen: HashMap::new()}
+    }
+
+    // Returns the node applying op to args, reusing an equal node if there is
+    // one.
+    pub fn node(&mut self, op: OpType, args: Vec<usize>) -> usize {
+        let key = (op.to_string(), args);
+        if let Some(&id) = self.seen.get(&key) {
+            return id;
+        }
+        let id = self.nodes.len();
+        self.nodes.push(Node{op: op, args: key.1.clone()});
+        self.seen.insert(key, id);
+        id
+    }
+
+    // Reads a verified tape and returns the root node. Each operation is built
+    // by make from the operation and the nodes of its operands, local slots
+    // used by the tape are resolved to the nodes they hold.
+    pub fn read<F>(&mut self, tape: &Vec<OpType>, mut make: F) -> usize
+        where F: FnMut(&mut Dag, &OpType, Vec<usize>) -> usize {
+        let mut stack: Vec<usize> = Vec::new();
+        let mut locals: Vec<usize> = Vec::new();
+
+        for op in tape {
+            let arity = match op {
+                &OpType::Const(_) | &OpType::Var(_) | &OpType::UVar(_) => 0,
+                &OpType::Func(_) | &OpType::Pow(_) => 1,
+                &OpType::Op(ref s) if s == "ite" => 3,
+                &OpType::Op(_) => 2,
+                &OpType::Store(i) => {
+                    if locals.len() <= i {
+                        locals.resize(i + 1, 0);
+                    }
+                    locals[i] = *stack.last().unwrap();
+                    continue;
+                },
+                &OpType::Load(i) => {
+                    stack.push(locals[i]);
+                    continue;
+                },
+            };
+            let at = stack.len() - arity;
+            let args = stack.split_off(at);
+            let id = make(self, op, args);
+            stack.push(id);
+        }
+        stack[0]
+    }
+
+    // Writes the nodes reachable from root back out as a tape, operands before
+    // the operation using them. Operations used more than once are stored the
+    // first time and loaded afterwards.
+    pub fn write(&self, root: usize) -> Vec<OpType> {
+        let mut uses = vec![0; self.nodes.len()];
+        let mut visited = vec![false; self.nodes.len()];
+        let mut todo = vec![root];
+        visited[root] = true;
+        while let Some(id) = todo.pop() {
+            for &a in &self.nodes[id].args {
+                uses[a] += 1;
+                if !visited[a] {
+                    visited[a] = true;
+                    todo.push(a);
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        let mut slot: Vec<Option<usize>> = vec![None; self.nodes.len()];
+        let mut n_stored = 0;
+        let mut work = vec![(root, false)];
+        while let Some((id, expanded)) = work.pop() {
+            let node = &self.nodes[id];
+            if let Some(k) = slot[id] {
+                out.push(OpType::Load(k));
+            }
+            else if node.args.is_empty() {
+                out.push(node.op.clone());
+            }
+            else if !expanded {
+                work.push((id, true));
+                for &a in node.args.iter().rev() {
+                    work.push((a, false));
+                }
+            }
+            else {
+                out.push(node.op.clone());
+                if uses[id] > 1 {
+                    out.push(OpType::Store(n_stored));
+                    slot[id] = Some(n_stored);
+                    n_stored += 1;
+                }
+            }
+        }
+        out
+    }
+}
@@ -0,0 +1,539 @@
+// Versioned on-disk format for complete function definitions.
+//
+// A definition holds the instruction tape together with everything needed to
+// rebuild a FuncObj and its search domain: constants, user variables, input
+// names, domains and kinds, constraints and equalities, plus free-form
+// metadata. Interval endpoints are stored as the bit patterns of their f64
+// values so that nothing is lost to decimal printing and parsing.
+//
+// Two encodings are supported. JSON is meant to be read and written by other
+// tools, the binary encoding is compact and starts with the bytes "GLPF".
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Read, Write};
+
+use serde_json;
+use serde_json::{Value, Map};
+
+use gr::{GI, InputKind, kind_of};
+
+use super::{FuncObj, CompilePolicy};
+
+pub const FORMAT_NAME: &'static str = "gelpia-function";
+// Version 2 added constraints, version 3 equalities and version 4 input kinds,
+// definitions of older versions are still read.
+pub const FORMAT_VERSION: u64 = 4;
+const MAGIC: &'static [u8; 4] = b"GLPF";
+
+#[derive(Clone)]
+pub struct FuncDef {
+    // Comma separated instruction tape, see FunctionInterpreter.md
+    pub instructions: String,
+    pub constants: Vec<GI>,
+    pub names: Vec<String>,
+    pub domain: Vec<GI>,
+    // The kind of every input, empty when all of them are real.
+    pub kinds: Vec<InputKind>,
+    pub param_names: Vec<String>,
+    pub params: Vec<GI>,
+    // Tapes of the constraints g(x) <= 0, over the same constants, inputs and
+    // parameters as the function.
+    pub constraints: Vec<String>,
+    // Tapes of the equalities h(x) = 0, like the constraints.
+    pub equalities: Vec<String>,
+    pub metadata: BTreeMap<String, String>,
+}
+
+pub fn bits_to_hex(x: f64) -> String {
+    format!("{:016x}", x.to_bits())
+}
+
+pub fn hex_to_bits(s: &str) -> Result<f64, String> {
+    u64::from_str_radix(s, 16)
+        .map(f64::from_bits)
+        .map_err(|_| format!("Expected 16 hex digits for a float, got '{}'", s))
+}
+
+pub fn interval_to_json(x: &GI) -> Value {
+    json!({"lower": bits_to_hex(x.lower()), "upper": bits_to_hex(x.upper())})
+}
+
+pub fn interval_from_json(v: &Value, what: &str) -> Result<GI, String> {
+    let field = |name: &str| -> Result<f64, String> {
+        match v.get(name).and_then(|f| f.as_str()) {
+            Some(s) => hex_to_bits(s),
+            None => Err(format!("{}: missing '{}'", what, name)),
+        }
+    };
+    Ok(GI::new_d(field("lower")?, field("upper")?))
+}
+
+// Only discrete inputs record their kind.
+pub fn kind_to_json(input: &mut Value, kind: &InputKind) {
+    let input = input.as_object_mut().unwrap();
+    match *kind {
+        InputKind::Real => (),
+        InputKind::Integer => {
+            input.insert("kind".to_string(), json!("integer"));
+        }
+        InputKind::Values(ref vs) => {
+            input.insert("kind".to_string(), json!("values"));
+            input.insert("values".to_string(),
+                         json!(vs.iter().map(|&v| bits_to_hex(v)).collect::<Vec<_>>()));
+        }
+    }
+}
+
+fn kind_from_json(v: &Value, what: &str) -> Result<InputKind, String> {
+    match v.get("kind").map(|k| k.as_str()) {
+        None | Some(Some("real")) => Ok(InputKind::Real),
+        Some(Some("integer")) => Ok(InputKind::Integer),
+        Some(Some("values")) => {
+            let mut vs = vec![];
+            for x in array(v, "values").map_err(|msg| format!("{}: {}", what, msg))? {
+                match x.as_str() {
+                    Some(s) => vs.push(hex_to_bits(s)?),
+                    None => return Err(format!("{}: expected hex strings as values",
+                                               what)),
+                }
+            }
+            InputKind::values(vs).map_err(|msg| format!("{}: {}", what, msg))
+        }
+        _ => Err(format!("{}: unknown kind, expected real, integer or values", what)),
+    }
+}
+
+fn array<'a>(v: &'a Value, name: &str) -> Result<&'a Vec<Value>, String> {
+    match v.get(name) {
+        Some(a) => a.as_array().ok_or(format!("'{}' must be an array", name)),
+        None => Err(format!("Missing '{}'", name)),
+    }
+}
+
+fn string(v: &Value, name: &str, what: &str) -> Result<String, String> {
+    match v.get(name).and_then(|s| s.as_str()) {
+        Some(s) => Ok(s.to_string()),
+        None => Err(format!("{}: missing string '{}'", what, name)),
+    }
+}
+
+// Little endian writers and a cursor reader for the binary encoding.
+pub fn put_u32(out: &mut Vec<u8>, x: u32) {
+    for i in 0..4 {
+        out.push((x >> (8*i)) as u8);
+    }
+}
+
+pub fn put_f64(out: &mut Vec<u8>, x: f64) {
+    let bits = x.to_bits();
+    for i in 0..8 {
+        out.push((bits >> (8*i)) as u8);
+    }
+}
+
+pub fn put_str(out: &mut Vec<u8>, s: &str) {
+    put_u32(out, s.len() as u32);
+    out.extend_from_slice(s.as_bytes());
+}
+
+pub fn put_interval(out: &mut Vec<u8>, x: &GI) {
+    put_f64(out, x.lower());
+    put_f64(out, x.upper());
+}
+
+// A kind is a tag, 0 for real, 1 for integer and 2 for enumerated inputs, the
+// last followed by the values.
+fn put_kind(out: &mut Vec<u8>, kind: &InputKind) {
+    match *kind {
+        InputKind::Real => put_u32(out, 0),
+        InputKind::Integer => put_u32(out, 1),
+        InputKind::Values(ref vs) => {
+            put_u32(out, 2);
+            put_u32(out, vs.len() as u32);
+            for &v in vs {
+                put_f64(out, v);
+            }
+        }
+    }
+}
+
+// Reads the values written by the put functions in order.
+pub struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader{data: data, pos: 0}
+    }
+
+    // Number of bytes read so far.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    pub fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.data.len() - self.pos < n {
+            return Err(format!("Unexpected end of data at byte {}", self.pos));
+        }
+        let s = &self.data[self.pos..self.pos + n];
+        self.pos += n;
+        Ok(s)
+    }
+
+    pub fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok((0..4).fold(0, |acc, i| acc | (b[i] as u32) << (8*i)))
+    }
+
+    pub fn f64(&mut self) -> Result<f64, String> {
+        let b = self.take(8)?;
+        Ok(f64::from_bits((0..8).fold(0, |acc, i| acc | (b[i] as u64) << (8*i))))
+    }
+
+    pub fn string(&mut self) -> Result<String, String> {
+        let n = self.u32()? as usize;
+        let pos = self.pos;
+        String::from_utf8(self.take(n)?.to_vec())
+            .map_err(|_| format!("Invalid UTF-8 string at byte {}", pos))
+    }
+
+    pub fn interval(&mut self) -> Result<GI, String> {
+        let lower = self.f64()?;
+        Ok(GI::new_d(lower, self.f64()?))
+    }
+
+    fn kind(&mut self) -> Result<InputKind, String> {
+        let pos = self.pos;
+        match self.u32()? {
+            0 => Ok(InputKind::Real),
+            1 => Ok(InputKind::Integer),
+            2 => {
+                let mut vs = vec![];
+                for _ in 0..self.u32()? {
+                    vs.push(self.f64()?);
+                }
+                InputKind::values(vs).map_err(|msg| format!("At byte {}: {}", pos, msg))
+            }
+            t => Err(format!("Unknown input kind {} at byte {}", t, pos)),
+        }
+    }
+}
+
+// Definitions without discrete inputs keep no kinds.
+fn real_if_all(kinds: Vec<InputKind>) -> Vec<InputKind> {
+    if kinds.iter().any(|k| k.is_discrete()) { kinds } else { vec![] }
+}
+
+impl FuncDef {
+    // Checks that the parts of the definition agree with each other and that
+    // the tape is valid for them.
+    pub fn check(&self) -> Result<(), String> {
+        if self.names.len() != self.domain.len() {
+            return Err(format!("Got {} input names for {} input domains",
+                               self.names.len(), self.domain.len()));
+        }
+        if !self.kinds.is_empty() && self.kinds.len() != self.domain.len() {
+            return Err(format!("Got {} input kinds for {} inputs",
+                               self.kinds.len(), self.domain.len()));
+        }
+        if self.param_names.len() != self.params.len() {
+            return Err(format!("Got {} parameter names for {} parameters",
+                               self.param_names.len(), self.params.len()));
+        }
+        let insts = super::parse_instructions(&self.instructions)?;
+        super::verify(&insts, self.constants.len(), self.domain.len(),
+                      self.params.len())?;
+        let tapes = self.constraints.iter().enumerate()
+            .map(|(i, c)| (format!("Constraint {}", i), c))
+            .chain(self.equalities.iter().enumerate()
+                   .map(|(i, h)| (format!("Equality {}", i), h)));
+        for (what, tape) in tapes {
+            let insts = super::parse_instructions(tape)
+                .map_err(|msg| format!("{}: {}", what, msg))?;
+            super::verify(&insts, self.constants.len(), self.domain.len(),
+                          self.params.len())
+                .map_err(|msg| format!("{}: {}", what, msg))?;
+        }
+        Ok(())
+    }
+
+    // Builds the function described by this definition.
+    pub fn build(&self, debug: bool, suffix: String, policy: CompilePolicy)
+                 -> Result<FuncObj, String> {
+        self.check()?;
+        let mut f = FuncObj::new(&self.constants, &self.params, &self.instructions,
+                                 self.domain.len(), debug, suffix, policy)?;
+        for c in &self.constraints {
+            f.add_constraint(c, self.domain.len())?;
+        }
+        for h in &self.equalities {
+            f.add_equality(h, self.domain.len())?;
+        }
+        f.set_input_kinds(self.kinds.clone());
+        Ok(f)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_value()).unwrap()
+    }
+
+    // The JSON document of to_json, for embedding a definition in other
+    // documents.
+    pub fn to_value(&self) -> Value {
+        let tape = |t: &String| -> Vec<Value> {
+            t.split(',').map(|i| Value::String(i.trim().to_string())).collect()
+        };
+        let insts = tape(&self.instructions);
+        let inputs: Vec<Value> = self.names.iter().zip(self.domain.iter()).enumerate()
+            .map(|(i, (n, d))| {
+                let mut input = json!({"name": n, "domain": interval_to_json(d)});
+                kind_to_json(&mut input, kind_of(&self.kinds, i));
+                input
+            })
+            .collect();
+        let params: Vec<Value> = self.param_names.iter().zip(self.params.iter())
+            .map(|(n, p)| json!({"name": n, "value": interval_to_json(p)}))
+            .collect();
+        let mut metadata = Map::new();
+        for (k, v) in &self.metadata {
+            metadata.insert(k.clone(), Value::String(v.clone()));
+        }
+        json!({
+            "format": FORMAT_NAME,
+            "version": FORMAT_VERSION,
+            "instructions": insts,
+            "constants": self.constants.iter().map(interval_to_json)
+                .collect::<Vec<_>>(),
+            "inputs": inputs,
+            "params": params,
+            "constraints": self.constraints.iter().map(&tape).collect::<Vec<_>>(),
+            "equalities": self.equalities.iter().map(&tape).collect::<Vec<_>>(),
+            "metadata": metadata,
+        })
+    }
+
+    pub fn from_json(text: &str) -> Result<FuncDef, String> {
+        let doc: Value = serde_json::from_str(text)
+            .map_err(|e| format!("Invalid JSON: {}", e))?;
+        FuncDef::from_value(&doc)
+    }
+
+    // Reads a definition from the document of to_value.
+    pub fn from_value(doc: &Value) -> Result<FuncDef, String> {
+        if doc.get("format").and_then(|f| f.as_str()) != Some(FORMAT_NAME) {
+            return Err(format!("Not a {} document", FORMAT_NAME));
+        }
+        match doc.get("version").and_then(|v| v.as_u64()) {
+            Some(v) if 1 <= v && v <= FORMAT_VERSION => (),
+            Some(v) => return Err(format!("Unsupported format version {}, \
+                                           expected {}", v, FORMAT_VERSION)),
+            None => return Err("Missing format version".to_string()),
+        }
+
+        let tape = |a: &Vec<Value>, what: &str| -> Result<String, String> {
+            let mut insts = vec![];
+            for (pos, i) in a.iter().enumerate() {
+                match i.as_str() {
+                    Some(s) => insts.push(s.to_string()),
+                    None => return Err(format!("{}Instruction {}: expected a string",
+                                               what, pos)),
+                }
+            }
+            Ok(insts.join(","))
+        };
+        let instructions = tape(array(doc, "instructions")?, "")?;
+        let mut constants = vec![];
+        for (pos, c) in array(doc, "constants")?.iter().enumerate() {
+            constants.push(interval_from_json(c, &format!("Constant {}", pos))?);
+        }
+        let mut names = vec![];
+        let mut domain = vec![];
+        let mut kinds = vec![];
+        for (pos, i) in array(doc, "inputs")?.iter().enumerate() {
+            let what = format!("Input {}", pos);
+            names.push(string(i, "name", &what)?);
+            match i.get("domain") {
+                Some(d) => domain.push(interval_from_json(d, &what)?),
+                None => return Err(format!("{}: missing 'domain'", what)),
+            }
+            kinds.push(kind_from_json(i, &what)?);
+        }
+        let mut param_names = vec![];
+        let mut params = vec![];
+        if doc.get("params").is_some() {
+            for (pos, p) in array(doc, "params")?.iter().enumerate() {
+                let what = format!("Parameter {}", pos);
+                param_names.push(string(p, "name", &what)?);
+                match p.get("value") {
+                    Some(v) => params.push(interval_from_json(v, &what)?),
+                    None => return Err(format!("{}: missing 'value'", what)),
+                }
+            }
+        }
+        let tapes = |name: &str, what: &str| -> Result<Vec<String>, String> {
+            let mut out = vec![];
+            if doc.get(name).is_some() {
+                for (pos, c) in array(doc, name)?.iter().enumerate() {
+                    match c.as_array() {
+                        Some(a) => out.push(tape(a, &format!("{} {}: ", what, pos))?),
+                        None => return Err(format!("{} {}: expected an array",
+                                                   what, pos)),
+                    }
+                }
+            }
+            Ok(out)
+        };
+        let constraints = tapes("constraints", "Constraint")?;
+        let equalities = tapes("equalities", "Equality")?;
+        let mut metadata = BTreeMap::new();
+        if let Some(m) = doc.get("metadata") {
+            let m = m.as_object().ok_or("'metadata' must be an object")?;
+            for (k, v) in m {
+                match v.as_str() {
+                    Some(s) => { metadata.insert(k.clone(), s.to_string()); },
+                    None => return Err(format!("Metadata '{}' must be a string", k)),
+                }
+            }
+        }
+
+        let def = FuncDef{instructions: instructions, constants: constants,
+                          names: names, domain: domain, kinds: real_if_all(kinds),
+                          param_names: param_names, params: params,
+                          constraints: constraints, equalities: equalities,
+                          metadata: metadata};
+        def.check()?;
+        Ok(def)
+    }
+
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        put_u32(&mut out, FORMAT_VERSION as u32);
+        put_str(&mut out, &self.instructions);
+        put_u32(&mut out, self.constants.len() as u32);
+        for c in &self.constants {
+            put_interval(&mut out, c);
+        }
+        put_u32(&mut out, self.names.len() as u32);
+        for (i, (n, d)) in self.names.iter().zip(self.domain.iter()).enumerate() {
+            put_str(&mut out, n);
+            put_interval(&mut out, d);
+            put_kind(&mut out, kind_of(&self.kinds, i));
+        }
+        put_u32(&mut out, self.params.len() as u32);
+        for (n, p) in self.param_names.iter().zip(self.params.iter()) {
+            put_str(&mut out, n);
+            put_interval(&mut out, p);
+        }
+        put_u32(&mut out, self.constraints.len() as u32);
+        for c in &self.constraints {
+            put_str(&mut out, c);
+        }
+        put_u32(&mut out, self.equalities.len() as u32);
+        for h in &self.equalities {
+            put_str(&mut out, h);
+        }
+        put_u32(&mut out, self.metadata.len() as u32);
+        for (k, v) in &self.metadata {
+            put_str(&mut out, k);
+            put_str(&mut out, v);
+        }
+        out
+    }
+
+    pub fn from_binary(data: &[u8]) -> Result<FuncDef, String> {
+        let mut r = Reader{data: data, pos: 0};
+        if r.take(4).ok() != Some(&MAGIC[..]) {
+            return Err(format!("Not a binary {} file", FORMAT_NAME));
+        }
+        let version = r.u32()?;
+        if version == 0 || version as u64 > FORMAT_VERSION {
+            return Err(format!("Unsupported format version {}, expected {}",
+                               version, FORMAT_VERSION));
+        }
+        let instructions = r.string()?;
+        let mut constants = vec![];
+        for _ in 0..r.u32()? {
+            constants.push(r.interval()?);
+        }
+        let mut names = vec![];
+        let mut domain = vec![];
+        let mut kinds = vec![];
+        for _ in 0..r.u32()? {
+            names.push(r.string()?);
+            domain.push(r.interval()?);
+            if version >= 4 {
+                kinds.push(r.kind()?);
+            }
+        }
+        let mut param_names = vec![];
+        let mut params = vec![];
+        for _ in 0..r.u32()? {
+            param_names.push(r.string()?);
+            params.push(r.interval()?);
+        }
+        let mut constraints = vec![];
+        if version >= 2 {
+            for _ in 0..r.u32()? {
+                constraints.push(r.string()?);
+            }
+        }
+        let mut equalities = vec![];
+        if version >= 3 {
+            for _ in 0..r.u32()? {
+                equalities.push(r.string()?);
+            }
+        }
+        let mut metadata = BTreeMap::new();
+        for _ in 0..r.u32()? {
+            let k = r.string()?;
+            metadata.insert(k, r.string()?);
+        }
+        if r.pos != data.len() {
+            return Err(format!("{} trailing bytes after definition",
+                               data.len() - r.pos));
+        }
+
+        let def = FuncDef{instructions: instructions, constants: constants,
+                          names: names, domain: domain, kinds: real_if_all(kinds),
+                          param_names: param_names, params: params,
+                          constraints: constraints, equalities: equalities,
+                          metadata: metadata};
+        def.check()?;
+        Ok(def)
+    }
+
+    // Reads a definition, the encoding is detected from the contents.
+    pub fn load(path: &str) -> Result<FuncDef, String> {
+        let mut data = vec![];
+        File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut data))
+            .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        if data.starts_with(MAGIC) {
+            FuncDef::from_binary(&data)
+        }
+        else {
+            match String::from_utf8(data) {
+                Ok(text) => FuncDef::from_json(&text),
+                Err(_) => Err(format!("{} is neither JSON nor a binary {} file",
+                                      path, FORMAT_NAME)),
+            }
+        }
+    }
+
+    // Writes a definition, paths ending in ".json" get the JSON encoding and
+    // everything else the binary one.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let data = if path.ends_with(".json") {
+            self.to_json().into_bytes()
+        } else {
+            self.to_binary()
+        };
+        File::create(path)
+            .and_then(|mut f| f.write_all(&data))
+            .map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+}
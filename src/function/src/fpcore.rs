@@ -0,0 +1,583 @@
+// Reader for FPBench's FPCore format.
+//
+// An FPCore expression is turned into a function definition. The body becomes
+// the instruction tape and the ranges given for each argument in :pre become
+// the input domain. Numbers and named constants are enclosed in intervals, so
+// the tape describes the exact real valued expression. Anything without an
+// interval counterpart is reported with the line it appears on. The expression
+// translation is shared with the SMT-LIB reader.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Read;
+use std::f64::consts;
+
+use gr::GI;
+
+use super::FuncDef;
+use super::sexp;
+use super::sexp::Sexp;
+
+// Functions of one argument that map directly to an "f<name>" instruction.
+const UNARY: [(&'static str, &'static str); 16] =
+    [("fabs", "abs"), ("sqrt", "sqrt"), ("exp", "exp"), ("log", "log"),
+     ("sin", "sin"), ("cos", "cos"), ("tan", "tan"), ("asin", "asin"),
+     ("acos", "acos"), ("atan", "atan"), ("sinh", "sinh"), ("cosh", "cosh"),
+     ("tanh", "tanh"), ("asinh", "asinh"), ("acosh", "acosh"),
+     ("atanh", "atanh")];
+
+// The same for SMT-LIB, which accepts both the cvc5 and the dReal spelling of
+// the inverse trigonometric functions.
+const SMT_UNARY: [(&'static str, &'static str); 16] =
+    [("abs", "abs"), ("sqrt", "sqrt"), ("exp", "exp"), ("log", "log"),
+     ("sin", "sin"), ("cos", "cos"), ("tan", "tan"), ("arcsin", "asin"),
+     ("arccos", "acos"), ("arctan", "atan"), ("asin", "asin"), ("acos", "acos"),
+     ("atan", "atan"), ("sinh", "sinh"), ("cosh", "cosh"), ("tanh", "tanh")];
+
+// The s-expression languages expressions are read from. They share arithmetic,
+// comparisons, boolean connectives and let, but differ in function names,
+// named constants and annotations.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Dialect {
+    FPCore,
+    SmtLib,
+}
+
+// Smallest interval with floating point endpoints around a correctly rounded
+// positive constant.
+fn around(c: f64) -> GI {
+    GI::new_d(f64::from_bits(c.to_bits() - 1), f64::from_bits(c.to_bits() + 1))
+}
+
+fn named_constant(name: &str) -> Option<GI> {
+    Some(match name {
+        "PI" => around(consts::PI),
+        "PI_2" => around(consts::FRAC_PI_2),
+        "PI_4" => around(consts::FRAC_PI_4),
+        "M_1_PI" => around(consts::FRAC_1_PI),
+        "M_2_PI" => around(consts::FRAC_2_PI),
+        "M_2_SQRTPI" => around(consts::FRAC_2_SQRT_PI),
+        "E" => around(consts::E),
+        "LOG2E" => around(consts::LOG2_E),
+        "LOG10E" => around(consts::LOG10_E),
+        "LN2" => around(consts::LN_2),
+        "LN10" => around(consts::LN_10),
+        "SQRT2" => around(consts::SQRT_2),
+        "SQRT1_2" => around(consts::FRAC_1_SQRT_2),
+        "TRUE" => GI::new_p(1.0),
+        "FALSE" => GI::new_p(0.0),
+        _ => return None,
+    })
+}
+
+// Encloses a decimal or rational number, None if s is not a number.
+pub(crate) fn number(s: &str, line: usize) -> Result<Option<GI>, String> {
+    let first = s.chars().next().unwrap_or(' ');
+    let numeric = first.is_digit(10) ||
+        ((first == '-' || first == '+' || first == '.') && s.len() > 1 &&
+         s[1..].chars().next().map_or(false, |c| c.is_digit(10) || c == '.'));
+    if !numeric {
+        return Ok(None);
+    }
+    if s.contains("0x") || s.contains("0X") {
+        return Err(format!("Line {}: hexadecimal number '{}' is not supported",
+                           line, s));
+    }
+    let parse = |t: &str| GI::new_c(t).map_err(
+        |_| format!("Line {}: invalid number '{}'", line, s));
+    let mut parts = s.splitn(2, '/');
+    let num = parse(parts.next().unwrap())?;
+    match parts.next() {
+        Some(den) => Ok(Some(num/parse(den)?)),
+        None => Ok(Some(num)),
+    }
+}
+
+fn arity(line: usize, op: &str, args: &[Sexp], n: usize) -> Result<(), String> {
+    if args.len() != n {
+        return Err(format!("Line {}: '{}' expects {} arguments, got {}",
+                           line, op, n, args.len()));
+    }
+    Ok(())
+}
+
+// Translates expressions into tape instructions, collecting the constants
+// they use.
+pub(crate) struct Builder {
+    pub names: Vec<String>,
+    pub constants: Vec<GI>,
+    pub dialect: Dialect,
+}
+
+impl Builder {
+    // The tape reading constant c, adding it unless an identical one exists.
+    pub fn constant(&mut self, c: GI) -> Vec<String> {
+        let same = |x: &GI| x.lower().to_bits() == c.lower().to_bits() &&
+            x.upper().to_bits() == c.upper().to_bits();
+        let i = match self.constants.iter().position(same) {
+            Some(i) => i,
+            None => {
+                self.constants.push(c);
+                self.constants.len() - 1
+            }
+        };
+        vec![format!("c{}", i)]
+    }
+
+    // Left fold of a binary operator over two or more arguments.
+    fn fold(&mut self, e: &Sexp, op: &str, inst: &str, args: &[Sexp],
+            env: &Vec<(String, Vec<String>)>) -> Result<Vec<String>, String> {
+        if args.len() < 2 {
+            return Err(format!("Line {}: '{}' expects at least 2 arguments, got {}",
+                               e.line(), op, args.len()));
+        }
+        let mut out = self.expr(&args[0], env)?;
+        for a in &args[1..] {
+            out.extend(self.expr(a, env)?);
+            out.push(inst.to_string());
+        }
+        Ok(out)
+    }
+
+    // Tape for ite(c, a, b).
+    fn ite(c: Vec<String>, a: Vec<String>, b: Vec<String>) -> Vec<String> {
+        let mut out = c;
+        out.extend(a);
+        out.extend(b);
+        out.push("oite".to_string());
+        out
+    }
+
+    // A chain of comparisons (< a b c) holds when every adjacent pair does.
+    fn compare(&mut self, e: &Sexp, op: &str, args: &[Sexp],
+               env: &Vec<(String, Vec<String>)>) -> Result<Vec<String>, String> {
+        if args.len() < 2 {
+            return Err(format!("Line {}: '{}' expects at least 2 arguments, got {}",
+                               e.line(), op, args.len()));
+        }
+        let mut out: Option<Vec<String>> = None;
+        for pair in args.windows(2) {
+            let a = self.expr(&pair[0], env)?;
+            let b = self.expr(&pair[1], env)?;
+            let mut test = a.clone();
+            test.extend(b.clone());
+            if op == "==" || op == "=" {
+                // Equal when neither side is greater
+                test.push("o<=".to_string());
+                let mut ge = a;
+                ge.extend(b);
+                ge.push("o>=".to_string());
+                let zero = self.constant(GI::new_p(0.0));
+                test = Builder::ite(test, ge, zero);
+            } else {
+                test.push(format!("o{}", op));
+            }
+            out = Some(match out {
+                Some(prev) => {
+                    let zero = self.constant(GI::new_p(0.0));
+                    Builder::ite(prev, test, zero)
+                },
+                None => test,
+            });
+        }
+        Ok(out.unwrap())
+    }
+
+    // Translates e, where env holds the tapes of the names bound by enclosing
+    // lets.
+    pub fn expr(&mut self, e: &Sexp, env: &Vec<(String, Vec<String>)>)
+                -> Result<Vec<String>, String> {
+        let line = e.line();
+        let items = match e {
+            &Sexp::Atom(ref s, _) => {
+                if let Some(c) = number(s, line)? {
+                    return Ok(self.constant(c));
+                }
+                if let Some(&(_, ref tape)) = env.iter().rev().find(|b| &b.0 == s) {
+                    return Ok(tape.clone());
+                }
+                if let Some(i) = self.names.iter().position(|n| n == s) {
+                    return Ok(vec![format!("i{}", i)]);
+                }
+                let named = match self.dialect {
+                    Dialect::FPCore => named_constant(s),
+                    Dialect::SmtLib => match s.as_str() {
+                        "true" => Some(GI::new_p(1.0)),
+                        "false" => Some(GI::new_p(0.0)),
+                        _ => None,
+                    },
+                };
+                if let Some(c) = named {
+                    return Ok(self.constant(c));
+                }
+                return Err(format!("Line {}: unknown variable or constant '{}'",
+                                   line, s));
+            },
+            &Sexp::Str(ref s, _) =>
+                return Err(format!("Line {}: unexpected string \"{}\"", line, s)),
+            &Sexp::List(ref items, _) => items,
+        };
+        let op = match e.head() {
+            Some(op) => op,
+            None => return Err(format!("Line {}: expected an operator", line)),
+        };
+        let args = &items[1..];
+
+        let unary: &[(&str, &str)] = match self.dialect {
+            Dialect::FPCore => &UNARY,
+            Dialect::SmtLib => &SMT_UNARY,
+        };
+        if let Some(&(_, f)) = unary.iter().find(|u| u.0 == op) {
+            arity(line, op, args, 1)?;
+            let mut out = self.expr(&args[0], env)?;
+            out.push(format!("f{}", f));
+            return Ok(out);
+        }
+        let one = |b: &mut Builder| b.constant(GI::new_p(1.0));
+        let zero = |b: &mut Builder| b.constant(GI::new_p(0.0));
+        match op {
+            "+" => self.fold(e, op, "o+", args, env),
+            "*" => self.fold(e, op, "o*", args, env),
+            "/" => self.fold(e, op, "o/", args, env),
+            "-" if args.len() == 1 => {
+                let mut out = self.expr(&args[0], env)?;
+                out.push("fneg".to_string());
+                Ok(out)
+            },
+            "-" => self.fold(e, op, "o-", args, env),
+            "pow" => {
+                arity(line, op, args, 2)?;
+                let mut out = self.expr(&args[0], env)?;
+                match args[1].atom().and_then(|s| s.parse::<i32>().ok()) {
+                    Some(n) => out.push(format!("p{}", n)),
+                    None => {
+                        out.extend(self.expr(&args[1], env)?);
+                        out.push("op".to_string());
+                    }
+                }
+                Ok(out)
+            },
+            "fma" => {
+                arity(line, op, args, 3)?;
+                let mut out = self.fold(e, op, "o*", &args[..2], env)?;
+                out.extend(self.expr(&args[2], env)?);
+                out.push("o+".to_string());
+                Ok(out)
+            },
+            "hypot" => {
+                arity(line, op, args, 2)?;
+                let mut out = self.expr(&args[0], env)?;
+                out.push("p2".to_string());
+                out.extend(self.expr(&args[1], env)?);
+                out.extend(vec!["p2".to_string(), "o+".to_string(),
+                                "fsqrt".to_string()]);
+                Ok(out)
+            },
+            "expm1" => {
+                arity(line, op, args, 1)?;
+                let mut out = self.expr(&args[0], env)?;
+                out.push("fexp".to_string());
+                out.extend(one(self));
+                out.push("o-".to_string());
+                Ok(out)
+            },
+            "log1p" => {
+                arity(line, op, args, 1)?;
+                let mut out = self.expr(&args[0], env)?;
+                out.extend(one(self));
+                out.extend(vec!["o+".to_string(), "flog".to_string()]);
+                Ok(out)
+            },
+            "fmax" | "fmin" => {
+                arity(line, op, args, 2)?;
+                let a = self.expr(&args[0], env)?;
+                let b = self.expr(&args[1], env)?;
+                let mut test = a.clone();
+                test.extend(b.clone());
+                test.push(if op == "fmax" { "o>=" } else { "o<=" }.to_string());
+                Ok(Builder::ite(test, a, b))
+            },
+            "<" | "<=" | ">" | ">=" | "==" | "=" => self.compare(e, op, args, env),
+            "and" | "or" => {
+                if args.is_empty() {
+                    return Err(format!("Line {}: '{}' expects arguments", line, op));
+                }
+                let mut out = self.expr(&args[0], env)?;
+                for a in &args[1..] {
+                    let next = self.expr(a, env)?;
+                    out = if op == "and" {
+                        let z = zero(self);
+                        Builder::ite(out, next, z)
+                    } else {
+                        let o = one(self);
+                        Builder::ite(out, o, next)
+                    };
+                }
+                Ok(out)
+            },
+            "not" => {
+                arity(line, op, args, 1)?;
+                let c = self.expr(&args[0], env)?;
+                let (z, o) = (zero(self), one(self));
+                Ok(Builder::ite(c, z, o))
+            },
+            "if" | "ite" => {
+                arity(line, op, args, 3)?;
+                let c = self.expr(&args[0], env)?;
+                let a = self.expr(&args[1], env)?;
+                let b = self.expr(&args[2], env)?;
+                Ok(Builder::ite(c, a, b))
+            },
+            "let" | "let*" => {
+                arity(line, op, args, 2)?;
+                let bindings = match args[0].list() {
+                    Some(b) => b,
+                    None => return Err(format!("Line {}: '{}' expects a list of \
+                                                bindings", line, op)),
+                };
+                let mut inner = env.clone();
+                for b in bindings {
+                    let (name, value) = match b.list().map(|l| &l[..]) {
+                        Some(&[Sexp::Atom(ref n, _), ref v]) => (n.clone(), v),
+                        _ => return Err(format!("Line {}: malformed binding",
+                                                b.line())),
+                    };
+                    // let binds in the outer scope, let* in order
+                    let tape = if op == "let" {
+                        self.expr(value, env)?
+                    } else {
+                        self.expr(value, &inner)?
+                    };
+                    inner.push((name, tape));
+                }
+                self.expr(&args[1], &inner)
+            },
+            // FPCore puts the annotated expression last, SMT-LIB first
+            "!" => match (self.dialect, args.first(), args.last()) {
+                (Dialect::FPCore, _, Some(body)) |
+                (Dialect::SmtLib, Some(body), _) => self.expr(body, env),
+                _ => Err(format!("Line {}: annotation without expression", line)),
+            },
+            _ => Err(format!("Line {}: unsupported operator '{}'", line, op)),
+        }
+    }
+}
+
+// Bounds on the inputs collected from :pre.
+struct Ranges {
+    lower: Vec<Option<f64>>,
+    upper: Vec<Option<f64>>,
+}
+
+impl Ranges {
+    fn bound(&self, b: &Sexp, names: &Vec<String>) -> Result<Bound, String> {
+        let line = b.line();
+        if let Some(s) = b.atom() {
+            if let Some(i) = names.iter().position(|n| n == s) {
+                return Ok(Bound::Input(i));
+            }
+            if let Some(c) = number(s, line)? {
+                return Ok(Bound::Value(c));
+            }
+            if let Some(c) = named_constant(s) {
+                return Ok(Bound::Value(c));
+            }
+        }
+        if b.head() == Some("-") && b.list().unwrap().len() == 2 {
+            if let Bound::Value(c) = self.bound(&b.list().unwrap()[1], names)? {
+                return Ok(Bound::Value(-c));
+            }
+        }
+        Err(format!("Line {}: :pre bounds must be numbers, constants or inputs",
+                    line))
+    }
+
+    fn set_lower(&mut self, i: usize, x: f64) {
+        self.lower[i] = Some(self.lower[i].map_or(x, |l| l.max(x)));
+    }
+
+    fn set_upper(&mut self, i: usize, x: f64) {
+        self.upper[i] = Some(self.upper[i].map_or(x, |u| u.min(x)));
+    }
+
+    fn collect(&mut self, pre: &Sexp, names: &Vec<String>) -> Result<(), String> {
+        let op = pre.head().unwrap_or("");
+        let args = match pre.list() {
+            Some(l) => &l[1..],
+            None => &[],
+        };
+        match op {
+            "and" => {
+                for a in args {
+                    self.collect(a, names)?;
+                }
+            },
+            "<" | "<=" | ">" | ">=" | "==" => {
+                let mut bounds = Vec::new();
+                for a in args {
+                    bounds.push(self.bound(a, names)?);
+                }
+                if op == ">" || op == ">=" {
+                    bounds.reverse();
+                }
+                for pair in bounds.windows(2) {
+                    match (&pair[0], &pair[1]) {
+                        (&Bound::Value(c), &Bound::Input(i)) => {
+                            self.set_lower(i, c.lower());
+                            if op == "==" {
+                                self.set_upper(i, c.upper());
+                            }
+                        },
+                        (&Bound::Input(i), &Bound::Value(c)) => {
+                            self.set_upper(i, c.upper());
+                            if op == "==" {
+                                self.set_lower(i, c.lower());
+                            }
+                        },
+                        (&Bound::Input(_), &Bound::Input(_)) =>
+                            return Err(format!("Line {}: relations between inputs \
+                                                are not supported in :pre",
+                                               pre.line())),
+                        _ => (),
+                    }
+                }
+            },
+            _ => return Err(format!("Line {}: unsupported :pre condition, expected \
+                                     'and' of range comparisons", pre.line())),
+        }
+        Ok(())
+    }
+}
+
+enum Bound {
+    Input(usize),
+    Value(GI),
+}
+
+// Converts one FPCore expression into a definition.
+fn convert(core: &Vec<Sexp>, line: usize) -> Result<FuncDef, String> {
+    // (FPCore name? (args...) props... body)
+    let mut at = 1;
+    let mut metadata = BTreeMap::new();
+    if let Some(id) = core.get(at).and_then(|s| s.atom()) {
+        metadata.insert("name".to_string(), id.to_string());
+        at += 1;
+    }
+    let args = match core.get(at).and_then(|a| a.list()) {
+        Some(a) => a,
+        None => return Err(format!("Line {}: FPCore is missing its argument list",
+                                   line)),
+    };
+    let mut names = Vec::new();
+    for a in args {
+        let name = match a {
+            &Sexp::Atom(ref s, _) => s.clone(),
+            // (! props... name) annotations
+            &Sexp::List(ref l, _) if a.head() == Some("!") =>
+                match l.last().and_then(|n| n.atom()) {
+                    Some(n) => n.to_string(),
+                    None => return Err(format!("Line {}: malformed argument",
+                                               a.line())),
+                },
+            _ => return Err(format!("Line {}: tensor arguments are not supported",
+                                    a.line())),
+        };
+        names.push(name);
+    }
+
+    let body = match core.last() {
+        Some(b) if core.len() > at + 1 => b,
+        _ => return Err(format!("Line {}: FPCore is missing its body", line)),
+    };
+    let mut pre = None;
+    let mut props = core[at + 1..core.len() - 1].iter();
+    while let Some(key) = props.next() {
+        let key = match key.atom() {
+            Some(k) if k.starts_with(':') => k,
+            _ => return Err(format!("Line {}: expected a property", key.line())),
+        };
+        let value = match props.next() {
+            Some(v) => v,
+            None => return Err(format!("Line {}: property {} has no value",
+                                       line, key)),
+        };
+        match (key, value) {
+            (":pre", v) => pre = Some(v),
+            (k, &Sexp::Str(ref s, _)) | (k, &Sexp::Atom(ref s, _)) => {
+                metadata.insert(k[1..].to_string(), s.clone());
+            },
+            _ => (),
+        }
+    }
+
+    let mut ranges = Ranges{lower: vec![None; names.len()],
+                            upper: vec![None; names.len()]};
+    if let Some(p) = pre {
+        ranges.collect(p, &names)?;
+    }
+    let mut domain = Vec::new();
+    for (i, n) in names.iter().enumerate() {
+        let (lo, hi) = match (ranges.lower[i], ranges.upper[i]) {
+            (Some(lo), Some(hi)) => (lo, hi),
+            (None, _) => return Err(format!("Input '{}' has no lower bound, give \
+                                             one in :pre", n)),
+            (_, None) => return Err(format!("Input '{}' has no upper bound, give \
+                                             one in :pre", n)),
+        };
+        if lo > hi {
+            return Err(format!("Input '{}' has an empty range in :pre", n));
+        }
+        domain.push(GI::new_d(lo, hi));
+    }
+
+    let mut builder = Builder{names: names.clone(), constants: Vec::new(),
+                              dialect: Dialect::FPCore};
+    let tape = builder.expr(body, &Vec::new())?;
+    metadata.insert("source".to_string(), "fpcore".to_string());
+
+    let def = FuncDef{instructions: tape.join(","), constants: builder.constants,
+                      names: names, domain: domain, kinds: vec![], param_names: vec![],
+                      params: vec![], constraints: vec![], equalities: vec![],
+                      metadata: metadata};
+    def.check()?;
+    Ok(def)
+}
+
+// Name of an FPCore, from its identifier or its :name property.
+fn core_name(core: &Vec<Sexp>) -> Option<String> {
+    if let Some(id) = core.get(1).and_then(|s| s.atom()) {
+        return Some(id.to_string());
+    }
+    core.iter().position(|p| p.atom() == Some(":name"))
+        .and_then(|i| core.get(i + 1))
+        .and_then(|v| match v {
+            &Sexp::Str(ref s, _) | &Sexp::Atom(ref s, _) => Some(s.clone()),
+            _ => None,
+        })
+}
+
+// Reads the FPCore with the given name, or the first one in text when no name
+// is given.
+pub fn read(text: &str, name: Option<&str>) -> Result<FuncDef, String> {
+    let exprs = sexp::parse(text)?;
+    let mut cores = exprs.iter().filter(|e| e.head() == Some("FPCore"));
+    let core = match name {
+        None => cores.next(),
+        Some(n) => cores.find(|e| core_name(e.list().unwrap()).as_ref()
+                              .map(|s| s.as_str()) == Some(n)),
+    };
+    match (core, name) {
+        (Some(e), _) => convert(e.list().unwrap(), e.line()),
+        (None, None) => Err("No FPCore expression found".to_string()),
+        (None, Some(n)) => Err(format!("No FPCore named '{}' found", n)),
+    }
+}
+
+// Reads an FPCore file, see read.
+pub fn load(path: &str, name: Option<&str>) -> Result<FuncDef, String> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut text))
+        .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    read(&text, name)
+}
@@ -2,23 +2,49 @@
 extern crate dylib;
 use dylib::DynamicLibrary;
 
+#[macro_use]
+extern crate serde_json;
+
 use std::path::Path;
 use std::mem::transmute;
 
 use std::process::Command;
 use std::thread;
+use std::panic::{self, AssertUnwindSafe};
 
 use std::sync::atomic::{AtomicBool, Ordering, AtomicPtr};
 use std::option::Option;
 
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, Mutex, Condvar};
 use std::fmt;
+use std::str::FromStr;
 
 // Internal libraries
 extern crate gr;
 use gr::*;
+use gr::point::EP;
+
+mod verify;
+use verify::{parse_instructions, verify};
+
+mod vm;
+use vm::Program;
+
+mod dag;
+
+mod opt;
 
+pub mod plugin;
 
+mod render;
+pub use render::Format;
+
+mod sexp;
+pub mod fpcore;
+pub mod smtlib;
+
+pub mod format;
+pub use format::FuncDef;
 
 
 #[derive(Clone)]
@@ -28,7 +54,9 @@ enum OpType {
     Var(usize),
     UVar(usize),
     Op(String),
-    Pow(i32)
+    Pow(i32),
+    Store(usize),
+    Load(usize)
 }
 
 impl fmt::Display for OpType {
@@ -40,56 +68,496 @@ impl fmt::Display for OpType {
             &OpType::Var(i) => format!("i{}", i),
             &OpType::UVar(i) => format!("v{}", i),
             &OpType::Op(ref s) => format!("o{}", s),
-            &OpType::Pow(i) => format!("p{}", i)
+            &OpType::Pow(i) => format!("p{}", i),
+            &OpType::Store(i) => format!("s{}", i),
+            &OpType::Load(i) => format!("l{}", i)
         };
         write!(f, "{}", outstring)
     }
 
 }
 
+// State of the background compilation of a function.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CompileStatus {
+    // Compilation is still running, calls are interpreted.
+    Pending,
+    // The compiled function is loaded and used for all calls.
+    Compiled,
+    // Compilation was not attempted, calls are always interpreted.
+    Disabled,
+    // Compilation failed, the string holds the reason and captured stderr.
+    Failed(String),
+}
+
+impl fmt::Display for CompileStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outstring = match self {
+            &CompileStatus::Pending => "pending",
+            &CompileStatus::Compiled => "compiled",
+            &CompileStatus::Disabled => "disabled",
+            &CompileStatus::Failed(_) => "failed",
+        };
+        write!(f, "{}", outstring)
+    }
+}
+
+impl CompileStatus {
+    // Why compilation failed, with the captured stderr, if it did.
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            &CompileStatus::Failed(ref msg) => Some(msg),
+            _ => None,
+        }
+    }
+}
+
+// How a FuncObj makes use of the compiled version of its function.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CompilePolicy {
+    // Interpret until compilation finishes, then switch over.
+    Background,
+    // Block until compilation finishes, interpret if it failed.
+    Wait,
+    // Never compile, always interpret.
+    Interpret,
+    // Block until compilation finishes and fail if it did not succeed.
+    Require,
+}
+
+impl FromStr for CompilePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<CompilePolicy, String> {
+        match s {
+            "background" => Ok(CompilePolicy::Background),
+            "wait" => Ok(CompilePolicy::Wait),
+            "interp" => Ok(CompilePolicy::Interpret),
+            "require" => Ok(CompilePolicy::Require),
+            _ => Err(format!("Unknown compile policy '{}', expected one of \
+                              background, wait, interp or require", s)),
+        }
+    }
+}
+
+// Whether a box satisfies the constraints of a function. Equalities count as
+// satisfied within the equality tolerance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feasibility {
+    // Every point of the box satisfies every constraint.
+    Feasible,
+    // No point of the box satisfies some constraint.
+    Infeasible,
+    // Neither could be shown.
+    Undecided,
+}
+
+// Signature of the compiled function: inputs, constants and user variables.
+type CompiledFunc = fn(&Vec<GI>, &Vec<GI>, &Vec<GI>) -> (GI, Option<Vec<GI>>);
+// Signature of the compiled batch function: number of points, inputs in
+// structure of arrays layout, constants, user variables and the output vector.
+type CompiledBatchFunc = fn(usize, &Vec<Vec<GI>>, &Vec<GI>, &Vec<GI>, &mut Vec<GI>);
+// Signature of the compiled point function, as the batch function but taking
+// points and producing a rigorous lower bound for each.
+type CompiledPointFunc = fn(usize, &Vec<Vec<f64>>, &Vec<GI>, &Vec<GI>, &mut Vec<f64>);
+
 #[derive(Clone)]
 pub struct FuncObj {
     handle: Arc<RwLock<Option<DynamicLibrary>>>,
     user_vars: Vec<GI>,
     constants: Vec<GI>,
+    // Constants read by the optimized program, the given constants followed by
+    // those created by constant folding.
+    program_constants: Vec<GI>,
+    point_user_vars: Vec<EP>,
+    point_constants: Vec<EP>,
     instructions: Vec<OpType>,
+    program: Arc<Program>,
     switched: Arc<AtomicBool>,
-    function: Arc<AtomicPtr<fn(&Vec<GI>, &Vec<GI>) -> (GI, Option<Vec<GI>>)>>,
-
+    function: Arc<AtomicPtr<CompiledFunc>>,
+    batched: Arc<AtomicBool>,
+    batch_function: Arc<AtomicPtr<CompiledBatchFunc>>,
+    pointed: Arc<AtomicBool>,
+    point_function: Arc<AtomicPtr<CompiledPointFunc>>,
+    status: Arc<(Mutex<CompileStatus>, Condvar)>,
+    // Whether calls return the negated function, see negated.
+    negated: bool,
+    // Constraints g(x) <= 0 on the inputs, see add_constraint.
+    constraints: Vec<FuncObj>,
+    // Constraints h(x) = 0 on the inputs, see add_equality.
+    equalities: Vec<FuncObj>,
+    // |h(x)| <= eq_tolerance counts as satisfying h(x) = 0.
+    eq_tolerance: f64,
+    // The kind of every input, empty when all of them are real.
+    input_kinds: Vec<InputKind>,
 }
 
 unsafe impl Sync for FuncObj {}
 unsafe impl Send for FuncObj {}
-fn dummy(_x: &Vec<GI>, _c: &Vec<GI>) -> (GI, Option<Vec<GI>>) {
+fn dummy(_x: &Vec<GI>, _c: &Vec<GI>, _v: &Vec<GI>) -> (GI, Option<Vec<GI>>) {
     (GI::new_c("1.0").unwrap(), None)
 }
+fn dummy_batch(_n: usize, _xs: &Vec<Vec<GI>>, _c: &Vec<GI>, _v: &Vec<GI>,
+               _out: &mut Vec<GI>) {
+}
+fn dummy_point(_n: usize, _xs: &Vec<Vec<f64>>, _c: &Vec<GI>, _v: &Vec<GI>,
+               _out: &mut Vec<f64>) {
+}
 
 impl FuncObj {
     pub fn call(&self, _x: &Vec<GI>) -> (GI, Option<Vec<GI>>) {
+        let (fx, dfx) = self.evaluate(_x);
+        if self.negated {
+            (-fx, dfx.map(|d| d.into_iter().map(|di| -di).collect()))
+        } else {
+            (fx, dfx)
+        }
+    }
+
+    // The function -f, sharing the code and compilation of this function. A
+    // minimum of f is found by maximizing -f. The tape, constants and renderings
+    // still describe f, and the constraints are kept as they are.
+    pub fn negated(&self) -> FuncObj {
+        let mut f = self.clone();
+        f.negated = !self.negated;
+        f
+    }
+
+    fn evaluate(&self, _x: &Vec<GI>) -> (GI, Option<Vec<GI>>) {
         if self.switched.load(Ordering::Acquire) {
             let real_func = unsafe{
-                std::mem::transmute::<*mut fn(&Vec<GI>, &Vec<GI>)->(GI, Option<Vec<GI>>),
-                                      fn(&Vec<GI>, &Vec<GI>)->(GI, Option<Vec<GI>>)>(
+                std::mem::transmute::<*mut CompiledFunc, CompiledFunc>(
                     self.function.load(Ordering::Acquire))};
-            real_func(_x, &self.constants)
+            real_func(_x, &self.constants, &self.user_vars)
+        }
+        else {
+            self.interpreted(_x, &self.program_constants)
+        }
+    }
+
+    // Evaluates the function on a batch of points given in structure of
+    // arrays layout, xs[i][j] is input i of point j, and returns the value at
+    // each point. Gradients are not computed.
+    pub fn call_batch(&self, xs: &Vec<Vec<GI>>) -> Vec<GI> {
+        let n = match xs.first() {
+            Some(x) => x.len(),
+            None => return vec![self.call(&vec![]).0],
+        };
+        let mut out = Vec::with_capacity(n);
+        if self.switched.load(Ordering::Acquire) {
+            if self.batched.load(Ordering::Acquire) {
+                let real_func = unsafe{
+                    std::mem::transmute::<*mut CompiledBatchFunc, CompiledBatchFunc>(
+                        self.batch_function.load(Ordering::Acquire))};
+                real_func(n, xs, &self.constants, &self.user_vars, &mut out);
+            }
+            else {
+                // Libraries built before the batch entry point existed.
+                let mut x = Vec::with_capacity(xs.len());
+                for j in 0..n {
+                    x.clear();
+                    x.extend(xs.iter().map(|xi| xi[j]));
+                    out.push(self.evaluate(&x).0);
+                }
+            }
+        }
+        else {
+            self.program.eval_batch(n, xs, &self.program_constants, &self.user_vars,
+                                    &mut out);
+        }
+        if self.negated {
+            for v in out.iter_mut() {
+                *v = -*v;
+            }
+        }
+        out
+    }
+
+    // Returns a rigorous lower bound of the function at the point x. This is
+    // cheaper than taking the lower bound of call on a degenerate interval
+    // but may be less tight.
+    pub fn call_point(&self, x: &Vec<f64>) -> f64 {
+        let xs = x.iter().map(|&xi| vec![xi]).collect();
+        self.call_point_batch(&xs)[0]
+    }
+
+    // Returns a rigorous lower bound of the function at each point of a batch
+    // given in the same layout as call_batch.
+    pub fn call_point_batch(&self, xs: &Vec<Vec<f64>>) -> Vec<f64> {
+        let n = match xs.first() {
+            Some(x) => x.len(),
+            None => 1,
+        };
+        let mut out = Vec::with_capacity(n);
+        // The compiled point function only gives lower bounds of f
+        if self.pointed.load(Ordering::Acquire) && !self.negated {
+            let real_func = unsafe{
+                std::mem::transmute::<*mut CompiledPointFunc, CompiledPointFunc>(
+                    self.point_function.load(Ordering::Acquire))};
+            real_func(n, xs, &self.constants, &self.user_vars, &mut out);
         }
         else {
-            self.interpreted(_x, &self.constants)
+            let pxs = xs.iter()
+                .map(|xi| xi.iter().map(|&x| EP::new(x)).collect())
+                .collect::<Vec<_>>();
+            let mut res = Vec::with_capacity(n);
+            self.program.eval_point_batch(n, &pxs, &self.point_constants,
+                                          &self.point_user_vars, &mut res);
+            if self.negated {
+                out.extend(res.iter().map(|r| -r.upper()));
+            } else {
+                out.extend(res.iter().map(|r| r.lower()));
+            }
         }
+        out
     }
 
-    fn set(&self, f: fn(&Vec<GI>,&Vec<GI>) -> (GI, Option<Vec<GI>>), handle: DynamicLibrary) {
+    fn set(&self, f: CompiledFunc, batch: Option<CompiledBatchFunc>,
+           point: Option<CompiledPointFunc>, handle: DynamicLibrary) {
         // This FuncObj owns the handle from the dynamic lib as the lifetime of
         // f is tied to the lifetime of the handle.
-        self.function.store(unsafe{std::mem::transmute::<fn(&Vec<GI>, &Vec<GI>)->(GI, Option<Vec<GI>>),
-                                                         *mut fn(&Vec<GI>, &Vec<GI>)->(GI, Option<Vec<GI>>)>(f)}, Ordering::Release);
+        self.function.store(unsafe{std::mem::transmute::<CompiledFunc, *mut CompiledFunc>(f)},
+                            Ordering::Release);
+        if let Some(b) = batch {
+            self.batch_function.store(
+                unsafe{std::mem::transmute::<CompiledBatchFunc, *mut CompiledBatchFunc>(b)},
+                Ordering::Release);
+            self.batched.store(true, Ordering::Release);
+        }
+        if let Some(p) = point {
+            self.point_function.store(
+                unsafe{std::mem::transmute::<CompiledPointFunc, *mut CompiledPointFunc>(p)},
+                Ordering::Release);
+            self.pointed.store(true, Ordering::Release);
+        }
         let mut h = self.handle.write().unwrap();
         *h = Some(handle);
         self.switched.store(true, Ordering::Release);
     }
 
+    pub fn user_vars(&self) -> &Vec<GI> {
+        &self.user_vars
+    }
+
+    pub fn constants(&self) -> &Vec<GI> {
+        &self.constants
+    }
+
+    // The instruction tape in the comma separated form accepted by new.
+    pub fn tape(&self) -> String {
+        self.instructions.iter().map(|i| i.to_string())
+            .collect::<Vec<_>>().join(",")
+    }
+
+    // Renders the tape in the given format, naming inputs and user variables
+    // after names and param_names. When domain is given every operation is
+    // annotated with its enclosure over that box.
+    pub fn render(&self, format: Format, names: &[String], param_names: &[String],
+                  domain: Option<&[GI]>) -> String {
+        render::render(&self.instructions, &self.constants, &self.user_vars,
+                       format, names, param_names, domain)
+    }
+
+    // Writes an SMT-LIB script asserting that the function falls below lower
+    // or exceeds upper somewhere in domain where the constraints hold, which an
+    // SMT solver can use to check claimed bounds. Inputs and user variables are
+    // named as in render.
+    pub fn smtlib(&self, names: &[String], param_names: &[String], domain: &[GI],
+                  lower: Option<f64>, upper: Option<f64>) -> Result<String, String> {
+        let tapes = |fs: &Vec<FuncObj>| fs.iter().map(|g| g.instructions.clone())
+            .collect::<Vec<_>>();
+        smtlib::export(&self.instructions, &tapes(&self.constraints),
+                       &tapes(&self.equalities), self.eq_tolerance, &self.constants,
+                       &self.user_vars, names, param_names, domain, &self.input_kinds,
+                       lower, upper)
+    }
+
+    // Sets the value of the user variable read by the "v<index>" instruction.
+    // The new value is seen by both the interpreted and compiled function, by
+    // the constraints and by clones made after this call.
+    pub fn set_user_var(&mut self, index: usize, value: GI) -> Result<(), String> {
+        if index >= self.user_vars.len() {
+            return Err(format!("User variable index {} out of range, there are {} \
+                                user variables", index, self.user_vars.len()));
+        }
+        self.user_vars[index] = value;
+        self.point_user_vars[index] = EP::from_gi(value);
+        for g in self.constraints.iter_mut().chain(self.equalities.iter_mut()) {
+            g.set_user_var(index, value)?;
+        }
+        Ok(())
+    }
+
+    // Adds the constraint g(x) <= 0, where g is given as a tape over the same
+    // constants, user variables and n_inputs inputs as the function.
+    // Constraints are always interpreted.
+    pub fn add_constraint(&mut self, instructions: &String, n_inputs: usize)
+                          -> Result<(), String> {
+        let g = FuncObj::new(&self.constants, &self.user_vars, instructions,
+                             n_inputs, false, String::new(), CompilePolicy::Interpret)
+            .map_err(|msg| format!("Constraint {}: {}", self.constraints.len(), msg))?;
+        self.constraints.push(g);
+        Ok(())
+    }
+
+    // Adds the constraint h(x) = 0, given as a tape like in add_constraint.
+    pub fn add_equality(&mut self, instructions: &String, n_inputs: usize)
+                        -> Result<(), String> {
+        let h = FuncObj::new(&self.constants, &self.user_vars, instructions,
+                             n_inputs, false, String::new(), CompilePolicy::Interpret)
+            .map_err(|msg| format!("Equality {}: {}", self.equalities.len(), msg))?;
+        self.equalities.push(h);
+        Ok(())
+    }
+
+    // Sets the tolerance eps within which |h(x)| <= eps counts as satisfying
+    // an equality, 0 by default.
+    pub fn set_equality_tolerance(&mut self, eps: f64) -> Result<(), String> {
+        if !(eps >= 0.0 && eps.is_finite()) {
+            return Err(format!("The equality tolerance must be a finite number \
+                                of at least 0, got {}", eps));
+        }
+        self.eq_tolerance = eps;
+        Ok(())
+    }
+
+    pub fn equality_tolerance(&self) -> f64 {
+        self.eq_tolerance
+    }
+
+    // The tapes of the constraints in the form accepted by add_constraint.
+    pub fn constraint_tapes(&self) -> Vec<String> {
+        self.constraints.iter().map(|g| g.tape()).collect()
+    }
+
+    // The tapes of the equalities in the form accepted by add_equality.
+    pub fn equality_tapes(&self) -> Vec<String> {
+        self.equalities.iter().map(|h| h.tape()).collect()
+    }
+
+    // The inequality constraints g, as functions of the inputs.
+    pub fn constraints(&self) -> &[FuncObj] {
+        &self.constraints
+    }
+
+    // The equality constraints h, as functions of the inputs.
+    pub fn equalities(&self) -> &[FuncObj] {
+        &self.equalities
+    }
+
+    // Declares the kinds of the inputs, one per input. The solvers only
+    // consider admissible values of discrete inputs.
+    pub fn set_input_kinds(&mut self, kinds: Vec<InputKind>) {
+        self.input_kinds = if kinds.iter().any(|k| k.is_discrete()) { kinds } else { vec![] };
+    }
+
+    // The kinds of the inputs, empty when all of them are real.
+    pub fn input_kinds(&self) -> &[InputKind] {
+        &self.input_kinds
+    }
+
+    pub fn has_discrete_inputs(&self) -> bool {
+        !self.input_kinds.is_empty()
+    }
+
+    // Whether there are inequality or equality constraints.
+    pub fn has_constraints(&self) -> bool {
+        !self.constraints.is_empty() || !self.equalities.is_empty()
+    }
+
+    // Classifies the box x against the constraints. Without constraints every
+    // box is feasible. A constraint whose enclosure is empty, such as the
+    // square root of a negative box, is defined nowhere in x and so certainly
+    // fails.
+    pub fn feasibility(&self, x: &Vec<GI>) -> Feasibility {
+        let mut result = Feasibility::Feasible;
+        for g in &self.constraints {
+            let gx = g.call(x).0;
+            if gx.is_empty() || gx.lower() > 0.0 {
+                return Feasibility::Infeasible;
+            }
+            if gx.upper() > 0.0 {
+                result = Feasibility::Undecided;
+            }
+        }
+        let eps = self.eq_tolerance;
+        for h in &self.equalities {
+            let hx = h.call(x).0;
+            if hx.is_empty() || hx.lower() > eps || hx.upper() < -eps {
+                return Feasibility::Infeasible;
+            }
+            if hx.upper() > eps || hx.lower() < -eps {
+                result = Feasibility::Undecided;
+            }
+        }
+        result
+    }
+
+    // How far each point of a batch, given in the layout of call_batch, may
+    // violate the constraints: the sum of the positive upper bounds of the
+    // constraints there, plus the amounts by which the equalities may leave
+    // the tolerance. Only points with a violation of 0 are certainly feasible.
+    // Points where a constraint has an empty enclosure are certainly
+    // infeasible, as in feasibility, and have an infinite violation.
+    pub fn violations(&self, xs: &Vec<Vec<GI>>) -> Vec<f64> {
+        let n = xs.first().map_or(1, |x| x.len());
+        let mut out = vec![0.0; n];
+        for g in &self.constraints {
+            for (v, gx) in out.iter_mut().zip(g.call_batch(xs)) {
+                if gx.is_empty() {
+                    *v = std::f64::INFINITY;
+                } else if gx.upper() > 0.0 {
+                    *v += gx.upper();
+                }
+            }
+        }
+        let eps = self.eq_tolerance;
+        for h in &self.equalities {
+            for (v, hx) in out.iter_mut().zip(h.call_batch(xs)) {
+                let excess = (hx.upper() - eps).max(-hx.lower() - eps);
+                if hx.is_empty() {
+                    *v = std::f64::INFINITY;
+                } else if excess > 0.0 {
+                    *v += excess;
+                }
+            }
+        }
+        out
+    }
+
+    // Records the outcome of compilation and wakes up anyone waiting on it.
+    fn set_status(&self, status: CompileStatus) {
+        let &(ref lock, ref cvar) = &*self.status;
+        *lock.lock().unwrap() = status;
+        cvar.notify_all();
+    }
+
+    pub fn compile_status(&self) -> CompileStatus {
+        let &(ref lock, _) = &*self.status;
+        lock.lock().unwrap().clone()
+    }
+
+    // Blocks until compilation is no longer pending and returns its outcome.
+    pub fn wait_for_compile(&self) -> CompileStatus {
+        let &(ref lock, ref cvar) = &*self.status;
+        let mut status = lock.lock().unwrap();
+        while *status == CompileStatus::Pending {
+            status = cvar.wait(status).unwrap();
+        }
+        status.clone()
+    }
+
     fn interpreted(&self, _x: &Vec<GI>, _c: &Vec<GI>) -> (GI, Option<Vec<GI>>) {
+        (self.program.eval(_x, _c, &self.user_vars), None)
+    }
+
+    // Evaluates the tape directly with a stack machine. This is the original
+    // interpreter, kept as a reference to test and benchmark the register
+    // based interpreter against, and only built for those.
+    #[cfg(any(test, feature = "bench"))]
+    pub fn call_reference(&self, _x: &Vec<GI>) -> GI {
         let mut stack: Vec<GI> = Vec::new();
+        let mut locals: Vec<GI> = Vec::new();
         for inst in &self.instructions {
             match inst {
                 &OpType::Func(ref s) => {
@@ -114,7 +582,7 @@ impl FuncObj {
                         "atanh" => op.atanh(),
                         "floor_power2" => op.floor_power2(),
                         "sym_interval" => op.sym_interval(),
-                        _     => unreachable!()
+                        _     => *op = (plugin::lookup(s).unwrap().interval)(*op)
                     };
                 },
                 &OpType::Const(i) => {
@@ -126,6 +594,12 @@ impl FuncObj {
                 &OpType::UVar(i) => {
                     stack.push(self.user_vars[i]);
                 },
+                &OpType::Op(ref s) if s == "ite" => {
+                    let else_val = stack.pop().unwrap();
+                    let then_val = stack.pop().unwrap();
+                    let cond = stack.last_mut().unwrap();
+                    *cond = ite(*cond, then_val, else_val);
+                },
                 &OpType::Op(ref s) => {
                     let right = stack.pop().unwrap();
                     let left = stack.last_mut().unwrap();
@@ -136,101 +610,182 @@ impl FuncObj {
                         "/" => left.div(right),
                         "p" => left.powi(right),
                         "sub2" => left.sub2(right),
+                        "<" => *left = lt(*left, right),
+                        "<=" => *left = le(*left, right),
+                        ">" => *left = gt(*left, right),
+                        ">=" => *left = ge(*left, right),
                         _   => unreachable!()
                     };
                 },
                 &OpType::Pow(exp) => {
                     let arg = stack.last_mut().unwrap();
                     arg.pow(exp);
+                },
+                &OpType::Store(i) => {
+                    if locals.len() <= i {
+                        locals.resize(i + 1, GI::new_e());
+                    }
+                    locals[i] = *stack.last().unwrap();
+                },
+                &OpType::Load(i) => {
+                    stack.push(locals[i]);
                 }
             }
         }
-        (stack[0], None)
-    }
-
-    pub fn new(consts: &Vec<GI>, instructions: &String, debug: bool, suffix: String) -> FuncObj {
-        let mut insts = vec![];
-
-        for inst in instructions.split(',') {
-            let dummy = inst.trim().to_string();
-            let (first, rest) = dummy.split_at(1);
-            insts.push(match first {
-                "c" => OpType::Const(rest.to_string().parse::<usize>().unwrap()),
-                "i" => OpType::Var(rest.to_string().parse::<usize>().unwrap()),
-                "v" => OpType::UVar(rest.to_string().parse::<usize>().unwrap()),
-                "o" => OpType::Op(rest.to_string()),
-                "f" => OpType::Func(rest.to_string()),
-                "p" => OpType::Pow(rest.to_string().parse::<i32>().unwrap()),
-                _   => panic!()
-            });
-        }
+        stack[0]
+    }
+
+    // The function interpreted by the register based interpreter on the tape
+    // as given, without the optimizations of opt, so that it can be compared
+    // with call_reference on the same tape.
+    #[cfg(any(test, feature = "bench"))]
+    pub fn unoptimized(&self) -> FuncObj {
+        FuncObj{program: Arc::new(Program::new(&self.instructions)),
+                program_constants: self.constants.clone(),
+                point_constants: self.constants.iter().map(|&c| EP::from_gi(c)).collect(),
+                ..self.clone()}
+    }
+
+    // Builds a function from its constants, initial user variable values and
+    // instruction tape. The tape is checked against the number of constants,
+    // user variables and inputs up front, so a malformed tape is reported here
+    // rather than at evaluation time.
+    pub fn new(consts: &Vec<GI>, user_vars: &Vec<GI>, instructions: &String,
+               n_inputs: usize, debug: bool, suffix: String,
+               policy: CompilePolicy) -> Result<FuncObj, String> {
+        let insts = parse_instructions(instructions)?;
+        verify(&insts, consts.len(), n_inputs, user_vars.len())?;
+        let (optimized, program_consts) = opt::optimize(&insts, consts);
+
+        let initial_status = if policy == CompilePolicy::Interpret {
+            CompileStatus::Disabled
+        } else {
+            CompileStatus::Pending
+        };
         let result =
             FuncObj{handle: Arc::new(RwLock::new(Option::None)),
-                user_vars: vec![],
+                user_vars: user_vars.clone(),
                 constants: consts.clone(),
+                program_constants: program_consts.clone(),
+                point_user_vars: user_vars.iter().map(|&u| EP::from_gi(u)).collect(),
+                point_constants: program_consts.iter().map(|&c| EP::from_gi(c)).collect(),
+                program: Arc::new(Program::new(&optimized)),
                 instructions: insts,
                 switched: Arc::new(AtomicBool::new(false)),
-                function: Arc::new(AtomicPtr::new(unsafe{std::mem::transmute::<fn(&Vec<GI>, &Vec<GI>)->(GI, Option<Vec<GI>>),
-                                                                               *mut fn(&Vec<GI>, &Vec<GI>)->(GI, Option<Vec<GI>>)>(dummy)}))
+                function: Arc::new(AtomicPtr::new(unsafe{std::mem::transmute::<CompiledFunc, *mut CompiledFunc>(dummy)})),
+                batched: Arc::new(AtomicBool::new(false)),
+                batch_function: Arc::new(AtomicPtr::new(unsafe{std::mem::transmute::<CompiledBatchFunc, *mut CompiledBatchFunc>(dummy_batch)})),
+                pointed: Arc::new(AtomicBool::new(false)),
+                point_function: Arc::new(AtomicPtr::new(unsafe{std::mem::transmute::<CompiledPointFunc, *mut CompiledPointFunc>(dummy_point)})),
+                status: Arc::new((Mutex::new(initial_status), Condvar::new())),
+                negated: false,
+                constraints: Vec::new(),
+                equalities: Vec::new(),
+                eq_tolerance: 0.0,
+                input_kinds: Vec::new(),
         };
 
+        if policy == CompilePolicy::Interpret {
+            return Ok(result);
+        }
+
         {
             let fo_c = result.clone();
             thread::spawn(move || {
-                &fo_c.compile(debug, &suffix);
+                // A panic while compiling is a failure too, or waiters would
+                // block forever
+                let status = match panic::catch_unwind(AssertUnwindSafe(|| {
+                    fo_c.compile(debug, &suffix)
+                })) {
+                    Ok(Ok(())) => CompileStatus::Compiled,
+                    Ok(Err(msg)) => CompileStatus::Failed(msg),
+                    Err(cause) => {
+                        let msg = cause.downcast_ref::<&str>().map(|s| s.to_string())
+                            .or_else(|| cause.downcast_ref::<String>().cloned())
+                            .unwrap_or_else(|| "unknown cause".to_string());
+                        CompileStatus::Failed(format!("Compilation panicked: {}", msg))
+                    }
+                };
+                fo_c.set_status(status);
             })
         };
-        result
+
+        match policy {
+            CompilePolicy::Wait => {
+                result.wait_for_compile();
+            },
+            CompilePolicy::Require => {
+                if let CompileStatus::Failed(msg) = result.wait_for_compile() {
+                    return Err(format!("Compilation of function failed: {}", msg));
+                }
+            },
+            _ => ()
+        }
+        Ok(result)
     }
 
-    fn compile(&self, debug: bool, suffix: &String) {
+    // Builds and loads the function library, the error holds the reason for
+    // failure along with any captured stderr.
+    fn compile(&self, debug: bool, suffix: &String) -> Result<(), String> {
         let mut process = Command::new("build_func.sh");
         if debug {
             println!("compile suffix: {}", suffix);
         }
-        let ignore = process.arg(suffix.clone()).output();
-        if !ignore.is_ok() {
-            return;
-        }
-        let ignore2 = ignore.unwrap();
-        if !ignore2.status.success() {
-            return;
+        let output = match process.arg(suffix.clone()).output() {
+            Ok(o) => o,
+            Err(err) => return Err(format!("Unable to run build_func.sh: {}", err)),
+        };
+        if !output.status.success() {
+            return Err(format!("build_func.sh exited with {}: {}",
+                               output.status,
+                               String::from_utf8_lossy(&output.stderr)));
         }
         DynamicLibrary::prepend_search_path(Path::new("./.compiled"));
         let dylib: String = "libfunc_".to_string() + suffix + ".so".into();
 
-        let fl = DynamicLibrary::open(Some(Path::new(&dylib)));
-        if !fl.is_ok() {
-            return;
-        }
-        let f = fl.unwrap();
+        let f = match DynamicLibrary::open(Some(Path::new(&dylib))) {
+            Ok(f) => f,
+            Err(err) => return Err(format!("Unable to open {}: {}", dylib, err)),
+        };
 
         let g = unsafe{match f.symbol("gelpia_func") {
-            Ok(func) => transmute::<*mut u32, fn(&Vec<GI>, &Vec<GI>)->(GI, Option<Vec<GI>>)>(func),
-            Err(err) => panic!("Could not load function: {}", err),
+            Ok(func) => transmute::<*mut u32, CompiledFunc>(func),
+            Err(err) => return Err(format!("Could not load function: {}", err)),
+        }};
+        let b = unsafe{match f.symbol("gelpia_func_batch") {
+            Ok(func) => Some(transmute::<*mut u32, CompiledBatchFunc>(func)),
+            Err(_) => None,
+        }};
+        let p = unsafe{match f.symbol("gelpia_func_point_batch") {
+            Ok(func) => Some(transmute::<*mut u32, CompiledPointFunc>(func)),
+            Err(_) => None,
         }};
-        self.set(g, f);
+        self.set(g, b, p, f);
+        Ok(())
     }
 }
 
 
-
 #[test]
 fn test1() {
-    let constants = vec![GI::new_c("1"), GI::new_c("2"), GI::new_c("3")];
-    let f = FuncObj::new(&constants,
-                         &"c0,c1,o-,c2,o+".to_string());
-    let expected = ((GI::new_c("1") - GI::new_c("2")) + GI::new_c("3")).to_string();
-    assert!(f.call(&vec![]).to_string() == expected);
+    let constants = vec![GI::new_c("1").unwrap(), GI::new_c("2").unwrap(), GI::new_c("3").unwrap()];
+    let f = FuncObj::new(&constants, &vec![],
+                         &"c0,c1,o-,c2,o+".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let expected = ((GI::new_c("1").unwrap() - GI::new_c("2").unwrap()) + GI::new_c("3").unwrap()).to_string();
+    assert!(f.call(&vec![]).0.to_string() == expected);
 }
 
 #[test]
 fn test2() {
-    let consts = vec![GI::new_c("1"), GI::new_c("2"), GI::new_c("3")];
-    let f = FuncObj::new(&consts,
-                         &"c0,fsin,c1,o*,fcos,c2,o/".to_string());
+    let consts = vec![GI::new_c("1").unwrap(), GI::new_c("2").unwrap(), GI::new_c("3").unwrap()];
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0,fsin,c1,o*,fcos,c2,o/".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = cos(sin(consts[0]) * consts[1])/consts[2];
-    assert!(f.call(&vec![]).to_string() == expected.to_string());
+    assert!(f.call(&vec![]).0.to_string() == expected.to_string());
 }
 
 #[test]
@@ -238,108 +793,704 @@ fn sum_inputs() {
     let consts = vec![];
     let v = vec![GI::new_d(1.0, 1.0), GI::new_d(1.0, 2.0), GI::new_d(1.0, 3.0),
                  GI::new_d(1.0, 4.0), GI::new_d(1.0, 5.0), GI::new_d(1.0, 6.0)];
-    let f = FuncObj::new(&consts,
-                         &"i0,i1, o+, i2, o+, i3, o+, i4, o+, i5, o+".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"i0,i1, o+, i2, o+, i3, o+, i4, o+, i5, o+".to_string(), 6,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = (v[0] + v[1] + v[2] + v[3] + v[4] + v[5]).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
 
 #[test]
 fn test3() {
-    let consts = vec![GI::new_c("0.1")];
+    let consts = vec![GI::new_c("0.1").unwrap()];
     let v = vec![];
-    let f = FuncObj::new(&consts,
-                &"c0, fsin".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, fsin".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = sin(consts[0]).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
 
 #[test]
 fn test4() {
-    let consts = vec![GI::new_c("[-3, 4]")];
+    let consts = vec![GI::new_c("[-3, 4]").unwrap()];
     let v = vec![];
-    let f = FuncObj::new(&consts,
-                &"c0, fabs".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, fabs".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = abs(consts[0]).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
 
 #[test]
 fn test5() {
-    let consts = vec![GI::new_c("[-3, 4]")];
+    let consts = vec![GI::new_c("[-3, 4]").unwrap()];
     let v = vec![];
-    let f = FuncObj::new(&consts,
-                &"c0, fcos".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, fcos".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = cos(consts[0]).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
 
 #[test]
 fn test6() {
-    let consts = vec![GI::new_c("[-3, 4]")];
+    let consts = vec![GI::new_c("[-3, 4]").unwrap()];
     let v = vec![];
-    let f = FuncObj::new(&consts,
-                &"c0, ftan".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, ftan".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = tan(consts[0]).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
 
 #[test]
 fn test7() {
-    let consts = vec![GI::new_c("[-3, 4]")];
+    let consts = vec![GI::new_c("[-3, 4]").unwrap()];
     let v = vec![];
-    let f = FuncObj::new(&consts,
-                &"c0, fexp".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, fexp".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = exp(consts[0]).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
 
 #[test]
 fn test8() {
-    let consts = vec![GI::new_c("[-3, 4]")];
+    let consts = vec![GI::new_c("[-3, 4]").unwrap()];
     let v = vec![];
-    let f = FuncObj::new(&consts,
-                &"c0, fneg".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, fneg".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = (-consts[0]).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
 
 #[test]
 fn test9() {
-    let consts = vec![GI::new_c("[0.1, .2]")];
+    let consts = vec![GI::new_c("[0.1, .2]").unwrap()];
     let v = vec![];
-    let f = FuncObj::new(&consts,
-                &"c0, flog".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, flog".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = log(consts[0]).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
 #[test]
 fn test10() {
-    let consts = vec![GI::new_c("[2.0, 3.0]"),
-                      GI::new_c("[1.5, 2.5]")];
+    let consts = vec![GI::new_c("[2.0, 3.0]").unwrap(),
+                      GI::new_c("[1.5, 2.5]").unwrap()];
     let v = vec![];
-    let f = FuncObj::new(&consts,
-                         &"c0, c1, op".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, c1, op".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = powi(consts[0], consts[1]).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
 #[test]
 fn test_power() {
-    let consts = vec![GI::new_c("[2.0, 3.0]"),
-                      GI::new_c("[1.5, 2.5]")];
+    let consts = vec![GI::new_c("[2.0, 3.0]").unwrap(),
+                      GI::new_c("[1.5, 2.5]").unwrap()];
     let v = vec![];
-    let f = FuncObj::new(&consts,
-                         &"c0, p-1, c1, p2, o+".to_string());
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, p-1, c1, p2, o+".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
     let expected = (pow(consts[0], -1) + pow(consts[1], 2)).to_string();
-    let real = f.call(&v).to_string();
+    let real = f.call(&v).0.to_string();
+    assert!(real == expected, "real = {}, expected = {}", real, expected);
+}
+
+#[test]
+fn verify_underflow() {
+    let consts = vec![GI::new_c("1").unwrap()];
+    let f = FuncObj::new(&consts, &vec![],
+                         &"c0, o+".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret);
+    assert!(f.is_err());
+}
+
+#[test]
+fn verify_index_bounds() {
+    let consts = vec![GI::new_c("1").unwrap()];
+    let bad_const = FuncObj::new(&consts, &vec![],
+                                 &"c1".to_string(), 0,
+                                 false, "test".to_string(),
+                                 CompilePolicy::Interpret);
+    assert!(bad_const.is_err());
+    let bad_input = FuncObj::new(&consts, &vec![],
+                                 &"c0, i2, o*".to_string(), 2,
+                                 false, "test".to_string(),
+                                 CompilePolicy::Interpret);
+    assert!(bad_input.is_err());
+}
+
+#[test]
+fn verify_unknown_and_leftover() {
+    let consts = vec![GI::new_c("1").unwrap()];
+    let unknown = FuncObj::new(&consts, &vec![],
+                               &"c0, fcot".to_string(), 0,
+                               false, "test".to_string(),
+                               CompilePolicy::Interpret);
+    assert!(unknown.is_err());
+    let leftover = FuncObj::new(&consts, &vec![],
+                                &"c0, c0".to_string(), 0,
+                                false, "test".to_string(),
+                                CompilePolicy::Interpret);
+    assert!(leftover.is_err());
+    let multibyte = FuncObj::new(&consts, &vec![],
+                                 &"c0, é1".to_string(), 0,
+                                 false, "test".to_string(),
+                                 CompilePolicy::Interpret);
+    assert!(multibyte.err().unwrap().contains("unknown instruction"));
+}
+
+#[test]
+fn user_vars() {
+    let consts = vec![GI::new_c("2").unwrap()];
+    let mut f = FuncObj::new(&consts, &vec![GI::new_c("1").unwrap()],
+                             &"c0, v0, o*".to_string(), 0,
+                             false, "test".to_string(),
+                             CompilePolicy::Interpret).unwrap();
+    let v = vec![];
+    assert!(f.call(&v).0.to_string() == (consts[0] * GI::new_c("1").unwrap()).to_string());
+    f.set_user_var(0, GI::new_c("[3, 4]").unwrap()).unwrap();
+    let expected = (consts[0] * GI::new_c("[3, 4]").unwrap()).to_string();
+    let real = f.call(&v).0.to_string();
+    assert!(real == expected, "real = {}, expected = {}", real, expected);
+    assert!(f.set_user_var(1, GI::new_c("0").unwrap()).is_err());
+}
+
+#[test]
+fn register_matches_reference() {
+    let consts = vec![GI::new_c("[0.5, 0.75]").unwrap(), GI::new_c("3").unwrap()];
+    let v = vec![GI::new_d(-1.0, 2.0), GI::new_d(0.25, 0.5)];
+    let f = FuncObj::new(&consts, &vec![],
+                         &"i0, c0, o*, fsin, i1, p2, c1, i0, o-, o/, o+, fexp".to_string(), 2,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let expected = f.call_reference(&v).to_string();
+    let real = f.call(&v).0.to_string();
     assert!(real == expected, "real = {}, expected = {}", real, expected);
 }
+
+#[test]
+fn register_single_leaf() {
+    let consts = vec![];
+    let v = vec![GI::new_d(-1.0, 2.0)];
+    let f = FuncObj::new(&consts, &vec![],
+                         &"i0".to_string(), 1,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let real = f.call(&v).0.to_string();
+    assert!(real == v[0].to_string(), "real = {}, expected = {}", real, v[0].to_string());
+}
+
+#[test]
+fn batch_matches_call() {
+    let consts = vec![GI::new_c("[0.5, 0.75]").unwrap(), GI::new_c("3").unwrap()];
+    let f = FuncObj::new(&consts, &vec![],
+                         &"i0, c0, o*, fsin, i1, p2, c1, i0, o-, o/, o+, fexp".to_string(), 2,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let xs = vec![vec![GI::new_d(-1.0, 2.0), GI::new_p(0.5), GI::new_p(-3.0)],
+                  vec![GI::new_d(0.25, 0.5), GI::new_p(1.5), GI::new_d(2.0, 4.0)]];
+    let real = f.call_batch(&xs);
+    assert!(real.len() == 3);
+    for j in 0..3 {
+        let expected = f.call(&vec![xs[0][j], xs[1][j]]).0.to_string();
+        assert!(real[j].to_string() == expected, "real = {}, expected = {}",
+                real[j].to_string(), expected);
+    }
+}
+
+#[test]
+fn point_bounds_interval() {
+    let consts = vec![GI::new_c("0.1").unwrap(), GI::new_c("3").unwrap()];
+    let f = FuncObj::new(&consts, &vec![],
+                         &"i0, c0, o*, fsin, i1, p2, c1, i0, o-, o/, o+, i1, o*".to_string(), 2,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let xs = vec![vec![0.5, -1.25, 2.0e10], vec![1.5, 0.1, -3.0]];
+    let real = f.call_point_batch(&xs);
+    for j in 0..3 {
+        let x = vec![xs[0][j], xs[1][j]];
+        let exact = f.call(&x.iter().map(|&xi| GI::new_p(xi)).collect()).0;
+        assert!(real[j] <= exact.upper(), "real = {}, enclosure = {}",
+                real[j], exact.to_string());
+        assert!(real[j] == f.call_point(&x));
+    }
+    assert!(real[0] >= f.call(&vec![GI::new_p(0.5), GI::new_p(1.5)]).0.lower() - 1e-12);
+}
+
+#[test]
+fn input_kinds() {
+    let mut f = FuncObj::new(&vec![], &vec![], &"i0,i1,o+".to_string(), 2,
+                             false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    f.set_input_kinds(vec![InputKind::Real, InputKind::Real]);
+    assert!(!f.has_discrete_inputs());
+    assert!(InputKind::values(vec![]).is_err());
+    assert!(InputKind::values(vec![1.0, std::f64::NAN]).is_err());
+    let values = InputKind::values(vec![4.0, 1.0, 2.0, 1.0]).unwrap();
+    assert!(values == InputKind::Values(vec![1.0, 2.0, 4.0]));
+    f.set_input_kinds(vec![InputKind::Integer, values]);
+    assert!(f.has_discrete_inputs());
+
+    let names = vec!["x".to_string(), "y".to_string()];
+    let domain = vec![GI::new_d(-1.5, 2.0), GI::new_d(0.0, 8.0)];
+    let script = f.smtlib(&names, &[], &domain, None, Some(6.0)).unwrap();
+    assert!(script.contains("(set-logic QF_NIRA)"), "{}", script);
+    assert!(script.contains("(assert (is_int x))"), "{}", script);
+    assert!(script.contains("(assert (or (= y 1.0) (= y 2.0) (= y 4.0)))"), "{}", script);
+}
+
+#[cfg(test)]
+fn sample_def() -> FuncDef {
+    let mut metadata = std::collections::BTreeMap::new();
+    metadata.insert("source".to_string(), "test".to_string());
+    FuncDef{instructions: "i0, c0, o*, v0, o+, i1, p2, o-".to_string(),
+            constants: vec![GI::new_c("0.1").unwrap()],
+            names: vec!["x".to_string(), "y".to_string()],
+            domain: vec![GI::new_d(-1.0, 2.0), GI::new_c("[0.1, 0.3]").unwrap()],
+            kinds: vec![InputKind::Integer, InputKind::values(vec![0.3, 0.1, 0.2]).unwrap()],
+            param_names: vec!["a".to_string()],
+            params: vec![GI::new_c("1e-300").unwrap()],
+            constraints: vec!["i0, i1, o+, v0, o-".to_string()],
+            equalities: vec!["i0, i1, o*, c0, o-".to_string()],
+            metadata: metadata}
+}
+
+#[cfg(test)]
+fn same_def(a: &FuncDef, b: &FuncDef) -> bool {
+    let bits = |v: &Vec<GI>| v.iter()
+        .map(|x| (x.lower().to_bits(), x.upper().to_bits()))
+        .collect::<Vec<_>>();
+    a.instructions.replace(" ", "") == b.instructions.replace(" ", "") &&
+        bits(&a.constants) == bits(&b.constants) &&
+        a.names == b.names && bits(&a.domain) == bits(&b.domain) && a.kinds == b.kinds &&
+        a.param_names == b.param_names && bits(&a.params) == bits(&b.params) &&
+        a.constraints.iter().map(|c| c.replace(" ", "")).collect::<Vec<_>>() ==
+        b.constraints.iter().map(|c| c.replace(" ", "")).collect::<Vec<_>>() &&
+        a.equalities.iter().map(|h| h.replace(" ", "")).collect::<Vec<_>>() ==
+        b.equalities.iter().map(|h| h.replace(" ", "")).collect::<Vec<_>>() &&
+        a.metadata == b.metadata
+}
+
+#[test]
+fn format_round_trip() {
+    let def = sample_def();
+    let from_json = FuncDef::from_json(&def.to_json()).unwrap();
+    assert!(same_def(&def, &from_json), "JSON round trip changed the definition");
+    let from_binary = FuncDef::from_binary(&def.to_binary()).unwrap();
+    assert!(same_def(&def, &from_binary), "binary round trip changed the definition");
+
+    let f = from_binary.build(false, "test".to_string(),
+                              CompilePolicy::Interpret).unwrap();
+    let g = def.build(false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    let x = vec![GI::new_p(0.5), GI::new_p(0.25)];
+    assert!(f.call(&x).0.to_string() == g.call(&x).0.to_string());
+    assert!(f.tape() == "i0,c0,o*,v0,o+,i1,p2,o-");
+}
+
+#[test]
+fn format_rejects_bad_input() {
+    let def = sample_def();
+    let json = def.to_json().replace("\"version\": 4", "\"version\": 99");
+    assert!(FuncDef::from_json(&json).is_err());
+    let mut binary = def.to_binary();
+    binary.push(0);
+    assert!(FuncDef::from_binary(&binary).is_err());
+    binary.truncate(10);
+    assert!(FuncDef::from_binary(&binary).is_err());
+    let mut bad = def.clone();
+    bad.instructions = "i0, i2, o+".to_string();
+    assert!(FuncDef::from_binary(&bad.to_binary()).is_err());
+    let mut bad = def.clone();
+    bad.kinds.pop();
+    assert!(bad.check().is_err());
+    let json = def.to_json().replace("\"integer\"", "\"natural\"");
+    assert!(FuncDef::from_json(&json).is_err());
+}
+
+#[test]
+fn conditionals() {
+    // abs(x) written as a clamp: ite(x < 0, -x, x)
+    let consts = vec![GI::new_c("0").unwrap()];
+    let f = FuncObj::new(&consts, &vec![],
+                         &"i0, c0, o<, i0, fneg, i0, oite".to_string(), 1,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let cases = [(GI::new_d(-3.0, -1.0), "[1, 3]"),
+                 (GI::new_d(2.0, 4.0), "[2, 4]"),
+                 (GI::new_d(-1.0, 2.0), "[-2, 2]")];
+    for &(x, expected) in cases.iter() {
+        let expected = GI::new_c(expected).unwrap().to_string();
+        let real = f.call(&vec![x]).0.to_string();
+        assert!(real == expected, "real = {}, expected = {}", real, expected);
+        assert!(f.call_reference(&vec![x]).to_string() == expected);
+    }
+    assert!(f.call_point(&vec![-2.0]) == 2.0);
+
+    let g = FuncObj::new(&consts, &vec![],
+                         &"i0, c0, o>=, i0, c0, o<=, o*".to_string(), 1,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    assert!(g.call(&vec![GI::new_p(0.0)]).0.to_string() ==
+            GI::new_p(1.0).to_string());
+    assert!(g.call(&vec![GI::new_d(0.0, 1.0)]).0.to_string() ==
+            GI::new_d(0.0, 1.0).to_string());
+    assert!(FuncObj::new(&consts, &vec![], &"i0, c0, oite".to_string(), 1,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).is_err());
+}
+
+
+#[test]
+fn local_slots() {
+    let consts = vec![GI::new_c("2").unwrap()];
+    let x = vec![GI::new_d(0.5, 1.5), GI::new_d(-1.0, 2.0)];
+    // sin(x0)*sin(x0) + sin(x0) with the sine kept in slot 0
+    let f = FuncObj::new(&consts, &vec![],
+                         &"i0, fsin, s0, l0, o*, l0, o+".to_string(), 2,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let expected = (sin(x[0])*sin(x[0]) + sin(x[0])).to_string();
+    assert!(f.call(&x).0.to_string() == expected);
+    assert!(f.call_reference(&x).to_string() == expected);
+    let batch = f.call_batch(&vec![vec![x[0], x[0]], vec![x[1], x[1]]]);
+    assert!(batch.iter().all(|y| y.to_string() == expected));
+
+    assert!(FuncObj::new(&consts, &vec![], &"i0, l0, o+".to_string(), 1,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).is_err());
+}
+
+#[test]
+fn shared_subexpressions() {
+    let consts = vec![GI::new_c("2").unwrap()];
+    let x = vec![GI::new_d(0.5, 1.5), GI::new_d(-1.0, 2.0)];
+    let tape = "i0, i1, o*, c0, o+, fexp, i0, i1, o*, c0, o+, fexp, o*, \
+                i0, i1, o*, o-";
+    let insts = parse_instructions(tape).unwrap();
+    let mut d = dag::Dag::new();
+    let root = d.read(&insts, |d, op, args| d.node(op.clone(), args));
+    let shared = d.write(root).iter().map(|i| i.to_string())
+        .collect::<Vec<_>>().join(",");
+    assert!(shared == "i0,i1,o*,s0,c0,o+,fexp,s1,l1,o*,l0,o-",
+            "shared = {}", shared);
+
+    let f = FuncObj::new(&consts, &vec![], &tape.to_string(), 2,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    assert!(f.call(&x).0.to_string() == f.call_reference(&x).to_string());
+    assert!(f.unoptimized().call(&x).0.to_string() == f.call_reference(&x).to_string());
+    assert!(f.call_point(&vec![1.0, 0.5]) <= (2.5f64).exp().powi(2) - 0.5);
+}
+
+#[test]
+fn optimize_tapes() {
+    let consts = vec![GI::new_c("1").unwrap(), GI::new_c("0").unwrap(),
+                      GI::new_c("0.1").unwrap(), GI::new_c("3").unwrap()];
+    let optimized = |tape: &str| {
+        let insts = parse_instructions(tape).unwrap();
+        let (insts, c) = opt::optimize(&insts, &consts);
+        (insts.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(","), c)
+    };
+
+    // Constant subtrees are folded with outward rounding.
+    let (tape, c) = optimized("c2, c3, o/, fsin, i0, o+");
+    assert!(tape == format!("c{},i0,o+", c.len() - 1), "tape = {}", tape);
+    assert!(c.last().unwrap().to_string() == sin(consts[2]/consts[3]).to_string());
+
+    // Identities
+    assert!(optimized("i0, c0, o*, c1, o+, c0, o/, c1, osub2").0 == "i0");
+    assert!(optimized("i0, c1, i1, o-, o+").0 == "i0,i1,o-");
+    assert!(optimized("i0, i1, fneg, fneg, o-, i0, i0, osub2, o+").0 == "i0,i1,o-");
+    assert!(optimized("c0, c1, o>, i1, i0, oite").0 == "i1");
+    assert!(optimized("i0, c1, o>, i1, i1, oite").0 == "i1");
+
+    // x*x is squared, which is tighter than the product
+    let (tape, _) = optimized("i0, fsin, i0, fsin, o*");
+    assert!(tape == "i0,fsin,p2", "tape = {}", tape);
+    let f = FuncObj::new(&consts, &vec![], &"i0, i0, o*".to_string(), 1,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let x = vec![GI::new_d(-1.0, 2.0)];
+    assert!(f.call(&x).0.to_string() == GI::new_d(0.0, 4.0).to_string());
+    assert!(f.call_reference(&x).to_string() == GI::new_d(-2.0, 4.0).to_string());
+}
+
+#[cfg(test)]
+fn plus_one(x: GI) -> GI {
+    x + GI::new_p(1.0)
+}
+
+#[test]
+fn plugin_functions() {
+    let consts = vec![GI::new_c("2").unwrap()];
+    let tape = "i0, fplus_one, c0, o*".to_string();
+    assert!(plugin::register("plus_one", plus_one, None).is_ok());
+    assert!(plugin::register("sin", plus_one, None).is_err());
+    assert!(plugin::register("bad,name", plus_one, None).is_err());
+    assert!(plugin::names().contains(&"plus_one".to_string()));
+
+    let f = FuncObj::new(&consts, &vec![], &tape, 1,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let x = vec![GI::new_d(1.0, 2.0)];
+    let expected = GI::new_d(4.0, 6.0).to_string();
+    assert!(f.call(&x).0.to_string() == expected);
+    assert!(f.call_reference(&x).to_string() == expected);
+    let p = f.call_point(&vec![0.5]);
+    assert!(p <= 3.0 && p > 3.0 - 1e-12, "p = {}", p);
+
+    // Constant arguments are folded through the plugin as well
+    let g = FuncObj::new(&consts, &vec![], &"c0, fplus_one".to_string(), 0,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    assert!(g.call(&vec![]).0.to_string() == GI::new_p(3.0).to_string());
+
+    assert!(FuncObj::new(&consts, &vec![], &"i0, fnot_registered".to_string(), 1,
+                         false, "test".to_string(),
+                         CompilePolicy::Interpret).is_err());
+}
+
+#[test]
+fn render_formats() {
+    let consts = vec![GI::new_c("1").unwrap(), GI::new_c("2").unwrap()];
+    let f = FuncObj::new(&consts, &vec![GI::new_p(3.0)],
+                         &"i0, i1, c0, o+, o*, fsin, p2, v0, i1, fneg, o-, o/".to_string(),
+                         2, false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let names = vec!["x".to_string(), "y_1".to_string()];
+    let params = vec!["a".to_string()];
+
+    let infix = f.render(Format::Infix, &names, &params, None);
+    assert!(infix == "sin(x*(y_1 + 1))^2/(a - -y_1)", "infix = {}", infix);
+    let latex = f.render(Format::Latex, &names, &params, None);
+    assert!(latex == "\\frac{\\sin\\left(x \\cdot \\left(\\mathit{y\\_1} + 1\\right)\\right)^{2}}\
+                      {a - -\\mathit{y\\_1}}", "latex = {}", latex);
+    let dot = f.render(Format::Dot, &[], &[], None);
+    assert!(dot.starts_with("digraph function {") && dot.contains("label=\"x1\""));
+
+    // Enclosures over a box, the root comes first
+    let domain = vec![GI::new_d(0.0, 1.0), GI::new_d(1.0, 2.0)];
+    let annotated = f.render(Format::Infix, &names, &params, Some(&domain));
+    let lines: Vec<&str> = annotated.lines().collect();
+    assert!(lines[0] == infix);
+    assert!(lines[1] == format!("  {}  {}", render::interval(f.call(&domain).0), infix),
+            "annotated = {}", annotated);
+    assert!(lines.contains(&"  [2, 3]  y_1 + 1"));
+    assert!(f.render(Format::Dot, &names, &params, Some(&domain)).contains("\\n[2, 3]"));
+    assert!("svg".parse::<Format>().is_err());
+}
+
+#[test]
+fn fpcore_reader() {
+    let text = "
+        ; from the FPBench suite
+        (FPCore (x y)
+          :name \"example\"
+          :precision binary64
+          :pre (and (<= -1 x 1) (< 0.5 y) (<= y 2))
+          (let ([t (+ x 1/3)])
+            (if (< x 0) (/ t y) (fma t t (sin PI)))))
+        (FPCore fabs_err ((! :precision binary32 z))
+          :pre (<= 0 z 1e-3)
+          (- (fabs z) (pow z 2)))
+        (FPCore (a) :pre (<= 0 a 1) (cbrt a))
+        (FPCore (a) :pre (<= 0 a) a)";
+
+    let def = fpcore::read(text, None).unwrap();
+    assert!(def.names == vec!["x", "y"]);
+    assert!(def.domain[0].to_string() == GI::new_d(-1.0, 1.0).to_string());
+    assert!(def.domain[1].to_string() == GI::new_d(0.5, 2.0).to_string());
+    assert!(def.metadata["name"] == "example");
+    let f = def.build(false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    let x = vec![GI::new_p(-0.5), GI::new_p(1.0)];
+    let third = GI::new_c("1").unwrap()/GI::new_c("3").unwrap();
+    let expected = (x[0] + third)/x[1];
+    assert!(f.call(&x).0.to_string() == expected.to_string());
+
+    let def = fpcore::read(text, Some("fabs_err")).unwrap();
+    assert!(def.names == vec!["z"] && def.domain[0].upper() >= 1e-3);
+    assert!(def.instructions == "i0,fabs,i0,p2,o-", "{}", def.instructions);
+
+    let err = fpcore::read("(FPCore (a) :pre (<= 0 a 1)\n (cbrt a))", None)
+        .err().unwrap();
+    assert!(err == "Line 2: unsupported operator 'cbrt'", "{}", err);
+    let err = fpcore::read("(FPCore (a) :pre (<= 0 a) a)", None).err().unwrap();
+    assert!(err.contains("no upper bound"), "{}", err);
+    assert!(fpcore::read(text, Some("missing")).is_err());
+    assert!(fpcore::read("(FPCore (a) :pre (<= 0 a 1) (+ a", None).is_err());
+}
+
+#[test]
+fn smtlib_scripts() {
+    let c = GI::new_d(0.0625, 0.125);
+    let f = FuncObj::new(&vec![c], &vec![], &"i0,i1,o<,i0,c0,o*,fexp,i1,p2,o+,i1,oite"
+                         .to_string(), 2, false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    let names = vec!["x".to_string(), "y".to_string()];
+    let domain = vec![GI::new_d(-1.0, 2.0), GI::new_d(0.0, 0.75)];
+    let script = f.smtlib(&names, &[], &domain, None, Some(2.5)).unwrap();
+    assert!(script.contains("(assert (and (<= (- 1.0) x) (<= x 2.0)))"), "{}", script);
+    assert!(script.contains("(<= y (/ 3.0 4.0))"), "{}", script);
+    assert!(script.contains("(assert (and (<= (/ 1.0 16.0) c!0) (<= c!0 (/ 1.0 8.0))))"),
+            "{}", script);
+    assert!(script.contains("(assert (> objective (/ 5.0 2.0)))"), "{}", script);
+    assert!(f.smtlib(&names, &[], &domain, None, Some(std::f64::INFINITY)).is_err());
+    assert!(f.smtlib(&names, &[], &domain, Some(-1.0), Some(2.5)).unwrap()
+            .contains("(assert (or (< objective (- 1.0)) (> objective (/ 5.0 2.0))))"));
+
+    // Interval constants come back as inputs bounded by their interval
+    let def = smtlib::read(&script).unwrap();
+    assert!(def.names == vec!["x", "y", "c!0"], "{:?}", def.names);
+    assert!(def.domain[1].to_string() == domain[1].to_string());
+    assert!(def.domain[2].to_string() == c.to_string());
+    let g = def.build(false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    for x in &[vec![GI::new_p(1.5), GI::new_p(0.5)], vec![GI::new_p(-1.0), GI::new_p(0.0)]] {
+        let mut with_c = x.clone();
+        with_c.push(c);
+        assert!(f.call(x).0.to_string() == g.call(&with_c).0.to_string());
+    }
+
+    let text = "
+        (set-logic QF_NRA)
+        (declare-fun a () Real)
+        (declare-const b Real)
+        (assert (and (<= (- 1) a 1) (< (/ 1 2) b)))
+        (assert (<= b 3.5))
+        (define-fun g () Real (let ((s (* a b))) (ite (> s 0) (arcsin a) (- s))))
+        (assert (>= g 0.25))
+        (check-sat)";
+    let def = smtlib::read(text).unwrap();
+    assert!(def.domain[0].to_string() == GI::new_d(-1.0, 1.0).to_string());
+    assert!(def.domain[1].to_string() == GI::new_d(0.5, 3.5).to_string());
+    assert!(def.instructions == "i0,i1,o*,c0,o>,i0,fasin,i0,i1,o*,fneg,oite",
+            "{}", def.instructions);
+
+    let err = smtlib::read("(declare-fun n () Int)").err().unwrap();
+    assert!(err == "Line 1: 'n' is not Real, only real variables are supported",
+            "{}", err);
+    let err = smtlib::read("(declare-fun a () Real)\n(assert (<= 0 a 1))\n\
+                            (assert (> (* a a) (+ a 1)))").err().unwrap();
+    assert!(err.starts_with("Line 3: unsupported assertion"), "{}", err);
+    assert!(smtlib::read("(declare-fun a () Real)\n(assert (<= 0 a 1))").is_err());
+}
+
+#[test]
+fn constraints() {
+    // x*y subject to x + y - v0 <= 0 and -x <= 0
+    let mut f = FuncObj::new(&vec![], &vec![GI::new_p(1.0)], &"i0,i1,o*".to_string(), 2,
+                             false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    assert!(!f.has_constraints());
+    assert!(f.feasibility(&vec![GI::new_d(5.0, 6.0), GI::new_p(0.0)]) ==
+            Feasibility::Feasible);
+    f.add_constraint(&"i0,i1,o+,v0,o-".to_string(), 2).unwrap();
+    f.add_constraint(&"i0,fneg".to_string(), 2).unwrap();
+    assert!(f.add_constraint(&"i2".to_string(), 2).is_err());
+    assert!(f.constraint_tapes() == vec!["i0,i1,o+,v0,o-", "i0,fneg"]);
+
+    let boxed = |a: f64, b: f64, c: f64, d: f64| vec![GI::new_d(a, b), GI::new_d(c, d)];
+    assert!(f.feasibility(&boxed(0.0, 0.25, 0.0, 0.5)) == Feasibility::Feasible);
+    assert!(f.feasibility(&boxed(1.0, 2.0, 0.5, 1.0)) == Feasibility::Infeasible);
+    assert!(f.feasibility(&boxed(-1.0, -0.5, 0.0, 1.0)) == Feasibility::Infeasible);
+    assert!(f.feasibility(&boxed(0.0, 1.0, 0.0, 1.0)) == Feasibility::Undecided);
+    let v = f.violations(&vec![vec![GI::new_p(0.5), GI::new_p(-1.0), GI::new_p(2.0)],
+                               vec![GI::new_p(0.25), GI::new_p(0.0), GI::new_p(0.0)]]);
+    assert!(v == vec![0.0, 1.0, 1.0], "{:?}", v);
+    // The negated function keeps the constraints, parameters reach them
+    assert!(f.negated().feasibility(&boxed(1.0, 2.0, 0.5, 1.0)) == Feasibility::Infeasible);
+    f.set_user_var(0, GI::new_p(4.0)).unwrap();
+    assert!(f.feasibility(&boxed(1.0, 2.0, 0.5, 1.0)) == Feasibility::Feasible);
+
+    let names = vec!["x".to_string(), "y".to_string()];
+    let script = f.smtlib(&names, &["v".to_string()], &boxed(-1.0, 2.0, 0.0, 1.0),
+                          None, Some(2.0)).unwrap();
+    assert!(script.contains("(define-fun constraint!1 () Real"), "{}", script);
+    assert!(script.contains("(assert (<= constraint!0 0.0))"), "{}", script);
+    let script = f.smtlib(&names, &["v".to_string()], &boxed(-1.0, 2.0, 0.0, 1.0),
+                          Some(std::f64::INFINITY), Some(std::f64::NEG_INFINITY)).unwrap();
+    assert!(!script.contains("(> objective") && !script.contains("(< objective"),
+            "{}", script);
+}
+
+#[test]
+fn empty_constraints() {
+    // x subject to sqrt(x) - 1 <= 0, and to sqrt(x) = 0 in g
+    let new = || FuncObj::new(&vec![GI::new_p(1.0)], &vec![], &"i0".to_string(), 1,
+                              false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    let mut f = new();
+    f.add_constraint(&"i0,fsqrt,c0,o-".to_string(), 1).unwrap();
+    let mut g = new();
+    g.add_equality(&"i0,fsqrt".to_string(), 1).unwrap();
+    g.set_equality_tolerance(1.0).unwrap();
+
+    // The square root of a negative box is empty
+    let negative = vec![GI::new_d(-2.0, -1.0)];
+    assert!(f.feasibility(&negative) == Feasibility::Infeasible);
+    assert!(g.feasibility(&negative) == Feasibility::Infeasible);
+    let points = vec![vec![GI::new_p(-1.0), GI::new_p(0.25)]];
+    for v in vec![f.violations(&points), g.violations(&points)] {
+        assert!(v[0] == std::f64::INFINITY && v[1] == 0.0, "{:?}", v);
+    }
+}
+
+#[test]
+fn equalities() {
+    // x + y subject to x*y - 1 = 0
+    let consts = vec![GI::new_p(1.0)];
+    let mut f = FuncObj::new(&consts, &vec![], &"i0,i1,o+".to_string(), 2,
+                             false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    f.add_equality(&"i0,i1,o*,c0,o-".to_string(), 2).unwrap();
+    assert!(f.add_equality(&"i3".to_string(), 2).is_err());
+    assert!(f.has_constraints() && f.equality_tapes() == vec!["i0,i1,o*,c0,o-"]);
+    assert!(f.set_equality_tolerance(-1.0).is_err());
+    assert!(f.set_equality_tolerance(std::f64::NAN).is_err());
+
+    let boxed = |a: f64, b: f64, c: f64, d: f64| vec![GI::new_d(a, b), GI::new_d(c, d)];
+    assert!(f.feasibility(&boxed(2.0, 3.0, 2.0, 3.0)) == Feasibility::Infeasible);
+    assert!(f.feasibility(&boxed(0.5, 2.0, 0.5, 2.0)) == Feasibility::Undecided);
+    assert!(f.feasibility(&boxed(1.0, 1.0, 1.0, 1.0)) == Feasibility::Feasible);
+    // The points (1, 1) and (1, 1.25), batched per input
+    let points = vec![vec![GI::new_p(1.0), GI::new_p(1.0)],
+                      vec![GI::new_p(1.0), GI::new_p(1.25)]];
+    assert!(f.violations(&points) == vec![0.0, 0.25]);
+
+    // Within the tolerance, boxes close to the curve count as feasible
+    f.set_equality_tolerance(0.5).unwrap();
+    assert!(f.equality_tolerance() == 0.5);
+    assert!(f.feasibility(&boxed(1.0, 1.25, 1.0, 1.0)) == Feasibility::Feasible);
+    assert!(f.feasibility(&boxed(2.0, 3.0, 2.0, 3.0)) == Feasibility::Infeasible);
+    assert!(f.violations(&points) == vec![0.0, 0.0]);
+    assert!(f.negated().equalities().len() == 1);
+
+    let names = vec!["x".to_string(), "y".to_string()];
+    let script = f.smtlib(&names, &[], &boxed(0.0, 2.0, 0.0, 2.0), None, Some(3.0)).unwrap();
+    assert!(script.contains("(define-fun equality!0 () Real"), "{}", script);
+    let bounded = "(assert (and (<= (- (/ 1.0 2.0)) equality!0) (<= equality!0 (/ 1.0 2.0))))";
+    assert!(script.contains(bounded), "{}", script);
+    f.set_equality_tolerance(0.0).unwrap();
+    let script = f.smtlib(&names, &[], &boxed(0.0, 2.0, 0.0, 2.0), None, Some(3.0)).unwrap();
+    assert!(script.contains("(assert (= equality!0 0.0))"), "{}", script);
+}
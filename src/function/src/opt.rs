@@ -0,0 +1,157 @@
+// Algebraic simplification of instruction tapes.
+//
+// Every tape is optimized when a function is built, whatever produced it.
+// Subterms that only read constants are folded into a single interval constant
+// computed with the same outward rounded operations the interpreter would use,
+// so folding never loses rigor. Identities are only applied when they hold
+// exactly in interval arithmetic, or when the rewritten form is a tighter
+// enclosure of the same real function.
+
+use gr::GI;
+
+use super::OpType;
+use super::dag::Dag;
+use super::vm::apply_op;
+
+// Returns an optimized tape equivalent to the given one along with the
+// constants it reads, the given constants followed by any folded ones. The
+// tape must be verified.
+pub fn optimize(tape: &Vec<OpType>, consts: &Vec<GI>) -> (Vec<OpType>, Vec<GI>) {
+    let mut consts = consts.clone();
+    let mut dag = Dag::new();
+    let root = dag.read(tape, |dag, op, args| {
+        simplify(dag, &mut consts, op.clone(), args)
+    });
+    (dag.write(root), consts)
+}
+
+fn constant(dag: &Dag, consts: &Vec<GI>, id: usize) -> Option<GI> {
+    match dag.nodes[id].op {
+        OpType::Const(i) => Some(consts[i]),
+        _ => None,
+    }
+}
+
+// Whether the node is the constant point [v, v].
+fn is_point(dag: &Dag, consts: &Vec<GI>, id: usize, v: f64) -> bool {
+    constant(dag, consts, id).map_or(false, |c| c.lower() == v && c.upper() == v)
+}
+
+// The operand of the node if it is a negation.
+fn negated(dag: &Dag, id: usize) -> Option<usize> {
+    match dag.nodes[id].op {
+        OpType::Func(ref s) if s == "neg" => Some(dag.nodes[id].args[0]),
+        _ => None,
+    }
+}
+
+// Node for a constant with the given value, reusing an equal constant.
+fn make_const(dag: &mut Dag, consts: &mut Vec<GI>, value: GI) -> usize {
+    let index = consts.iter().position(|c| {
+        c.lower().to_bits() == value.lower().to_bits() &&
+            c.upper().to_bits() == value.upper().to_bits()
+    });
+    let index = match index {
+        Some(i) => i,
+        None => {
+            consts.push(value);
+            consts.len() - 1
+        }
+    };
+    dag.node(OpType::Const(index), vec![])
+}
+
+fn simplify(dag: &mut Dag, consts: &mut Vec<GI>, op: OpType, args: Vec<usize>)
+            -> usize {
+    if args.is_empty() {
+        return dag.node(op, args);
+    }
+
+    let values: Option<Vec<GI>> =
+        args.iter().map(|&a| constant(dag, consts, a)).collect();
+    if let Some(values) = values {
+        let value = apply_op(&op, &values);
+        return make_const(dag, consts, value);
+    }
+
+    let a = args[0];
+    let b = *args.last().unwrap();
+    match op {
+        OpType::Op(ref s) if s == "+" => {
+            if is_point(dag, consts, a, 0.0) {
+                return b;
+            }
+            if is_point(dag, consts, b, 0.0) {
+                return a;
+            }
+            if let Some(y) = negated(dag, b) {
+                return simplify(dag, consts, OpType::Op("-".to_string()), vec![a, y]);
+            }
+            if let Some(y) = negated(dag, a) {
+                return simplify(dag, consts, OpType::Op("-".to_string()), vec![b, y]);
+            }
+        },
+        OpType::Op(ref s) if s == "-" => {
+            if is_point(dag, consts, b, 0.0) {
+                return a;
+            }
+            if is_point(dag, consts, a, 0.0) {
+                return simplify(dag, consts, OpType::Func("neg".to_string()), vec![b]);
+            }
+            if let Some(y) = negated(dag, b) {
+                return simplify(dag, consts, OpType::Op("+".to_string()), vec![a, y]);
+            }
+        },
+        OpType::Op(ref s) if s == "*" => {
+            if is_point(dag, consts, a, 1.0) {
+                return b;
+            }
+            if is_point(dag, consts, b, 1.0) {
+                return a;
+            }
+            // Tighter than the product, which does not know both operands are
+            // the same value.
+            if a == b {
+                return simplify(dag, consts, OpType::Pow(2), vec![a]);
+            }
+        },
+        OpType::Op(ref s) if s == "/" => {
+            if is_point(dag, consts, b, 1.0) {
+                return a;
+            }
+        },
+        OpType::Op(ref s) if s == "sub2" => {
+            // sub2(x, 0) is exactly x and sub2(x, x) is the real value 0.
+            if is_point(dag, consts, b, 0.0) {
+                return a;
+            }
+            if a == b {
+                return make_const(dag, consts, GI::new_p(0.0));
+            }
+        },
+        OpType::Op(ref s) if s == "ite" => {
+            let (then_val, else_val) = (args[1], args[2]);
+            if then_val == else_val {
+                return then_val;
+            }
+            if let Some(cond) = constant(dag, consts, a) {
+                if cond.lower() > 0.0 || cond.upper() < 0.0 {
+                    return then_val;
+                }
+                if cond.lower() == 0.0 && cond.upper() == 0.0 {
+                    return else_val;
+                }
+            }
+        },
+        OpType::Func(ref s) if s == "neg" => {
+            if let Some(y) = negated(dag, a) {
+                return y;
+            }
+        },
+        OpType::Pow(1) => {
+            return a;
+        },
+        _ => (),
+    }
+    dag.node(op, args)
+}
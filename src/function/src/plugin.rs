@@ -0,0 +1,124 @@
+// Registry of the interval functions applied by "f<name>" instructions.
+//
+// The built in functions are always available. Further functions can be
+// registered through register, or loaded from a dynamic library that exports
+// gelpia_plugins. A FuncObj resolves the functions its tape uses when it is
+// built, so registering a function only affects functions built afterwards.
+
+use std::mem::transmute;
+use std::path::Path;
+use std::sync::RwLock;
+
+use dylib::DynamicLibrary;
+
+use gr::GI;
+use gr::point;
+use gr::point::EP;
+
+pub type IntervalFunc = fn(GI) -> GI;
+pub type PointFunc = fn(EP) -> EP;
+
+// Signature of gelpia_plugins, returns the name and interval function of
+// everything a plugin library provides.
+pub type PluginList = fn() -> Vec<(String, IntervalFunc)>;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Function {
+    // Encloses the range of the function over an interval.
+    pub interval: IntervalFunc,
+    // Evaluates the function with error bounded point arithmetic. Functions
+    // without a point version are evaluated on the enclosing interval.
+    pub point: Option<PointFunc>,
+}
+
+fn neg(x: GI) -> GI {
+    -x
+}
+
+fn point_neg(x: EP) -> EP {
+    -x
+}
+
+const BUILTINS: [(&'static str, IntervalFunc, Option<PointFunc>); 19] =
+    [("abs", gr::abs, Some(point::abs)),
+     ("sin", gr::sin, None),
+     ("asin", gr::asin, None),
+     ("cos", gr::cos, None),
+     ("acos", gr::acos, None),
+     ("tan", gr::tan, None),
+     ("atan", gr::atan, None),
+     ("exp", gr::exp, None),
+     ("log", gr::log, None),
+     ("neg", neg, Some(point_neg)),
+     ("sqrt", gr::sqrt, None),
+     ("sinh", gr::sinh, None),
+     ("cosh", gr::cosh, None),
+     ("tanh", gr::tanh, None),
+     ("asinh", gr::asinh, None),
+     ("acosh", gr::acosh, None),
+     ("atanh", gr::atanh, None),
+     ("floor_power2", gr::floor_power2, None),
+     ("sym_interval", gr::sym_interval, None)];
+
+static PLUGINS: RwLock<Vec<(String, Function)>> = RwLock::new(Vec::new());
+
+// Registers interval as the function applied by "f<name>", replacing any
+// plugin registered under the same name. The point version is optional. The
+// interval version must return an enclosure of the function's range over its
+// argument, or results are no longer rigorous.
+pub fn register(name: &str, interval: IntervalFunc, point: Option<PointFunc>)
+                -> Result<(), String> {
+    if name.is_empty() ||
+        !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("Invalid plugin function name '{}', expected \
+                            letters, digits and underscores", name));
+    }
+    if BUILTINS.iter().any(|b| b.0 == name) {
+        return Err(format!("Plugin function '{}' would replace the built in \
+                            function of the same name", name));
+    }
+    let function = Function{interval: interval, point: point};
+    let mut plugins = PLUGINS.write().unwrap();
+    match plugins.iter().position(|p| p.0 == name) {
+        Some(i) => plugins[i].1 = function,
+        None => plugins.push((name.to_string(), function)),
+    }
+    Ok(())
+}
+
+// Registers every function exported by the plugin library at path and
+// returns their names.
+pub fn load(path: &Path) -> Result<Vec<String>, String> {
+    let lib = match DynamicLibrary::open(Some(path)) {
+        Ok(l) => l,
+        Err(err) => return Err(format!("Unable to open plugin library {}: {}",
+                                       path.display(), err)),
+    };
+    let list = unsafe{match lib.symbol("gelpia_plugins") {
+        Ok(func) => transmute::<*mut u32, PluginList>(func),
+        Err(err) => return Err(format!("Could not load plugin list from {}: {}",
+                                       path.display(), err)),
+    }};
+    let functions = list();
+    for &(ref name, f) in &functions {
+        register(name, f, None)?;
+    }
+    // The registered functions live in the library, so it is never closed.
+    ::std::mem::forget(lib);
+    Ok(functions.into_iter().map(|(name, _)| name).collect())
+}
+
+// The function applied by "f<name>", if there is one.
+pub fn lookup(name: &str) -> Option<Function> {
+    if let Some(b) = BUILTINS.iter().find(|b| b.0 == name) {
+        return Some(Function{interval: b.1, point: b.2});
+    }
+    PLUGINS.read().unwrap().iter().find(|p| p.0 == name).map(|p| p.1)
+}
+
+// Names of all functions that can be used, built in ones first.
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = BUILTINS.iter().map(|b| b.0.to_string()).collect();
+    names.extend(PLUGINS.read().unwrap().iter().map(|p| p.0.clone()));
+    names
+}
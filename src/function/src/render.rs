@@ -0,0 +1,262 @@
+// Human readable views of instruction tapes for debugging.
+//
+// A tape can be rendered as infix, as LaTeX or as a Graphviz DAG. When a box
+// is given every operation is annotated with its enclosure over that box, which
+// shows where overestimation comes from.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use gr::GI;
+
+use super::OpType;
+use super::dag::Dag;
+use super::vm::apply_op;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Infix,
+    Latex,
+    Dot,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Format, String> {
+        match s {
+            "infix" => Ok(Format::Infix),
+            "latex" => Ok(Format::Latex),
+            "dot" => Ok(Format::Dot),
+            _ => Err(format!("Unknown function format '{}', expected one of \
+                              infix, latex or dot", s)),
+        }
+    }
+}
+
+// Binding strength of each kind of term, higher binds tighter.
+const COMPARE: u8 = 0;
+const SUM: u8 = 1;
+const PRODUCT: u8 = 2;
+const NEGATION: u8 = 3;
+const POWER: u8 = 4;
+const ATOM: u8 = 5;
+
+fn float(x: f64) -> String {
+    if x != 0.0 && x.is_finite() && (x.abs() >= 1e16 || x.abs() < 1e-5) {
+        format!("{:e}", x)
+    } else {
+        format!("{}", x)
+    }
+}
+
+pub fn interval(x: GI) -> String {
+    format!("[{}, {}]", float(x.lower()), float(x.upper()))
+}
+
+struct Renderer<'a> {
+    dag: Dag,
+    root: usize,
+    consts: &'a Vec<GI>,
+    names: &'a [String],
+    param_names: &'a [String],
+    // Enclosure of every node over the box, when one is given.
+    values: Option<Vec<GI>>,
+}
+
+impl<'a> Renderer<'a> {
+    fn input(&self, i: usize) -> String {
+        self.names.get(i).cloned().unwrap_or(format!("x{}", i))
+    }
+
+    fn param(&self, i: usize) -> String {
+        self.param_names.get(i).cloned().unwrap_or(format!("v{}", i))
+    }
+
+    fn constant(&self, i: usize) -> String {
+        let c = self.consts[i];
+        if c.lower() == c.upper() { float(c.lower()) } else { interval(c) }
+    }
+
+    // Label of a node on its own, without its operands.
+    fn label(&self, id: usize) -> String {
+        match self.dag.nodes[id].op {
+            OpType::Const(i) => self.constant(i),
+            OpType::Var(i) => self.input(i),
+            OpType::UVar(i) => self.param(i),
+            OpType::Pow(n) => format!("^{}", n),
+            OpType::Func(ref s) | OpType::Op(ref s) => s.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    // Renders node id as infix, returning the text and its binding strength.
+    fn infix(&self, id: usize) -> (String, u8) {
+        let node = &self.dag.nodes[id];
+        let arg = |k: usize, min: u8| {
+            let (s, p) = self.infix(node.args[k]);
+            if p < min { format!("({})", s) } else { s }
+        };
+        match node.op {
+            OpType::Const(i) if self.consts[i].lower() < 0.0 &&
+                self.consts[i].lower() == self.consts[i].upper() =>
+                (self.constant(i), NEGATION),
+            OpType::Const(_) | OpType::Var(_) | OpType::UVar(_) =>
+                (self.label(id), ATOM),
+            OpType::Pow(n) => (format!("{}^{}", arg(0, ATOM), n), POWER),
+            OpType::Func(ref s) if s == "neg" =>
+                (format!("-{}", arg(0, POWER)), NEGATION),
+            OpType::Func(ref s) => (format!("{}({})", s, arg(0, COMPARE)), ATOM),
+            OpType::Op(ref s) => match s.as_str() {
+                "+" => (format!("{} + {}", arg(0, SUM), arg(1, PRODUCT)), SUM),
+                "-" => (format!("{} - {}", arg(0, SUM), arg(1, PRODUCT)), SUM),
+                "*" => (format!("{}*{}", arg(0, PRODUCT), arg(1, NEGATION)), PRODUCT),
+                "/" => (format!("{}/{}", arg(0, PRODUCT), arg(1, NEGATION)), PRODUCT),
+                "<" | "<=" | ">" | ">=" =>
+                    (format!("{} {} {}", arg(0, SUM), s, arg(1, SUM)), COMPARE),
+                "p" => (format!("pow({}, {})", arg(0, COMPARE), arg(1, COMPARE)), ATOM),
+                "ite" => (format!("ite({}, {}, {})", arg(0, COMPARE), arg(1, COMPARE),
+                                  arg(2, COMPARE)), ATOM),
+                _ => (format!("{}({}, {})", s, arg(0, COMPARE), arg(1, COMPARE)), ATOM),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    fn latex_name(name: &str) -> String {
+        let escaped = name.replace("_", "\\_");
+        if name.chars().count() == 1 {
+            escaped
+        } else {
+            format!("\\mathit{{{}}}", escaped)
+        }
+    }
+
+    // Renders node id as LaTeX, returning the text and its binding strength.
+    fn latex(&self, id: usize) -> (String, u8) {
+        let node = &self.dag.nodes[id];
+        let arg = |k: usize, min: u8| {
+            let (s, p) = self.latex(node.args[k]);
+            if p < min { format!("\\left({}\\right)", s) } else { s }
+        };
+        let (text, strength) = match node.op {
+            OpType::Const(i) if self.consts[i].lower() < 0.0 &&
+                self.consts[i].lower() == self.consts[i].upper() =>
+                (self.constant(i), NEGATION),
+            OpType::Const(_) => (self.label(id), ATOM),
+            OpType::Var(i) => (Renderer::latex_name(&self.input(i)), ATOM),
+            OpType::UVar(i) => (Renderer::latex_name(&self.param(i)), ATOM),
+            OpType::Pow(n) => (format!("{}^{{{}}}", arg(0, ATOM), n), POWER),
+            OpType::Func(ref s) => match s.as_str() {
+                "neg" => (format!("-{}", arg(0, POWER)), NEGATION),
+                "abs" => (format!("\\left|{}\\right|", arg(0, COMPARE)), ATOM),
+                "sqrt" => (format!("\\sqrt{{{}}}", arg(0, COMPARE)), ATOM),
+                "exp" => (format!("e^{{{}}}", arg(0, COMPARE)), POWER),
+                "sin" | "cos" | "tan" | "sinh" | "cosh" | "tanh" | "log" =>
+                    (format!("\\{}\\left({}\\right)", s, arg(0, COMPARE)), ATOM),
+                "asin" | "acos" | "atan" =>
+                    (format!("\\arc{}\\left({}\\right)", &s[1..], arg(0, COMPARE)),
+                     ATOM),
+                _ => (format!("\\operatorname{{{}}}\\left({}\\right)",
+                              s.replace("_", "\\_"), arg(0, COMPARE)), ATOM),
+            },
+            OpType::Op(ref s) => match s.as_str() {
+                "+" => (format!("{} + {}", arg(0, SUM), arg(1, PRODUCT)), SUM),
+                "-" => (format!("{} - {}", arg(0, SUM), arg(1, PRODUCT)), SUM),
+                "*" => (format!("{} \\cdot {}", arg(0, PRODUCT), arg(1, NEGATION)),
+                        PRODUCT),
+                "/" => (format!("\\frac{{{}}}{{{}}}", arg(0, COMPARE), arg(1, COMPARE)),
+                        ATOM),
+                "p" => (format!("{}^{{{}}}", arg(0, ATOM), arg(1, COMPARE)), POWER),
+                "<" | ">" => (format!("{} {} {}", arg(0, SUM), s, arg(1, SUM)), COMPARE),
+                "<=" => (format!("{} \\le {}", arg(0, SUM), arg(1, SUM)), COMPARE),
+                ">=" => (format!("{} \\ge {}", arg(0, SUM), arg(1, SUM)), COMPARE),
+                "ite" => (format!("\\begin{{cases}} {} & \\text{{if }} {} \\\\ {} & \
+                                   \\text{{otherwise}} \\end{{cases}}",
+                                  arg(1, COMPARE), arg(0, COMPARE), arg(2, COMPARE)),
+                          ATOM),
+                _ => (format!("\\operatorname{{{}}}\\left({}, {}\\right)",
+                              s.replace("_", "\\_"), arg(0, COMPARE), arg(1, COMPARE)),
+                      ATOM),
+            },
+            _ => unreachable!(),
+        };
+        match self.values {
+            Some(ref values) if !node.args.is_empty() =>
+                (format!("\\underbrace{{{}}}_{{{}}}", text, interval(values[id])),
+                 ATOM),
+            _ => (text, strength),
+        }
+    }
+
+    fn dot(&self) -> String {
+        let mut out = String::new();
+        writeln!(out, "digraph function {{").unwrap();
+        writeln!(out, "    ordering=out;").unwrap();
+        for (id, node) in self.dag.nodes.iter().enumerate() {
+            let mut label = self.label(id);
+            if let Some(ref values) = self.values {
+                label = format!("{}\\n{}", label, interval(values[id]));
+            }
+            let shape = if node.args.is_empty() { "box" } else { "ellipse" };
+            writeln!(out, "    n{} [label=\"{}\", shape={}];", id, label, shape).unwrap();
+            for &a in &node.args {
+                writeln!(out, "    n{} -> n{};", id, a).unwrap();
+            }
+        }
+        writeln!(out, "    n{} [peripheries=2];", self.root).unwrap();
+        out.push('}');
+        out
+    }
+
+    // The infix expression, followed by a table of every operation and its
+    // enclosure when a box is given.
+    fn infix_table(&self) -> String {
+        let mut out = self.infix(self.root).0;
+        if let Some(ref values) = self.values {
+            for id in (0..self.dag.nodes.len()).rev() {
+                if !self.dag.nodes[id].args.is_empty() {
+                    write!(out, "\n  {}  {}", interval(values[id]), self.infix(id).0)
+                        .unwrap();
+                }
+            }
+        }
+        out
+    }
+}
+
+// Renders a verified tape. Inputs and user variables are shown with the given
+// names, or as x<n> and v<n> when there are not enough names. When domain is
+// given operations are annotated with their enclosure over it.
+pub fn render(tape: &Vec<OpType>, consts: &Vec<GI>, user_vars: &Vec<GI>,
+              format: Format, names: &[String], param_names: &[String],
+              domain: Option<&[GI]>) -> String {
+    let mut dag = Dag::new();
+    let root = dag.read(tape, |d, op, args| d.node(op.clone(), args));
+
+    let values = domain.map(|x| {
+        let mut values: Vec<GI> = Vec::with_capacity(dag.nodes.len());
+        // Operands are always created before the nodes using them.
+        for node in &dag.nodes {
+            let v = match node.op {
+                OpType::Const(i) => consts[i],
+                OpType::Var(i) => x[i],
+                OpType::UVar(i) => user_vars[i],
+                ref op => {
+                    let args: Vec<GI> = node.args.iter().map(|&a| values[a]).collect();
+                    apply_op(op, &args)
+                }
+            };
+            values.push(v);
+        }
+        values
+    });
+
+    let r = Renderer{dag: dag, root: root, consts: consts, names: names,
+                     param_names: param_names, values: values};
+    match format {
+        Format::Infix => r.infix_table(),
+        Format::Latex => r.latex(root).0,
+        Format::Dot => r.dot(),
+    }
+}
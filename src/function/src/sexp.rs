@@ -0,0 +1,127 @@
+// S-expression reader shared by the FPCore and SMT-LIB front ends.
+//
+// Square brackets are accepted as parentheses, comments run from ';' to the end
+// of the line and symbols may be quoted with '|'. Every node remembers the line
+// it starts on for diagnostics.
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Sexp {
+    Atom(String, usize),
+    Str(String, usize),
+    List(Vec<Sexp>, usize),
+}
+
+impl Sexp {
+    pub fn line(&self) -> usize {
+        match self {
+            &Sexp::Atom(_, l) | &Sexp::Str(_, l) | &Sexp::List(_, l) => l,
+        }
+    }
+
+    pub fn atom(&self) -> Option<&str> {
+        match self {
+            &Sexp::Atom(ref s, _) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn list(&self) -> Option<&Vec<Sexp>> {
+        match self {
+            &Sexp::List(ref l, _) => Some(l),
+            _ => None,
+        }
+    }
+
+    // The head symbol of a list, such as "+" in (+ a b).
+    pub fn head(&self) -> Option<&str> {
+        self.list().and_then(|l| l.first()).and_then(|h| h.atom())
+    }
+}
+
+// Reads all top level expressions of text.
+pub fn parse(text: &str) -> Result<Vec<Sexp>, String> {
+    let mut chars = text.chars().peekable();
+    let mut line = 1;
+    // Open lists with the line they started on and their closing character.
+    let mut open: Vec<(Vec<Sexp>, usize, char)> = Vec::new();
+    let mut top = Vec::new();
+
+    while let Some(c) = chars.next() {
+        let item = match c {
+            '\n' => {
+                line += 1;
+                continue;
+            },
+            ';' => {
+                while let Some(&c) = chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    chars.next();
+                }
+                continue;
+            },
+            '(' | '[' => {
+                open.push((Vec::new(), line, if c == '(' { ')' } else { ']' }));
+                continue;
+            },
+            ')' | ']' => match open.pop() {
+                Some((items, start, close)) => {
+                    if c != close {
+                        return Err(format!("Line {}: '{}' closes the list opened \
+                                            on line {}", line, c, start));
+                    }
+                    Sexp::List(items, start)
+                },
+                None => return Err(format!("Line {}: unmatched '{}'", line, c)),
+            },
+            '"' | '|' => {
+                let start = line;
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') if c == '"' => match chars.next() {
+                            Some(e) => s.push(e),
+                            None => break,
+                        },
+                        Some(e) if e == c => break,
+                        Some(e) => {
+                            if e == '\n' {
+                                line += 1;
+                            }
+                            s.push(e);
+                        },
+                        None => return Err(format!("Line {}: unterminated {}",
+                                                   start, if c == '"' {
+                                                       "string"
+                                                   } else {
+                                                       "quoted symbol"
+                                                   })),
+                    }
+                }
+                if c == '"' { Sexp::Str(s, start) } else { Sexp::Atom(s, start) }
+            },
+            c if c.is_whitespace() => continue,
+            c => {
+                let mut s = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()[];\"|".contains(c) {
+                        break;
+                    }
+                    s.push(c);
+                    chars.next();
+                }
+                Sexp::Atom(s, line)
+            }
+        };
+        match open.last_mut() {
+            Some(&mut (ref mut items, _, _)) => items.push(item),
+            None => top.push(item),
+        }
+    }
+
+    if let Some(&(_, start, _)) = open.last() {
+        return Err(format!("Line {}: list is never closed", start));
+    }
+    Ok(top)
+}
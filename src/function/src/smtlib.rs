@@ -0,0 +1,505 @@
+// SMT-LIB 2 export and import of objectives over their domains.
+//
+// An exported script declares the inputs, asserts the domain, the admissible
+// values of discrete inputs, the constraints and the equalities within their
+// tolerance, and asserts that the objective exceeds an upper bound or falls
+// below a lower bound. unsat confirms the bounds over the feasible domain,
+// while a model refutes them. Floats are written as exact rationals, interval
+// constants and parameters become variables constrained to their interval,
+// and functions beyond arithmetic use dReal's names. Scripts of the same shape
+// can be read back: declared Real constants become the inputs, bounded by the
+// assertions on them, and the objective is the term asserted to exceed a
+// constant.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs::File;
+use std::io::Read;
+
+use gr::{GI, InputKind, kind_of};
+
+use super::{FuncDef, OpType};
+use super::dag::Dag;
+use super::fpcore::{Builder, Dialect, number};
+use super::sexp;
+use super::sexp::Sexp;
+
+// Functions written under their own name.
+const FUNCTIONS: [&'static str; 12] =
+    ["sqrt", "exp", "log", "sin", "cos", "tan", "asin", "acos", "atan", "sinh",
+     "cosh", "tanh"];
+
+// Decimal text of m * 2^e.
+fn scaled(m: u64, e: u32) -> String {
+    // Digits least significant first
+    let mut digits: Vec<u8> = m.to_string().bytes().rev().map(|b| b - b'0').collect();
+    for _ in 0..e {
+        let mut carry = 0;
+        for d in digits.iter_mut() {
+            let v = *d*2 + carry;
+            *d = v%10;
+            carry = v/10;
+        }
+        if carry > 0 {
+            digits.push(carry);
+        }
+    }
+    digits.iter().rev().map(|&d| (b'0' + d) as char).collect()
+}
+
+// The exact value of a finite float as an SMT-LIB real.
+fn real(x: f64) -> String {
+    if x == 0.0 {
+        return "0.0".to_string();
+    }
+    let bits = x.to_bits();
+    let biased = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = bits & ((1 << 52) - 1);
+    let (mut m, mut e) = if biased == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | (1 << 52), biased - 1075)
+    };
+    while m & 1 == 0 {
+        m >>= 1;
+        e += 1;
+    }
+    let abs = if e >= 0 {
+        format!("{}.0", scaled(m, e as u32))
+    } else {
+        format!("(/ {}.0 {}.0)", m, scaled(1, -e as u32))
+    };
+    if x < 0.0 { format!("(- {})", abs) } else { abs }
+}
+
+// Quotes names that are not simple SMT-LIB symbols.
+fn symbol(name: &str) -> String {
+    let simple = name.chars().next().map_or(false, |c| !c.is_digit(10)) &&
+        name.chars().all(|c| c.is_ascii_alphanumeric() || "~!@$%^&*_-+=<>.?/".contains(c));
+    if simple { name.to_string() } else { format!("|{}|", name) }
+}
+
+// Declares a real variable and asserts that it lies in range.
+fn declare(out: &mut String, name: &str, range: GI) {
+    writeln!(out, "(declare-fun {} () Real)", name).unwrap();
+    let mut facts = Vec::new();
+    if range.lower().is_finite() {
+        facts.push(format!("(<= {} {})", real(range.lower()), name));
+    }
+    if range.upper().is_finite() {
+        facts.push(format!("(<= {} {})", name, real(range.upper())));
+    }
+    match facts.len() {
+        0 => (),
+        1 => writeln!(out, "(assert {})", facts[0]).unwrap(),
+        _ => writeln!(out, "(assert (and {}))", facts.join(" ")).unwrap(),
+    }
+}
+
+// The term computing op from the terms of its operands.
+fn operation(op: &OpType, a: &[&str]) -> Result<String, String> {
+    Ok(match *op {
+        OpType::Pow(0) => "1.0".to_string(),
+        OpType::Pow(n) => {
+            let product = if n.abs() == 1 {
+                a[0].to_string()
+            } else {
+                format!("(* {})", vec![a[0]; n.abs() as usize].join(" "))
+            };
+            if n > 0 { product } else { format!("(/ 1.0 {})", product) }
+        },
+        OpType::Func(ref f) => match f.as_str() {
+            "neg" => format!("(- {})", a[0]),
+            "abs" => format!("(ite (<= 0.0 {0}) {0} (- {0}))", a[0]),
+            f if FUNCTIONS.contains(&f) => format!("({} {})", f, a[0]),
+            f => return Err(format!("Function '{}' has no SMT-LIB counterpart", f)),
+        },
+        OpType::Op(ref s) => match s.as_str() {
+            "+" | "-" | "*" | "/" => format!("({} {} {})", s, a[0], a[1]),
+            // sub2 encloses the real difference
+            "sub2" => format!("(- {} {})", a[0], a[1]),
+            "p" => format!("(pow {} {})", a[0], a[1]),
+            "<" | "<=" | ">" | ">=" => format!("(ite ({} {} {}) 1.0 0.0)", s, a[0], a[1]),
+            "ite" => format!("(ite (= {} 0.0) {} {})", a[0], a[2], a[1]),
+            _ => unreachable!(),
+        },
+        _ => unreachable!(),
+    })
+}
+
+// Defines the verified tape as the Real term name. Operations are bound by
+// nested lets in tape order, so shared subterms are written once. Interval
+// constants and parameters are declared the first time a tape reads them.
+fn define<F>(out: &mut String, declared: &mut Vec<String>, name: &str,
+             tape: &Vec<OpType>, consts: &Vec<GI>, user_vars: &Vec<GI>,
+             input: &F, param_names: &[String]) -> Result<(), String>
+    where F: Fn(usize) -> String {
+    let mut dag = Dag::new();
+    let root = dag.read(tape, |d, op, args| d.node(op.clone(), args));
+
+    let mut terms: Vec<String> = Vec::with_capacity(dag.nodes.len());
+    let mut lets = Vec::new();
+    for (id, node) in dag.nodes.iter().enumerate() {
+        let (range, var) = match node.op {
+            OpType::Var(i) => {
+                terms.push(input(i));
+                continue;
+            },
+            OpType::Const(i) => (consts[i], format!("c!{}", i)),
+            OpType::UVar(i) => (user_vars[i], symbol(&param_names.get(i).cloned()
+                                                     .unwrap_or(format!("v{}", i)))),
+            ref op => {
+                let term = {
+                    let args: Vec<&str> = node.args.iter().map(|&a| terms[a].as_str())
+                        .collect();
+                    operation(op, &args)?
+                };
+                lets.push(format!("(let ((t!{} {}))", id, term));
+                terms.push(format!("t!{}", id));
+                continue;
+            },
+        };
+        if range.lower() == range.upper() {
+            terms.push(real(range.lower()));
+        } else {
+            if !declared.contains(&var) {
+                declare(out, &var, range);
+                declared.push(var.clone());
+            }
+            terms.push(var);
+        }
+    }
+
+    writeln!(out, "(define-fun {} () Real", name).unwrap();
+    for l in &lets {
+        writeln!(out, "  {}", l).unwrap();
+    }
+    writeln!(out, "  {}{})", terms[root], ")".repeat(lets.len())).unwrap();
+    Ok(())
+}
+
+// Writes a script asserting that the verified tape falls below lower or exceeds
+// upper somewhere in domain where the constraints hold, at least one bound
+// must be given. Inputs and user variables are named as in render, constraint
+// i is defined as constraint!i and equality i as equality!i.
+pub(crate) fn export(tape: &Vec<OpType>, constraints: &[Vec<OpType>],
+                     equalities: &[Vec<OpType>], eps: f64,
+                     consts: &Vec<GI>, user_vars: &Vec<GI>,
+                     names: &[String], param_names: &[String], domain: &[GI],
+                     kinds: &[InputKind], lower: Option<f64>, upper: Option<f64>)
+                     -> Result<String, String> {
+    // A lower bound of +inf or an upper bound of -inf claims that no point of
+    // the domain satisfies the constraints
+    let empty = lower == Some(f64::INFINITY) || upper == Some(f64::NEG_INFINITY);
+    for &b in lower.iter().chain(upper.iter()) {
+        if !b.is_finite() && !empty {
+            return Err(format!("Cannot export the bound {}, it is not finite", b));
+        }
+    }
+    if names.iter().chain(param_names).any(|n| n == "objective") {
+        return Err("The name 'objective' is used for the function in SMT-LIB \
+                    scripts, rename the input or parameter".to_string());
+    }
+
+    let mut out = String::new();
+    let claim = match (lower, upper) {
+        _ if empty => "no point of the domain satisfies the constraints".to_string(),
+        (Some(lo), Some(hi)) => format!("the objective lies in [{}, {}] on the domain",
+                                        lo, hi),
+        (Some(lo), None) => format!("the objective is at least {} on the domain", lo),
+        (None, Some(hi)) => format!("the objective is at most {} on the domain", hi),
+        (None, None) => return Err("No bound to export".to_string()),
+    };
+    writeln!(out, "; unsat confirms that {}", claim).unwrap();
+    // Integer inputs stay reals asserted to be integral, which needs the mixed
+    // logic
+    let logic = if kinds.contains(&InputKind::Integer) { "QF_NIRA" } else { "QF_NRA" };
+    writeln!(out, "(set-logic {})", logic).unwrap();
+    let input = |i: usize| symbol(&names.get(i).cloned().unwrap_or(format!("x{}", i)));
+    for (i, &x) in domain.iter().enumerate() {
+        declare(&mut out, &input(i), x);
+        match *kind_of(kinds, i) {
+            InputKind::Real => (),
+            InputKind::Integer => writeln!(out, "(assert (is_int {}))", input(i)).unwrap(),
+            InputKind::Values(ref vs) => {
+                let eqs: Vec<String> = vs.iter()
+                    .map(|&v| format!("(= {} {})", input(i), real(v))).collect();
+                if eqs.len() == 1 {
+                    writeln!(out, "(assert {})", eqs[0]).unwrap();
+                } else {
+                    writeln!(out, "(assert (or {}))", eqs.join(" ")).unwrap();
+                }
+            }
+        }
+    }
+
+    let mut declared = Vec::new();
+    define(&mut out, &mut declared, "objective", tape, consts, user_vars, &input,
+           param_names)?;
+    for (i, g) in constraints.iter().enumerate() {
+        let name = format!("constraint!{}", i);
+        define(&mut out, &mut declared, &name, g, consts, user_vars, &input,
+               param_names)?;
+        writeln!(out, "(assert (<= {} 0.0))", name).unwrap();
+    }
+    for (i, h) in equalities.iter().enumerate() {
+        let name = format!("equality!{}", i);
+        define(&mut out, &mut declared, &name, h, consts, user_vars, &input,
+               param_names)?;
+        if eps == 0.0 {
+            writeln!(out, "(assert (= {} 0.0))", name).unwrap();
+        } else {
+            writeln!(out, "(assert (and (<= {} {}) (<= {} {})))",
+                     real(-eps), name, name, real(eps)).unwrap();
+        }
+    }
+    // Any feasible point refutes a claim that there is none
+    if !empty {
+        let mut violations = Vec::new();
+        if let Some(lo) = lower {
+            violations.push(format!("(< objective {})", real(lo)));
+        }
+        if let Some(hi) = upper {
+            violations.push(format!("(> objective {})", real(hi)));
+        }
+        if violations.len() == 1 {
+            writeln!(out, "(assert {})", violations[0]).unwrap();
+        } else {
+            writeln!(out, "(assert (or {}))", violations.join(" ")).unwrap();
+        }
+    }
+    writeln!(out, "(check-sat)").unwrap();
+    write!(out, "(exit)").unwrap();
+    Ok(out)
+}
+
+enum Side {
+    Input(usize),
+    Value(GI),
+    Term,
+}
+
+struct Reader {
+    builder: Builder,
+    lower: Vec<Option<f64>>,
+    upper: Vec<Option<f64>>,
+    // Tapes of the terms named by define-fun.
+    defs: Vec<(String, Vec<String>)>,
+    objective: Option<Vec<String>>,
+}
+
+impl Reader {
+    // Encloses a term built from numbers only, None for any other term.
+    fn value(&self, e: &Sexp) -> Result<Option<GI>, String> {
+        if let Some(s) = e.atom() {
+            return number(s, e.line());
+        }
+        let op = e.head().unwrap_or("");
+        let args = match e.list() {
+            Some(l) if ["+", "-", "*", "/"].contains(&op) && l.len() > 1 => &l[1..],
+            _ => return Ok(None),
+        };
+        let mut values = Vec::new();
+        for a in args {
+            match self.value(a)? {
+                Some(v) => values.push(v),
+                None => return Ok(None),
+            }
+        }
+        if op == "-" && values.len() == 1 {
+            return Ok(Some(-values[0]));
+        }
+        let mut v = values[0];
+        for &w in &values[1..] {
+            v = match op {
+                "+" => v + w,
+                "-" => v - w,
+                "*" => v*w,
+                _ => v/w,
+            };
+        }
+        Ok(Some(v))
+    }
+
+    fn side(&self, e: &Sexp) -> Result<Side, String> {
+        if let Some(i) = e.atom().and_then(|s| self.builder.names.iter().position(|n| n == s)) {
+            return Ok(Side::Input(i));
+        }
+        Ok(match self.value(e)? {
+            Some(v) => Side::Value(v),
+            None => Side::Term,
+        })
+    }
+
+    fn set_lower(&mut self, i: usize, x: f64) {
+        self.lower[i] = Some(self.lower[i].map_or(x, |l| l.max(x)));
+    }
+
+    fn set_upper(&mut self, i: usize, x: f64) {
+        self.upper[i] = Some(self.upper[i].map_or(x, |u| u.min(x)));
+    }
+
+    // Records an assertion, which either bounds inputs by constants or states
+    // that the objective exceeds a constant. Strict and non strict bounds on
+    // inputs both give a closed domain.
+    fn assertion(&mut self, e: &Sexp) -> Result<(), String> {
+        let line = e.line();
+        let op = e.head().unwrap_or("");
+        let args = match e.list() {
+            Some(l) => &l[1..],
+            None => &[],
+        };
+        if op == "and" {
+            for a in args {
+                self.assertion(a)?;
+            }
+            return Ok(());
+        }
+        if !["<", "<=", ">", ">=", "="].contains(&op) || args.len() < 2 {
+            return Err(format!("Line {}: unsupported assertion, expected bounds on \
+                                inputs or (> objective bound)", line));
+        }
+        let mut sides = Vec::new();
+        for a in args {
+            sides.push(self.side(a)?);
+        }
+
+        if sides.iter().any(|s| match *s { Side::Term => true, _ => false }) {
+            let term = match (op, &sides[..]) {
+                (">", &[Side::Term, Side::Value(_)]) |
+                (">=", &[Side::Term, Side::Value(_)]) => &args[0],
+                ("<", &[Side::Value(_), Side::Term]) |
+                ("<=", &[Side::Value(_), Side::Term]) => &args[1],
+                _ => return Err(format!("Line {}: unsupported assertion, the \
+                                         objective must be asserted to exceed a \
+                                         constant", line)),
+            };
+            if self.objective.is_some() {
+                return Err(format!("Line {}: the objective is already asserted to \
+                                    exceed a bound", line));
+            }
+            self.objective = Some(self.builder.expr(term, &self.defs)?);
+            return Ok(());
+        }
+
+        if op == ">" || op == ">=" {
+            sides.reverse();
+        }
+        for pair in sides.windows(2) {
+            match (&pair[0], &pair[1]) {
+                (&Side::Value(c), &Side::Input(i)) => {
+                    self.set_lower(i, c.lower());
+                    if op == "=" {
+                        self.set_upper(i, c.upper());
+                    }
+                },
+                (&Side::Input(i), &Side::Value(c)) => {
+                    self.set_upper(i, c.upper());
+                    if op == "=" {
+                        self.set_lower(i, c.lower());
+                    }
+                },
+                (&Side::Input(_), &Side::Input(_)) =>
+                    return Err(format!("Line {}: relations between inputs are not \
+                                        supported", line)),
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn command(&mut self, cmd: &Sexp) -> Result<(), String> {
+        let line = cmd.line();
+        let op = match cmd.head() {
+            Some(op) => op,
+            None => return Err(format!("Line {}: expected a command", line)),
+        };
+        let args = &cmd.list().unwrap()[1..];
+        match op {
+            "set-logic" | "set-info" | "set-option" | "check-sat" | "get-model" |
+            "get-value" | "get-info" | "echo" | "exit" => (),
+            "declare-fun" | "declare-const" => {
+                let (name, sort) = match (op, args) {
+                    ("declare-fun", &[Sexp::Atom(ref n, _), Sexp::List(ref p, _), ref s])
+                        if p.is_empty() => (n, s),
+                    ("declare-const", &[Sexp::Atom(ref n, _), ref s]) => (n, s),
+                    _ => return Err(format!("Line {}: only constants can be declared",
+                                            line)),
+                };
+                if sort.atom() != Some("Real") {
+                    return Err(format!("Line {}: '{}' is not Real, only real \
+                                        variables are supported", line, name));
+                }
+                self.builder.names.push(name.clone());
+                self.lower.push(None);
+                self.upper.push(None);
+            },
+            "define-fun" => {
+                let (name, body) = match args {
+                    &[Sexp::Atom(ref n, _), Sexp::List(ref p, _), ref s, ref b]
+                        if p.is_empty() && s.atom() == Some("Real") => (n, b),
+                    _ => return Err(format!("Line {}: only Real terms without \
+                                             arguments can be defined", line)),
+                };
+                let tape = self.builder.expr(body, &self.defs)?;
+                self.defs.push((name.clone(), tape));
+            },
+            "assert" => match args {
+                &[ref a] => self.assertion(a)?,
+                _ => return Err(format!("Line {}: 'assert' expects 1 argument", line)),
+            },
+            _ => return Err(format!("Line {}: unsupported command '{}'", line, op)),
+        }
+        Ok(())
+    }
+}
+
+// Reads a script of real constant declarations, definitions of real terms and
+// assertions. Every input needs a lower and an upper bound, and exactly one
+// term has to be asserted to exceed a constant.
+pub fn read(text: &str) -> Result<FuncDef, String> {
+    let mut reader = Reader{builder: Builder{names: Vec::new(), constants: Vec::new(),
+                                             dialect: Dialect::SmtLib},
+                            lower: Vec::new(), upper: Vec::new(),
+                            defs: Vec::new(), objective: None};
+    for cmd in sexp::parse(text)? {
+        reader.command(&cmd)?;
+    }
+
+    let names = reader.builder.names.clone();
+    let mut domain = Vec::new();
+    for (i, n) in names.iter().enumerate() {
+        let (lo, hi) = match (reader.lower[i], reader.upper[i]) {
+            (Some(lo), Some(hi)) => (lo, hi),
+            (None, _) => return Err(format!("Input '{}' has no lower bound", n)),
+            (_, None) => return Err(format!("Input '{}' has no upper bound", n)),
+        };
+        if lo > hi {
+            return Err(format!("Input '{}' has an empty range", n));
+        }
+        domain.push(GI::new_d(lo, hi));
+    }
+    let tape = match reader.objective {
+        Some(t) => t,
+        None => return Err("No term is asserted to exceed a bound, expected \
+                            (assert (> objective bound))".to_string()),
+    };
+
+    let mut metadata = BTreeMap::new();
+    metadata.insert("source".to_string(), "smtlib".to_string());
+    let def = FuncDef{instructions: tape.join(","), constants: reader.builder.constants,
+                      names: names, domain: domain, kinds: vec![], param_names: vec![],
+                      params: vec![], constraints: vec![], equalities: vec![],
+                      metadata: metadata};
+    def.check()?;
+    Ok(def)
+}
+
+// Reads an SMT-LIB script from a file, see read.
+pub fn load(path: &str) -> Result<FuncDef, String> {
+    let mut text = String::new();
+    File::open(path)
+        .and_then(|mut f| f.read_to_string(&mut text))
+        .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+    read(&text)
+}
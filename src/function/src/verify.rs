@@ -0,0 +1,130 @@
+// Static checks on instruction tapes, run once when a FuncObj is built so that
+// evaluation never has to deal with a malformed tape.
+
+use super::OpType;
+use super::plugin;
+
+// Names accepted after an "o" instruction that take two operands.
+pub const OPERATORS: [&'static str; 10] =
+    ["+", "-", "*", "/", "p", "sub2", "<", "<=", ">", ">="];
+
+// Names accepted after an "o" instruction that take three operands.
+pub const TERNARY_OPERATORS: [&'static str; 1] = ["ite"];
+
+// Transforms the comma separated instruction string into a tape.
+pub fn parse_instructions(instructions: &str) -> Result<Vec<OpType>, String> {
+    let mut insts = vec![];
+
+    for (pos, inst) in instructions.split(',').enumerate() {
+        let inst = inst.trim();
+        if inst.is_empty() {
+            return Err(format!("Instruction {}: empty instruction", pos));
+        }
+        // The type may be any character, so split after it rather than after
+        // its first byte
+        let first_len = inst.chars().next().map_or(1, |c| c.len_utf8());
+        let (first, rest) = inst.split_at(first_len);
+        let index = || rest.parse::<usize>().map_err(
+            |_| format!("Instruction {} ('{}'): expected an index after '{}'",
+                        pos, inst, first));
+        insts.push(match first {
+            "c" => OpType::Const(index()?),
+            "i" => OpType::Var(index()?),
+            "v" => OpType::UVar(index()?),
+            "s" => OpType::Store(index()?),
+            "l" => OpType::Load(index()?),
+            "o" => OpType::Op(rest.to_string()),
+            "f" => OpType::Func(rest.to_string()),
+            "p" => OpType::Pow(rest.parse::<i32>().map_err(
+                |_| format!("Instruction {} ('{}'): expected an integer exponent",
+                            pos, inst))?),
+            _   => return Err(format!("Instruction {} ('{}'): unknown instruction \
+                                       type '{}'", pos, inst, first))
+        });
+    }
+
+    Ok(insts)
+}
+
+// Checks that the tape only uses known operations, only references existing
+// constants, inputs and user variables, only loads local slots that were
+// stored before, never pops an empty stack and leaves exactly one value behind.
+pub fn verify(insts: &Vec<OpType>, n_consts: usize, n_inputs: usize,
+              n_user_vars: usize) -> Result<(), String> {
+    let mut depth: usize = 0;
+    let mut stored: Vec<bool> = Vec::new();
+
+    for (pos, inst) in insts.iter().enumerate() {
+        let (pops, pushes) = match inst {
+            &OpType::Const(i) => {
+                if i >= n_consts {
+                    return Err(format!("Instruction {} ('{}'): constant index out \
+                                        of range, there are {} constants",
+                                       pos, inst, n_consts));
+                }
+                (0, 1)
+            },
+            &OpType::Var(i) => {
+                if i >= n_inputs {
+                    return Err(format!("Instruction {} ('{}'): input index out \
+                                        of range, there are {} inputs",
+                                       pos, inst, n_inputs));
+                }
+                (0, 1)
+            },
+            &OpType::UVar(i) => {
+                if i >= n_user_vars {
+                    return Err(format!("Instruction {} ('{}'): user variable \
+                                        index out of range, there are {} user \
+                                        variables", pos, inst, n_user_vars));
+                }
+                (0, 1)
+            },
+            &OpType::Func(ref s) => {
+                if plugin::lookup(s).is_none() {
+                    return Err(format!("Instruction {} ('{}'): unknown function \
+                                        '{}'", pos, inst, s));
+                }
+                (1, 1)
+            },
+            &OpType::Op(ref s) => {
+                if OPERATORS.contains(&s.as_str()) {
+                    (2, 1)
+                } else if TERNARY_OPERATORS.contains(&s.as_str()) {
+                    (3, 1)
+                } else {
+                    return Err(format!("Instruction {} ('{}'): unknown operator \
+                                        '{}'", pos, inst, s));
+                }
+            },
+            &OpType::Pow(_) => (1, 1),
+            &OpType::Store(i) => {
+                if stored.len() <= i {
+                    stored.resize(i + 1, false);
+                }
+                stored[i] = true;
+                (1, 1)
+            },
+            &OpType::Load(i) => {
+                if !stored.get(i).cloned().unwrap_or(false) {
+                    return Err(format!("Instruction {} ('{}'): local slot {} is \
+                                        loaded before it is stored", pos, inst, i));
+                }
+                (0, 1)
+            },
+        };
+
+        if depth < pops {
+            return Err(format!("Instruction {} ('{}'): needs {} operands but the \
+                                stack holds {}", pos, inst, pops, depth));
+        }
+        depth = depth - pops + pushes;
+    }
+
+    if depth != 1 {
+        return Err(format!("Tape leaves {} values on the stack, expected exactly 1",
+                           depth));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,410 @@
+// Register based interpreter for instruction tapes.
+//
+// The RPN tape is decoded once into instructions that operate on numbered
+// slots. Pushes of constants, inputs and user variables do not become
+// instructions, they are folded into the operands of the instruction that
+// consumes them. A value at stack depth d always lives in slot d, so the slot
+// file never needs more entries than the maximum stack depth of the tape.
+// Values kept in local slots by "s<n>" get one extra slot per store above the
+// stack slots, where later pushes can not overwrite them.
+
+use std::cell::RefCell;
+
+use gr::GI;
+use gr::point;
+use gr::point::EP;
+
+use super::OpType;
+use super::plugin;
+use super::plugin::Function;
+
+// Where an instruction reads an operand from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Src {
+    Slot(usize),
+    Const(usize),
+    Input(usize),
+    UVar(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Code {
+    // Binary operations, read a and b
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Powi,
+    Sub2,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    // Ternary operations, read a, b and c
+    Ite,
+    // Unary operations, read a
+    Pow(i32),
+    // Function from the plugin registry, an index into Program::funcs
+    Func(usize),
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Inst {
+    pub code: Code,
+    pub dst: usize,
+    pub a: Src,
+    pub b: Src,
+    pub c: Src,
+}
+
+#[derive(Clone, Debug)]
+pub struct Program {
+    pub insts: Vec<Inst>,
+    pub n_slots: usize,
+    pub result: Src,
+    // Functions used by Func instructions, resolved when decoding.
+    pub funcs: Vec<Function>,
+}
+
+thread_local! {
+    // Slot file reused by every evaluation on this thread.
+    static SCRATCH: RefCell<Vec<GI>> = RefCell::new(Vec::new());
+    // Slot file reused by every batched evaluation on this thread, slot k of
+    // point j lives at k*n + j.
+    static BATCH_SCRATCH: RefCell<Vec<GI>> = RefCell::new(Vec::new());
+    // Slot file for batched point evaluation, same layout as BATCH_SCRATCH.
+    static POINT_SCRATCH: RefCell<Vec<EP>> = RefCell::new(Vec::new());
+}
+
+fn decode_op(name: &str) -> Code {
+    match name {
+        "+" => Code::Add,
+        "-" => Code::Sub,
+        "*" => Code::Mul,
+        "/" => Code::Div,
+        "p" => Code::Powi,
+        "sub2" => Code::Sub2,
+        "<" => Code::Lt,
+        "<=" => Code::Le,
+        ">" => Code::Gt,
+        ">=" => Code::Ge,
+        "ite" => Code::Ite,
+        _   => unreachable!()
+    }
+}
+
+// Values the program can be run on.
+pub trait Value: Copy {
+    // Applies a unary or binary instruction to its operands, unary
+    // operations ignore b.
+    fn apply(code: Code, r: &mut Self, b: Self);
+    // Applies a registered function.
+    fn call(f: &Function, x: Self) -> Self;
+    // Selects a or b depending on cond, see gr::ite.
+    fn select(cond: Self, a: Self, b: Self) -> Self;
+    fn empty() -> Self;
+}
+
+impl Value for GI {
+    #[inline]
+    fn apply(code: Code, r: &mut GI, b: GI) {
+        match code {
+            Code::Add => r.add(b),
+            Code::Sub => r.sub(b),
+            Code::Mul => r.mul(b),
+            Code::Div => r.div(b),
+            Code::Powi => r.powi(b),
+            Code::Sub2 => r.sub2(b),
+            Code::Lt => *r = gr::lt(*r, b),
+            Code::Le => *r = gr::le(*r, b),
+            Code::Gt => *r = gr::gt(*r, b),
+            Code::Ge => *r = gr::ge(*r, b),
+            Code::Ite => unreachable!(),
+            Code::Pow(exp) => r.pow(exp),
+            Code::Func(_) => unreachable!(),
+        };
+    }
+
+    fn call(f: &Function, x: GI) -> GI {
+        (f.interval)(x)
+    }
+
+    fn select(cond: GI, a: GI, b: GI) -> GI {
+        gr::ite(cond, a, b)
+    }
+
+    fn empty() -> GI {
+        GI::new_e()
+    }
+}
+
+impl Value for EP {
+    #[inline]
+    fn apply(code: Code, r: &mut EP, b: EP) {
+        let a = *r;
+        *r = match code {
+            Code::Add => a + b,
+            Code::Sub => a - b,
+            Code::Mul => a*b,
+            Code::Div => a/b,
+            Code::Powi => point::powi(a, b),
+            Code::Sub2 => point::sub2(a, b),
+            Code::Lt => point::lt(a, b),
+            Code::Le => point::le(a, b),
+            Code::Gt => point::gt(a, b),
+            Code::Ge => point::ge(a, b),
+            Code::Ite => unreachable!(),
+            Code::Pow(exp) => point::pow(a, exp),
+            Code::Func(_) => unreachable!(),
+        };
+    }
+
+    fn call(f: &Function, x: EP) -> EP {
+        match f.point {
+            Some(p) => p(x),
+            None => EP::from_gi((f.interval)(x.to_gi())),
+        }
+    }
+
+    fn select(cond: EP, a: EP, b: EP) -> EP {
+        point::ite(cond, a, b)
+    }
+
+    fn empty() -> EP {
+        EP::unbounded()
+    }
+}
+
+// Applies a single operation of a tape to the values of its operands.
+pub fn apply_op<T: Value>(op: &OpType, args: &[T]) -> T {
+    let code = match op {
+        &OpType::Func(ref s) => return T::call(&plugin::lookup(s).unwrap(), args[0]),
+        &OpType::Pow(exp) => Code::Pow(exp),
+        &OpType::Op(ref s) => decode_op(s),
+        _ => unreachable!(),
+    };
+    if code == Code::Ite {
+        return T::select(args[0], args[1], args[2]);
+    }
+    let mut r = args[0];
+    T::apply(code, &mut r, *args.last().unwrap());
+    r
+}
+
+// Maximum stack depth reached by a verified tape.
+fn max_depth(tape: &Vec<OpType>) -> usize {
+    let mut depth = 0;
+    let mut max = 0;
+    for op in tape {
+        match op {
+            &OpType::Const(_) | &OpType::Var(_) | &OpType::UVar(_)
+                | &OpType::Load(_) => depth += 1,
+            &OpType::Op(ref s) if s == "ite" => depth -= 2,
+            &OpType::Op(_) => depth -= 1,
+            _ => (),
+        }
+        if depth > max {
+            max = depth;
+        }
+    }
+    max
+}
+
+impl Program {
+    // Decodes a verified tape.
+    pub fn new(tape: &Vec<OpType>) -> Program {
+        let mut stack: Vec<Src> = Vec::new();
+        let mut locals: Vec<Src> = Vec::new();
+        let mut insts: Vec<Inst> = Vec::new();
+        let mut names: Vec<&str> = Vec::new();
+        let mut funcs = Vec::new();
+        let n_stack = max_depth(tape);
+        let mut n_slots = n_stack;
+
+        for op in tape {
+            match op {
+                &OpType::Const(i) => stack.push(Src::Const(i)),
+                &OpType::Var(i) => stack.push(Src::Input(i)),
+                &OpType::UVar(i) => stack.push(Src::UVar(i)),
+                &OpType::Load(i) => stack.push(locals[i]),
+                &OpType::Store(i) => {
+                    let top = stack.pop().unwrap();
+                    let kept = match top {
+                        // Produced by the last instruction, which now writes
+                        // to a slot of its own instead.
+                        Src::Slot(d) if d < n_stack => {
+                            let last = insts.last_mut().unwrap();
+                            debug_assert!(last.dst == d);
+                            last.dst = n_slots;
+                            n_slots += 1;
+                            Src::Slot(last.dst)
+                        },
+                        // Constants, inputs, user variables and stored values
+                        // never change during a run and are used in place.
+                        _ => top,
+                    };
+                    if locals.len() <= i {
+                        locals.resize(i + 1, kept);
+                    }
+                    locals[i] = kept;
+                    stack.push(kept);
+                },
+                &OpType::Func(ref s) => {
+                    let a = stack.pop().unwrap();
+                    let dst = stack.len();
+                    let k = match names.iter().position(|&n| n == s) {
+                        Some(k) => k,
+                        None => {
+                            names.push(s);
+                            funcs.push(plugin::lookup(s).unwrap());
+                            funcs.len() - 1
+                        }
+                    };
+                    insts.push(Inst{code: Code::Func(k), dst: dst, a: a, b: a, c: a});
+                    stack.push(Src::Slot(dst));
+                },
+                &OpType::Pow(exp) => {
+                    let a = stack.pop().unwrap();
+                    let dst = stack.len();
+                    insts.push(Inst{code: Code::Pow(exp), dst: dst, a: a, b: a, c: a});
+                    stack.push(Src::Slot(dst));
+                },
+                &OpType::Op(ref s) => {
+                    let code = decode_op(s);
+                    let c = if code == Code::Ite {
+                        Some(stack.pop().unwrap())
+                    } else {
+                        None
+                    };
+                    let b = stack.pop().unwrap();
+                    let a = stack.pop().unwrap();
+                    let dst = stack.len();
+                    insts.push(Inst{code: code, dst: dst, a: a, b: b,
+                                    c: c.unwrap_or(b)});
+                    stack.push(Src::Slot(dst));
+                },
+            }
+        }
+
+        Program{insts: insts, n_slots: n_slots, result: stack[0], funcs: funcs}
+    }
+
+    #[inline]
+    fn fetch<T: Value>(src: Src, slots: &[T], x: &[T], c: &[T], v: &[T]) -> T {
+        match src {
+            Src::Slot(i) => slots[i],
+            Src::Const(i) => c[i],
+            Src::Input(i) => x[i],
+            Src::UVar(i) => v[i],
+        }
+    }
+
+    // Runs the program using the caller provided slot file.
+    pub fn run<T: Value>(&self, slots: &mut [T], x: &[T], c: &[T], v: &[T]) -> T {
+        for inst in &self.insts {
+            let mut r = Program::fetch(inst.a, slots, x, c, v);
+            let b = Program::fetch(inst.b, slots, x, c, v);
+            match inst.code {
+                Code::Ite => r = T::select(r, b, Program::fetch(inst.c, slots, x, c, v)),
+                Code::Func(k) => r = T::call(&self.funcs[k], r),
+                code => T::apply(code, &mut r, b),
+            }
+            slots[inst.dst] = r;
+        }
+        Program::fetch(self.result, slots, x, c, v)
+    }
+
+    // Runs the program using this thread's scratch slot file.
+    pub fn eval(&self, x: &[GI], c: &[GI], v: &[GI]) -> GI {
+        SCRATCH.with(|scratch| {
+            match scratch.try_borrow_mut() {
+                Ok(mut slots) => {
+                    if slots.len() < self.n_slots {
+                        slots.resize(self.n_slots, GI::new_e());
+                    }
+                    self.run(&mut slots, x, c, v)
+                },
+                // Nested evaluation on this thread, fall back to a fresh file.
+                Err(_) => {
+                    let mut slots = vec![GI::new_e(); self.n_slots];
+                    self.run(&mut slots, x, c, v)
+                }
+            }
+        })
+    }
+
+    #[inline]
+    fn fetch_batch<T: Value>(src: Src, j: usize, n: usize, slots: &[T],
+                             xs: &[Vec<T>], c: &[T], v: &[T]) -> T {
+        match src {
+            Src::Slot(i) => slots[i*n + j],
+            Src::Const(i) => c[i],
+            Src::Input(i) => xs[i][j],
+            Src::UVar(i) => v[i],
+        }
+    }
+
+    // Runs the program on n points given in structure of arrays layout,
+    // xs[i][j] is input i of point j. Each instruction is applied to all
+    // points before moving on to the next one.
+    pub fn run_batch<T: Value>(&self, slots: &mut [T], n: usize, xs: &[Vec<T>],
+                               c: &[T], v: &[T], out: &mut Vec<T>) {
+        for inst in &self.insts {
+            let base = inst.dst*n;
+            for j in 0..n {
+                let mut r = Program::fetch_batch(inst.a, j, n, slots, xs, c, v);
+                let b = Program::fetch_batch(inst.b, j, n, slots, xs, c, v);
+                match inst.code {
+                    Code::Ite => r = T::select(r, b, Program::fetch_batch(inst.c, j, n,
+                                                                          slots, xs,
+                                                                          c, v)),
+                    Code::Func(k) => r = T::call(&self.funcs[k], r),
+                    code => T::apply(code, &mut r, b),
+                }
+                slots[base + j] = r;
+            }
+        }
+        for j in 0..n {
+            out.push(Program::fetch_batch(self.result, j, n, slots, xs, c, v));
+        }
+    }
+
+    // Runs the program on n points using this thread's batch slot file.
+    pub fn eval_batch(&self, n: usize, xs: &[Vec<GI>], c: &[GI], v: &[GI],
+                      out: &mut Vec<GI>) {
+        BATCH_SCRATCH.with(|scratch| {
+            match scratch.try_borrow_mut() {
+                Ok(mut slots) => {
+                    if slots.len() < self.n_slots*n {
+                        slots.resize(self.n_slots*n, GI::new_e());
+                    }
+                    self.run_batch(&mut slots, n, xs, c, v, out)
+                },
+                Err(_) => {
+                    let mut slots = vec![GI::new_e(); self.n_slots*n];
+                    self.run_batch(&mut slots, n, xs, c, v, out)
+                }
+            }
+        })
+    }
+
+    // Runs the program on n points with error bounded point arithmetic,
+    // using this thread's point slot file.
+    pub fn eval_point_batch(&self, n: usize, xs: &[Vec<EP>], c: &[EP], v: &[EP],
+                            out: &mut Vec<EP>) {
+        POINT_SCRATCH.with(|scratch| {
+            match scratch.try_borrow_mut() {
+                Ok(mut slots) => {
+                    if slots.len() < self.n_slots*n {
+                        slots.resize(self.n_slots*n, EP::empty());
+                    }
+                    self.run_batch(&mut slots, n, xs, c, v, out)
+                },
+                Err(_) => {
+                    let mut slots = vec![EP::empty(); self.n_slots*n];
+                    self.run_batch(&mut slots, n, xs, c, v, out)
+                }
+            }
+        })
+    }
+}
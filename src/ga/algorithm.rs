@@ -1,13 +1,14 @@
+use std::cmp::Ordering;
 use std::sync::{Barrier, RwLock, Arc};
 use std::sync::atomic::{AtomicBool};
 use std::sync::atomic::Ordering as AtOrd;
 extern crate rand;
-use rand::{Rng, SeedableRng, XorShiftRng};
+use rand::Rng;
 use rand::distributions::{IndependentSample, Range};
 
 
 extern crate gr;
-use gr::{GI};
+use gr::{GI, kind_of};
 
 extern crate gelpia_utils;
 use gelpia_utils::{Flt, Parameters};
@@ -15,17 +16,85 @@ use gelpia_utils::{Flt, Parameters};
 extern crate function;
 use function::FuncObj;
 
-type GARng = XorShiftRng;
+// The xorshift generator of rand's XorShiftRng, producing the same numbers
+// from the same seed, with a state that can be saved and restored.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GARng {
+    state: [u32; 4],
+}
+
+impl GARng {
+    // A generator continuing from a state returned by state. The state must
+    // not be all zero.
+    pub fn from_state(state: [u32; 4]) -> GARng {
+        assert!(state.iter().any(|&s| s != 0), "GARng state is all zero");
+        GARng{state: state}
+    }
+
+    pub fn state(&self) -> [u32; 4] {
+        self.state
+    }
+}
+
+impl Rng for GARng {
+    fn next_u32(&mut self) -> u32 {
+        let x = self.state[0];
+        let t = x ^ (x << 11);
+        self.state[0] = self.state[1];
+        self.state[1] = self.state[2];
+        self.state[2] = self.state[3];
+        let w = self.state[3];
+        self.state[3] = w ^ (w >> 19) ^ (t ^ (t >> 8));
+        self.state[3]
+    }
+}
+
+// Uniform samples from the interval of an input, which may be a point, as it
+// is for discrete inputs holding a single value.
+struct Sampler {
+    lower: f64,
+    range: Option<Range<f64>>,
+}
+
+impl Sampler {
+    fn new(x: &GI) -> Sampler {
+        Sampler{lower: x.lower(),
+                range: if x.lower() < x.upper() { Some(Range::new(x.lower(), x.upper())) }
+                       else { None }}
+    }
+
+    fn ind_sample(&self, rng: &mut GARng) -> f64 {
+        match self.range {
+            Some(ref r) => r.ind_sample(rng),
+            None => self.lower,
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Individual {
     pub solution: Vec<GI>,
     pub fitness: Flt,
+    // How far the solution may violate the constraints of the function, 0
+    // when it certainly satisfies them.
+    pub violation: Flt,
 }
 
+/// Orders individuals from fittest to least fit: certainly feasible ones
+/// first, then by smaller violation and then by larger fitness.
+fn fitter(a: &Individual, b: &Individual) -> Ordering {
+    a.violation.partial_cmp(&b.violation).unwrap()
+        .then(b.fitness.partial_cmp(&a.fitness).unwrap())
+}
+
+// Runs the EA until stop is set. The generator starts from the state in rng
+// if there is one, and from the seed of param otherwise. Its state is put
+// back into rng whenever the population is released, so the two can be saved
+// together.
 pub fn ea(x_e: Vec<GI>,
           param: Parameters,
           population: Arc<RwLock<Vec<Individual>>>,
+          rng: Arc<RwLock<Option<[u32; 4]>>>,
           f_bestag: Arc<RwLock<Flt>>,
           x_bestbb: Arc<RwLock<Vec<GI>>>,
           b1: Arc<Barrier>,
@@ -39,7 +108,7 @@ pub fn ea(x_e: Vec<GI>,
     }
     let seed = param.seed;
     ea_core(&x_e, &param, &stop, &sync, &b1, &b2, &f_bestag,
-            &x_bestbb, population, &fo_c, seed);
+            &x_bestbb, population, rng, &fo_c, seed);
     return;
 }
 
@@ -48,25 +117,12 @@ fn ea_core(x_e: &Vec<GI>, param: &Parameters, stop: &Arc<AtomicBool>,
            sync: &Arc<AtomicBool>, b1: &Arc<Barrier>, b2: &Arc<Barrier>,
            f_bestag: &Arc<RwLock<Flt>>,
            x_bestbb: &Arc<RwLock<Vec<GI>>>,
-           population: Arc<RwLock<Vec<Individual>>>, fo_c: &FuncObj,
+           population: Arc<RwLock<Vec<Individual>>>,
+           rng_state: Arc<RwLock<Option<[u32; 4]>>>, fo_c: &FuncObj,
            seed: u32) {
-    let rng_seed: u32 =
-        match seed {
-            0 => 3735928579,
-            1 => rand::thread_rng().next_u32(),
-            _ => seed,
-        };
-    let seed_r: [u32; 4] = [(rng_seed & 0xFF000000) >> 24,
-                            (rng_seed & 0xFF0000) >> 16,
-                            (rng_seed & 0xFF00) >> 8 ,
-                            rng_seed & 0xFF];
-    let mut rng: GARng = GARng::from_seed(seed_r);
-
-    let dimension = Range::new(0, x_e.len());
-    let mut ranges = Vec::new();
-    for g in x_e {
-        ranges.push(Range::new(g.lower(), g.upper()));
-    }
+    let mut rng: GARng = GARng::from_state(rng_state.read().unwrap()
+                                           .unwrap_or_else(|| seed_state(seed)));
+    let ranges = x_e.iter().map(Sampler::new).collect();
 
     while !stop.load(AtOrd::Acquire) {
         if sync.load(AtOrd::Acquire) {
@@ -74,117 +130,233 @@ fn ea_core(x_e: &Vec<GI>, param: &Parameters, stop: &Arc<AtomicBool>,
             b2.wait();
         }
         let ref mut population = *population.write().unwrap();
-        if sample(param.population, population, fo_c, &ranges, &mut rng, stop) {
+        let stopped = evolve(param, stop, f_bestag, x_bestbb, population, fo_c,
+                             &ranges, &mut rng);
+        *rng_state.write().unwrap() = Some(rng.state());
+        if stopped {
             return;
         }
+    }
 
-        population.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
+    return;
+}
 
-        for _ in 0..100 {
-            if stop.load(AtOrd::Acquire) {
-                return;
-            }
-            population.truncate(param.elitism);
 
-            for _ in 0..param.selection {
-                population.push(rand_individual(fo_c, &ranges, &mut rng));
-            }
+// The initial state of the generator for a seed, 0 for a fixed default and
+// 1 for a random one.
+fn seed_state(seed: u32) -> [u32; 4] {
+    let rng_seed: u32 =
+        match seed {
+            0 => 3735928579,
+            1 => rand::thread_rng().next_u32(),
+            _ => seed,
+        };
+    [(rng_seed & 0xFF000000) >> 24,
+     (rng_seed & 0xFF0000) >> 16,
+     (rng_seed & 0xFF00) >> 8 ,
+     rng_seed & 0xFF]
+}
 
-            if next_generation(param.population, population, fo_c, param.mutation,
-                               param.crossover, &dimension, &ranges, &mut rng,
-                               stop) {
-                return;
-            }
-            population.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
 
-            // Report fittest of the fit.
-            {
-                let mut fbest = f_bestag.write().unwrap();
+// Runs a single epoch of the EA on the calling thread: refills the population
+// and runs 100 generations on it, as the EA thread does between two looks at
+// its sync flag. The generator continues from rng, or starts from the seed of
+// param, and its state is put back into rng.
+pub fn ea_epoch(x_e: &Vec<GI>, param: &Parameters,
+                population: &mut Vec<Individual>,
+                rng: &mut Option<[u32; 4]>,
+                f_bestag: &Arc<RwLock<Flt>>,
+                x_bestbb: &Arc<RwLock<Vec<GI>>>,
+                fo_c: &FuncObj) {
+    if x_e.len() == 0 {
+        return;
+    }
+    let mut generator = GARng::from_state(rng.unwrap_or_else(|| seed_state(param.seed)));
+    let ranges = x_e.iter().map(Sampler::new).collect();
+    let stop = Arc::new(AtomicBool::new(false));
+    evolve(param, &stop, f_bestag, x_bestbb, population, fo_c, &ranges, &mut generator);
+    *rng = Some(generator.state());
+}
+
 
-                *fbest =
-                    if *fbest < population[0].fitness { population[0].fitness }
-                else { *fbest };
-            }
+// Refills the population and runs 100 generations on it, returns whether
+// the EA was stopped on the way.
+fn evolve(param: &Parameters, stop: &Arc<AtomicBool>,
+          f_bestag: &Arc<RwLock<Flt>>, x_bestbb: &Arc<RwLock<Vec<GI>>>,
+          population: &mut Vec<Individual>, fo_c: &FuncObj,
+          ranges: &Vec<Sampler>, rng: &mut GARng) -> bool {
+    let dimension = Range::new(0, ranges.len());
+    let stopped = sample(param.population, population, fo_c, ranges, rng, stop);
+    population.sort_by(fitter);
+    if stopped {
+        report_fittest(population, f_bestag);
+        return true;
+    }
 
-            // Kill worst of the worst
-            let mut ftg = Vec::new();
-            {
-                let bestbb = x_bestbb.read().unwrap();
-                // From The Gods
-                for i in 0..bestbb.len() {
-                    ftg.push(Range::new(bestbb[i].lower(), bestbb[i].upper()));
-                }
+    for _ in 0..100 {
+        if stop.load(AtOrd::Acquire) {
+            return true;
+        }
+        population.truncate(param.elitism);
+
+        let selected = (0..param.selection)
+            .map(|_| rand_solution(ranges, rng))
+            .collect();
+        population.append(&mut evaluate(fo_c, selected));
+
+        let stopped = next_generation(param.population, population, fo_c,
+                                      param.mutation, param.crossover, &dimension,
+                                      ranges, rng, stop);
+        population.sort_by(fitter);
+        // A generation cut short by a stop is reported too
+        report_fittest(population, f_bestag);
+        if stopped {
+            return true;
+        }
+
+        // Kill worst of the worst
+        let mut ftg = Vec::new();
+        {
+            let bestbb = x_bestbb.read().unwrap();
+            // From The Gods
+            for i in 0..bestbb.len() {
+                ftg.push(Sampler::new(&bestbb[i]));
             }
-            let worst_ind = population.len() - 1;
-            population[worst_ind] = rand_individual(fo_c,
-                                                    &ftg,
-                                                    &mut rng);
-            population.sort_by(|a, b| b.fitness.partial_cmp(&a.fitness).unwrap());
         }
+        let worst_ind = population.len() - 1;
+        population[worst_ind] = rand_individual(fo_c,
+                                                &ftg,
+                                                rng);
+        population.sort_by(fitter);
     }
+    false
+}
 
-    return;
+
+// Report fittest of the fit of a sorted population, its fitness only bounds
+// the maximum if it is feasible.
+fn report_fittest(population: &Vec<Individual>, f_bestag: &Arc<RwLock<Flt>>) {
+    if let Some(best) = population.first() {
+        if best.violation == 0.0 {
+            let mut fbest = f_bestag.write().unwrap();
+            if *fbest < best.fitness {
+                *fbest = best.fitness;
+            }
+        }
+    }
 }
 
 
 fn sample(population_size: usize, population: &mut Vec<Individual>,
-          fo_c: &FuncObj, ranges: &Vec<Range<f64>>, rng: &mut GARng,
+          fo_c: &FuncObj, ranges: &Vec<Sampler>, rng: &mut GARng,
           stop: &Arc<AtomicBool>)
           -> bool {
+    let mut solutions = Vec::new();
+    let mut stopped = false;
     for i in 0..population_size-population.len() {
         if i % 64 == 0 && stop.load(AtOrd::Acquire) {
-            return true;
+            stopped = true;
+            break;
         }
-        population.push(rand_individual(fo_c, ranges, rng));
+        solutions.push(rand_solution(ranges, rng));
     }
+    // Solutions drawn before a stop are still evaluated and kept
+    population.append(&mut evaluate(fo_c, solutions));
 
-    false
+    stopped
 }
 
 
-fn rand_individual(fo_c: &FuncObj, ranges: &Vec<Range<f64>>, rng: &mut GARng)
-                   -> (Individual) {
+/// Evaluates the fitness of all solutions with a single batched call. Each
+/// fitness is a rigorous lower bound of the function at that point, so it
+/// can be shared with the branch and bound as a lower bound on the maximum
+/// when the point is also certainly feasible. Discrete inputs are first
+/// moved to their closest admissible value.
+pub fn evaluate(fo_c: &FuncObj, mut solutions: Vec<Vec<GI>>) -> Vec<Individual> {
+    if solutions.is_empty() {
+        return Vec::new();
+    }
+    let kinds = fo_c.input_kinds();
+    if !kinds.is_empty() {
+        for sol in solutions.iter_mut() {
+            for (i, si) in sol.iter_mut().enumerate() {
+                if kind_of(kinds, i).is_discrete() {
+                    *si = GI::new_p(kind_of(kinds, i).nearest(si.lower()));
+                }
+            }
+        }
+    }
+    let dims = solutions[0].len();
+    let mut xs = vec![Vec::with_capacity(solutions.len()); dims];
+    for sol in &solutions {
+        for (xi, &si) in xs.iter_mut().zip(sol.iter()) {
+            xi.push(si.lower());
+        }
+    }
+    let fitness = fo_c.call_point_batch(&xs);
+    let violation = if fo_c.has_constraints() {
+        let points = xs.iter()
+            .map(|xi| xi.iter().map(|&x| GI::new_p(x)).collect())
+            .collect();
+        fo_c.violations(&points)
+    } else {
+        vec![0.0; solutions.len()]
+    };
+    solutions.into_iter().zip(fitness.into_iter()).zip(violation.into_iter())
+        .map(|((sol, f), v)| Individual{solution: sol, fitness: f, violation: v})
+        .collect()
+}
+
+
+fn rand_solution(ranges: &Vec<Sampler>, rng: &mut GARng) -> Vec<GI> {
     let mut new_sol = Vec::new();
     for r in ranges {
         new_sol.push(GI::new_p(r.ind_sample(rng)));
     }
-    let (fitness_i, _) = fo_c.call(&new_sol);
-    let fitness = fitness_i.lower();
+    new_sol
+}
 
-    Individual{solution:new_sol, fitness:fitness}
+
+fn rand_individual(fo_c: &FuncObj, ranges: &Vec<Sampler>, rng: &mut GARng)
+                   -> (Individual) {
+    evaluate(fo_c, vec![rand_solution(ranges, rng)]).pop().unwrap()
 }
 
 
 fn next_generation(population_size:usize, population: &mut Vec<Individual>,
                    fo_c: &FuncObj, mut_rate: f64, crossover: f64,
-                   dimension: &Range<usize>, ranges: &Vec<Range<f64>>,
+                   dimension: &Range<usize>, ranges: &Vec<Sampler>,
                    rng: &mut GARng, stop: &Arc<AtomicBool>)
                    -> bool {
 
     let elites = population.clone();
 
+    let mut solutions = Vec::new();
+    let mut stopped = false;
     for i in 0..population_size-population.len() {
         if i % 64 == 0 && stop.load(AtOrd::Acquire) {
-                return true;
+            stopped = true;
+            break;
         }
         if rng.gen::<f64>() < crossover {
-            population.push(breed((&mut *rng).choose(&elites).unwrap(),
-                                  rng.choose(&elites).unwrap(),
-                                  fo_c,
-                                  dimension, rng));
+            solutions.push(breed((&mut *rng).choose(&elites).unwrap(),
+                                 rng.choose(&elites).unwrap(),
+                                 dimension, rng));
         } else {
-            population.push(mutate(rng.choose(&elites).unwrap(), fo_c, mut_rate,
-                                   ranges, rng));
+            solutions.push(mutate(rng.choose(&elites).unwrap(), mut_rate,
+                                  ranges, rng));
         }
     }
+    // Solutions bred before a stop are still evaluated and kept
+    population.append(&mut evaluate(fo_c, solutions));
 
-    false
+    stopped
 }
 
 
-fn mutate(input: &Individual, fo_c: &FuncObj, mut_rate: f64,
-          ranges: &Vec<Range<f64>>, rng: &mut GARng)
-          -> (Individual) {
+fn mutate(input: &Individual, mut_rate: f64,
+          ranges: &Vec<Sampler>, rng: &mut GARng)
+          -> Vec<GI> {
     let mut output_sol = Vec::new();
 
     for (r, &ind) in ranges.iter().zip(input.solution.iter()) {
@@ -196,21 +368,16 @@ fn mutate(input: &Individual, fo_c: &FuncObj, mut_rate: f64,
             });
     }
 
-    let (fitness_i, _) = fo_c.call(&output_sol);
-    let fitness = fitness_i.lower();
-
-    Individual{solution: output_sol, fitness: fitness}
+    output_sol
 }
 
 
-fn breed(parent1: &Individual, parent2: &Individual, fo_c: &FuncObj,
-         dimension: &Range<usize>, rng: &mut GARng) -> (Individual) {
-    let mut child = parent1.clone();
+fn breed(parent1: &Individual, parent2: &Individual,
+         dimension: &Range<usize>, rng: &mut GARng) -> Vec<GI> {
+    let mut child = parent1.solution.clone();
     let crossover_point = dimension.ind_sample(rng);
-    child.solution.truncate(crossover_point);
-    let mut rest = parent2.clone().solution.split_off(crossover_point);
-    child.solution.append(&mut rest);
-    let (fitness_i, _) = fo_c.call(&child.solution);
-    child.fitness = fitness_i.lower();
+    child.truncate(crossover_point);
+    let mut rest = parent2.solution.clone().split_off(crossover_point);
+    child.append(&mut rest);
     child
 }
@@ -13,6 +13,11 @@ use std::arch::x86::{__m128d,_mm_setzero_pd};
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::{__m128d,_mm_setzero_pd};
 
+pub mod point;
+pub mod kind;
+
+pub use kind::{InputKind, kind_of, admissible_midpoint};
+
 pub type CInterval = __m128d;
 
 trait Interval {
@@ -630,6 +635,57 @@ pub fn sub2(x: GI, y: GI) -> GI {
     result
 }
 
+// Interval comparisons, the result is [1, 1] when the comparison holds for
+// every pair of values, [0, 0] when it holds for none and [0, 1] otherwise.
+fn decide(always: bool, never: bool) -> GI {
+    if always {
+        GI::new_p(1.0)
+    } else if never {
+        GI::new_p(0.0)
+    } else {
+        GI::new_d(0.0, 1.0)
+    }
+}
+
+pub fn lt(x: GI, y: GI) -> GI {
+    decide(x.upper() < y.lower(), x.lower() >= y.upper())
+}
+
+pub fn le(x: GI, y: GI) -> GI {
+    decide(x.upper() <= y.lower(), x.lower() > y.upper())
+}
+
+pub fn gt(x: GI, y: GI) -> GI {
+    lt(y, x)
+}
+
+pub fn ge(x: GI, y: GI) -> GI {
+    le(y, x)
+}
+
+// Smallest interval containing both arguments.
+pub fn hull(x: GI, y: GI) -> GI {
+    if x.is_empty() {
+        y
+    } else if y.is_empty() {
+        x
+    } else {
+        GI::new_d(x.lower().min(y.lower()), x.upper().max(y.upper()))
+    }
+}
+
+// Selects a when cond is certainly nonzero and b when cond is zero. When the
+// condition is undecided the result encloses both branches.
+pub fn ite(cond: GI, a: GI, b: GI) -> GI {
+    if cond.lower() > 0.0 || cond.upper() < 0.0 {
+        a
+    } else if cond.lower() == 0.0 && cond.upper() == 0.0 {
+        b
+    } else {
+        hull(a, b)
+    }
+}
+
 pub fn exp(x: GI) -> GI {
     let mut result = GI{data: gaol_int{data: CInterval::new()}};
     unsafe{exp_g(&x.data, &mut result.data)};
@@ -666,17 +722,23 @@ pub fn widest_index(_x: &Vec<GI>) -> usize {
 }
 
 
-pub fn width_box(_x: &Vec<GI>, tol: f64) -> bool {
+// Whether the box is refined to the tolerance. Discrete inputs only are once
+// they hold a single value.
+pub fn width_box(_x: &Vec<GI>, tol: f64, kinds: &[InputKind]) -> bool {
     let mut w = NINF;
-    let mut widest = GI::new_e();
-    for a in _x {
+    let mut widest = None;
+    for (i, a) in _x.iter().enumerate() {
         let wid = a.width();
-        if wid > w {
+        if kind_of(kinds, i).is_discrete() {
+            if wid > 0.0 {
+                return false;
+            }
+        } else if wid > w {
             w = wid;
-            widest = *a;
+            widest = Some(*a);
         }
     }
-    eps_tol(widest, tol)
+    widest.map_or(true, |widest| eps_tol(widest, tol))
 }
 
 pub fn midpoint_box(_x: &Vec<GI>) -> Vec<GI> {
@@ -699,7 +761,9 @@ fn get_next_binade(current: f64)
     d
 }
 
-pub fn split_box(_x: &Vec<GI>) -> (Vec<Vec<GI>>, bool) {
+// Splits the box along its widest input. Discrete inputs are split between
+// two admissible values.
+pub fn split_box(_x: &Vec<GI>, kinds: &[InputKind]) -> (Vec<Vec<GI>>, bool) {
     let mut w = NINF;
     let mut w_ind: usize = 0;
     for i in 0.._x.len() {
@@ -710,6 +774,14 @@ pub fn split_box(_x: &Vec<GI>) -> (Vec<Vec<GI>>, bool) {
         }
     }
 
+    let kind = kind_of(kinds, w_ind);
+    if kind.is_discrete() {
+        return match kind::split_discrete(_x, w_ind, kind) {
+            Some((a, b)) => (vec![a, b], true),
+            None => (vec![_x.clone()], false),
+        };
+    }
+
     let mut a = _x.clone();
     let mut b = _x.clone();
     let nb = get_next_binade(_x[w_ind].midpoint().lower());
@@ -0,0 +1,134 @@
+// Kinds of input variables. Besides real inputs an input may only take
+// integer values or the values of a finite set. Boxes keep the bounds of such
+// inputs on admissible values, so a discrete input whose interval is a point
+// is fully refined.
+
+use super::GI;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum InputKind {
+    Real,
+    Integer,
+    // Sorted without duplicates, see InputKind::values.
+    Values(Vec<f64>),
+}
+
+static REAL: InputKind = InputKind::Real;
+
+// The kind of input i, inputs without a kind are real.
+pub fn kind_of(kinds: &[InputKind], i: usize) -> &InputKind {
+    kinds.get(i).unwrap_or(&REAL)
+}
+
+impl InputKind {
+    // An input taking one of the given values.
+    pub fn values(mut vs: Vec<f64>) -> Result<InputKind, String> {
+        if vs.is_empty() {
+            return Err("An enumerated input needs at least one value".to_string());
+        }
+        if let Some(v) = vs.iter().find(|v| !v.is_finite()) {
+            return Err(format!("Enumerated values must be finite, got {}", v));
+        }
+        vs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        vs.dedup();
+        Ok(InputKind::Values(vs))
+    }
+
+    pub fn is_discrete(&self) -> bool {
+        *self != InputKind::Real
+    }
+
+    // The admissible values inside x, for enumerated inputs.
+    fn inside<'a>(vs: &'a [f64], x: &GI) -> &'a [f64] {
+        let lo = vs.iter().position(|&v| v >= x.lower()).unwrap_or(vs.len());
+        let hi = vs.iter().rposition(|&v| v <= x.upper()).map_or(0, |i| i + 1);
+        if lo < hi { &vs[lo..hi] } else { &[] }
+    }
+
+    // The smallest interval holding the admissible values in x, None if x
+    // holds none.
+    pub fn snap(&self, x: &GI) -> Option<GI> {
+        match *self {
+            InputKind::Real => Some(*x),
+            InputKind::Integer => {
+                let (lo, hi) = (x.lower().ceil(), x.upper().floor());
+                if lo <= hi { Some(GI::new_d(lo, hi)) } else { None }
+            }
+            InputKind::Values(ref vs) => {
+                let vs = InputKind::inside(vs, x);
+                match (vs.first(), vs.last()) {
+                    (Some(&lo), Some(&hi)) => Some(GI::new_d(lo, hi)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    // The admissible value closest to v, ties going down. For a snapped
+    // interval holding v the result stays inside it.
+    pub fn nearest(&self, v: f64) -> f64 {
+        match *self {
+            InputKind::Real => v,
+            InputKind::Integer => {
+                let down = v.floor();
+                if v - down <= 0.5 { down } else { down + 1.0 }
+            }
+            InputKind::Values(ref vs) => {
+                let mut best = vs[0];
+                for &c in vs.iter() {
+                    if (c - v).abs() < (best - v).abs() {
+                        best = c;
+                    }
+                }
+                best
+            }
+        }
+    }
+
+    // Splits a snapped interval of a discrete input between two admissible
+    // values, None if it holds a single one.
+    fn split(&self, x: &GI) -> Option<(GI, GI)> {
+        match *self {
+            InputKind::Real => None,
+            InputKind::Integer => {
+                if x.lower() >= x.upper() {
+                    return None;
+                }
+                let m = (x.lower()/2.0 + x.upper()/2.0).floor();
+                Some((GI::new_d(x.lower(), m), GI::new_d(m + 1.0, x.upper())))
+            }
+            InputKind::Values(ref vs) => {
+                let vs = InputKind::inside(vs, x);
+                if vs.len() < 2 {
+                    return None;
+                }
+                let k = vs.len()/2;
+                Some((GI::new_d(vs[0], vs[k - 1]), GI::new_d(vs[k], vs[vs.len() - 1])))
+            }
+        }
+    }
+}
+
+// The midpoint of x, with discrete inputs at the admissible value closest to
+// their midpoint.
+pub fn admissible_midpoint(x: &Vec<GI>, kinds: &[InputKind]) -> Vec<GI> {
+    x.iter().enumerate().map(|(i, xi)| {
+        let mid = xi.midpoint();
+        match *kind_of(kinds, i) {
+            InputKind::Real => mid,
+            ref k => GI::new_p(k.nearest(mid.lower())),
+        }
+    }).collect()
+}
+
+// Splits x along input i as an input of the given kind, None if the
+// input cannot be split any further.
+pub fn split_discrete(x: &Vec<GI>, i: usize, kind: &InputKind)
+                      -> Option<(Vec<GI>, Vec<GI>)> {
+    kind.split(&x[i]).map(|(l, u)| {
+        let (mut a, mut b) = (x.clone(), x.clone());
+        a[i] = l;
+        b[i] = u;
+        (a, b)
+    })
+}
@@ -0,0 +1,216 @@
+// Points carried in plain f64 together with a bound on the accumulated
+// rounding error. Arithmetic rounds to nearest and grows the error bound so
+// that the exact value always lies in [v - e, v + e]. This is much cheaper than
+// interval arithmetic for polynomial expressions and still gives a rigorous
+// lower bound. Operations without a simple error model go through GI.
+
+use std::f64::INFINITY as INF;
+use std::f64::NEG_INFINITY as NINF;
+use std::ops::{Add, Mul, Sub, Div, Neg};
+
+use super::GI;
+
+// Unit roundoff for round to nearest.
+const U: f64 = 1.1102230246251565e-16;
+// Smallest subnormal, bounds the absolute error of an underflowing product.
+const ETA: f64 = 4.9406564584124654e-324;
+
+#[derive(Copy, Clone, Debug)]
+pub struct EP {
+    pub v: f64,
+    pub e: f64,
+}
+
+// Accounts for the rounding of the few operations used to compute an error
+// bound, so the bound itself is never too small.
+#[inline]
+fn up(e: f64) -> f64 {
+    e*(1.0 + 8.0*U) + ETA
+}
+
+impl EP {
+    pub fn new(x: f64) -> EP {
+        EP{v: x, e: 0.0}
+    }
+
+    // A point with no information, its lower bound is -inf.
+    pub fn unbounded() -> EP {
+        EP{v: 0.0, e: INF}
+    }
+
+    // Encloses an interval, the result is exact when the interval is a point.
+    pub fn from_gi(x: GI) -> EP {
+        EP::from_bounds(x.lower(), x.upper())
+    }
+
+    // Encloses [a, b], the result is exact when a == b.
+    pub fn from_bounds(a: f64, b: f64) -> EP {
+        if a == b && a.is_finite() {
+            return EP::new(a);
+        }
+        if !(a.is_finite() && b.is_finite()) || a > b {
+            return EP::unbounded();
+        }
+        let v = a*0.5 + b*0.5;
+        let r = if v - a > b - v { v - a } else { b - v };
+        EP{v: v, e: up(r)}
+    }
+
+    pub fn to_gi(&self) -> GI {
+        if self.e == 0.0 {
+            GI::new_p(self.v)
+        }
+        else {
+            GI::new_d(self.lower(), self.upper())
+        }
+    }
+
+    // Rigorous lower bound on the exact value.
+    pub fn lower(&self) -> f64 {
+        if self.e == 0.0 {
+            return self.v;
+        }
+        let l = self.v - self.e;
+        let l = l - (2.0*U*l.abs() + ETA);
+        if l.is_nan() { NINF } else { l }
+    }
+
+    // Rigorous upper bound on the exact value.
+    pub fn upper(&self) -> f64 {
+        if self.e == 0.0 {
+            return self.v;
+        }
+        let h = self.v + self.e;
+        let h = h + (2.0*U*h.abs() + ETA);
+        if h.is_nan() { INF } else { h }
+    }
+}
+
+impl Add for EP {
+    type Output = EP;
+    fn add(self, other: EP) -> EP {
+        let z = self.v + other.v;
+        EP{v: z, e: up(self.e + other.e + U*z.abs())}
+    }
+}
+
+impl Sub for EP {
+    type Output = EP;
+    fn sub(self, other: EP) -> EP {
+        let z = self.v - other.v;
+        EP{v: z, e: up(self.e + other.e + U*z.abs())}
+    }
+}
+
+impl Mul for EP {
+    type Output = EP;
+    fn mul(self, other: EP) -> EP {
+        let z = self.v*other.v;
+        EP{v: z, e: up(self.v.abs()*other.e + other.v.abs()*self.e
+                       + self.e*other.e + U*z.abs())}
+    }
+}
+
+impl Div for EP {
+    type Output = EP;
+    fn div(self, other: EP) -> EP {
+        let d = other.v.abs();
+        if !(d > other.e) {
+            // The divisor may be zero.
+            return EP::unbounded();
+        }
+        let z = self.v/other.v;
+        let num = d*self.e + self.v.abs()*other.e;
+        let prop = if num == 0.0 {
+            0.0
+        } else {
+            num/(d*((d - other.e)*(1.0 - 4.0*U))*(1.0 - 4.0*U))
+        };
+        EP{v: z, e: up(up(prop) + U*z.abs())}
+    }
+}
+
+impl Neg for EP {
+    type Output = EP;
+    fn neg(self) -> EP {
+        EP{v: -self.v, e: self.e}
+    }
+}
+
+pub fn abs(x: EP) -> EP {
+    EP{v: x.v.abs(), e: x.e}
+}
+
+pub fn pow(base: EP, exp: i32) -> EP {
+    let mut n = (exp as i64).abs();
+    let mut b = base;
+    let mut result = EP::new(1.0);
+    while n > 0 {
+        if n & 1 == 1 {
+            result = result*b;
+        }
+        n >>= 1;
+        if n > 0 {
+            b = b*b;
+        }
+    }
+    if exp < 0 { EP::new(1.0)/result } else { result }
+}
+
+pub fn powi(base: EP, exp: EP) -> EP {
+    EP::from_gi(super::powi(base.to_gi(), exp.to_gi()))
+}
+
+pub fn sub2(x: EP, y: EP) -> EP {
+    EP::from_gi(super::sub2(x.to_gi(), y.to_gi()))
+}
+
+fn decide(always: bool, never: bool) -> EP {
+    if always {
+        EP::new(1.0)
+    } else if never {
+        EP::new(0.0)
+    } else {
+        EP::from_bounds(0.0, 1.0)
+    }
+}
+
+pub fn lt(x: EP, y: EP) -> EP {
+    decide(x.upper() < y.lower(), x.lower() >= y.upper())
+}
+
+pub fn le(x: EP, y: EP) -> EP {
+    decide(x.upper() <= y.lower(), x.lower() > y.upper())
+}
+
+pub fn gt(x: EP, y: EP) -> EP {
+    lt(y, x)
+}
+
+pub fn ge(x: EP, y: EP) -> EP {
+    le(y, x)
+}
+
+// Same selection rule as gr::ite.
+pub fn ite(cond: EP, a: EP, b: EP) -> EP {
+    if cond.lower() > 0.0 || cond.upper() < 0.0 {
+        a
+    } else if cond.v == 0.0 && cond.e == 0.0 {
+        b
+    } else {
+        EP::from_bounds(a.lower().min(b.lower()), a.upper().max(b.upper()))
+    }
+}
+
+macro_rules! through_gi {
+    ($($name:ident),*) => {
+        $(
+            pub fn $name(x: EP) -> EP {
+                EP::from_gi(super::$name(x.to_gi()))
+            }
+        )*
+    }
+}
+
+through_gi!(dabs, sin, asin, sqrt, cos, acos, tan, atan, sinh, asinh, cosh,
+            acosh, tanh, atanh, floor_power2, sym_interval, exp, log);
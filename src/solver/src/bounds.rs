@@ -0,0 +1,84 @@
+// Building blocks shared by the branch and bound strategies.
+
+use gelpia_utils::{Quple, Flt, INF, NINF};
+
+use gr::{GI, admissible_midpoint};
+
+use function::FuncObj;
+
+use feasible::certify;
+
+/// Returns a tuple (function_estimate, eval_interval, derivative). Only
+/// points that certainly satisfy the constraints count towards the estimate,
+/// which is -inf when none of them does. Discrete inputs are estimated at
+/// admissible values. With equality constraints a box
+/// certified to hold a feasible point counts as well.
+/// # Arguments
+/// * `f` - The function to evaluate with
+/// * `input` - The input domain
+pub fn est_func(f: &FuncObj, input: &Vec<GI>) -> (Flt, GI, Option<Vec<GI>>) {
+    let mid = admissible_midpoint(input, f.input_kinds());
+    let (fsx, dfsx) = f.call(&input);
+    // Midpoint, upper corner and lower corner evaluated as one batch
+    let points = mid.iter().zip(input.iter())
+        .map(|(&mi, &si)| vec![mi, GI::new_p(si.upper()), GI::new_p(si.lower())])
+        .collect::<Vec<_>>();
+    let est_max = f.call_batch(&points).iter().zip(f.violations(&points))
+        .filter(|&(_, v)| v == 0.0)
+        .fold(NINF, |acc, (e, _)| acc.max(e.lower()));
+    let est_max = match certify(f, input) {
+        Some(b) => est_max.max(f.call(&b).0.lower()),
+        None => est_max,
+    };
+    (est_max, fsx, dfsx)
+}
+
+/// Returns a tuple (max_estimate, min_estimate, eval_interval, derivative)
+/// from the same evaluations as est_func, again only from certainly feasible
+/// points
+/// # Arguments
+/// * `f` - The function to evaluate with
+/// * `input` - The input domain
+pub fn est_range(f: &FuncObj, input: &Vec<GI>) -> (Flt, Flt, GI, Option<Vec<GI>>) {
+    let mid = admissible_midpoint(input, f.input_kinds());
+    let (fsx, dfsx) = f.call(&input);
+    let points = mid.iter().zip(input.iter())
+        .map(|(&mi, &si)| vec![mi, GI::new_p(si.upper()), GI::new_p(si.lower())])
+        .collect::<Vec<_>>();
+    let (est_max, est_min) = f.call_batch(&points).iter().zip(f.violations(&points))
+        .filter(|&(_, v)| v == 0.0)
+        .fold((NINF, INF), |(hi, lo), (e, _)| (hi.max(e.lower()), lo.min(e.upper())));
+    let (est_max, est_min) = match certify(f, input) {
+        Some(b) => {
+            let fb = f.call(&b).0;
+            (est_max.max(fb.lower()), est_min.min(fb.upper()))
+        }
+        None => (est_max, est_min),
+    };
+    (est_max, est_min, fsx, dfsx)
+}
+
+/// Returns the guaranteed upperbound for the algorithm
+/// from the queue.
+pub fn get_upper_bound<'a, I>(q: I, f_best_high: f64) -> f64
+    where I: IntoIterator<Item=&'a Quple> {
+    let mut max = f_best_high;
+    for qi in q {
+        max = max!{max, qi.fdata.upper()};
+    }
+    max
+}
+
+/// Returns the upper bound and box of the queued box with the highest upper
+/// bound, or max and x when no queued box exceeds max.
+pub fn best_box<'a, I>(q: I, max: f64, x: Vec<GI>) -> (f64, Vec<GI>)
+    where I: IntoIterator<Item=&'a Quple> {
+    let (mut max, mut x) = (max, x);
+    for qi in q {
+        if qi.fdata.upper() > max {
+            max = qi.fdata.upper();
+            x = qi.data.clone();
+        }
+    }
+    (max, x)
+}
@@ -0,0 +1,93 @@
+// Boxes that may still hold a global maximizer.
+//
+// When the search ends, every box that is still queued or that was set aside
+// as refined enough may hold a maximizer unless its upper bound is below the
+// final lower bound. Neighbouring candidates often cover a single maximizer
+// between them, so they can be merged into clusters.
+
+use std::cmp::Ordering;
+
+use gelpia_utils::Quple;
+
+use gr::{GI, InputKind, kind_of, hull};
+
+// A box that may hold a global extremum, or a cluster of them.
+#[derive(Clone)]
+pub struct Candidate {
+    pub at: Vec<GI>,
+    // Enclosure of the function over the box.
+    pub value: GI,
+    // The number of boxes merged into this one.
+    pub boxes: usize,
+}
+
+// The boxes whose upper bound is at least lower, highest upper bound first.
+pub fn collect<'a, I>(boxes: I, lower: f64) -> Vec<Candidate>
+    where I: IntoIterator<Item=&'a Quple> {
+    let mut out: Vec<Candidate> = boxes.into_iter()
+        .filter(|qi| qi.fdata.upper() >= lower)
+        .map(|qi| Candidate{at: qi.data.clone(), value: qi.fdata, boxes: 1})
+        .collect();
+    sort(&mut out);
+    out
+}
+
+fn sort(candidates: &mut Vec<Candidate>) {
+    candidates.sort_by(|a, b| b.value.upper().partial_cmp(&a.value.upper())
+                       .unwrap_or(Ordering::Equal));
+}
+
+// Whether the boxes touch or overlap, counting discrete inputs as touching
+// when no admissible value lies between them.
+fn adjacent(a: &[GI], b: &[GI], kinds: &[InputKind]) -> bool {
+    a.iter().zip(b.iter()).enumerate().all(|(i, (x, y))| {
+        let (lo, hi) = if x.upper() < y.lower() { (x, y) } else { (y, x) };
+        if lo.upper() >= hi.lower() {
+            return true;
+        }
+        match *kind_of(kinds, i) {
+            InputKind::Real => false,
+            InputKind::Integer => hi.lower() - lo.upper() <= 1.0,
+            InputKind::Values(ref vs) => !vs.iter().any(|&v| lo.upper() < v && v < hi.lower()),
+        }
+    })
+}
+
+// Merges adjacent candidates into clusters, each covered by the hull of its
+// boxes and bounded by the hull of their enclosures.
+pub fn cluster(candidates: Vec<Candidate>, kinds: &[InputKind]) -> Vec<Candidate> {
+    let n = candidates.len();
+    // Union find over the candidates
+    let mut parent: Vec<usize> = (0..n).collect();
+    fn root(parent: &mut Vec<usize>, mut i: usize) -> usize {
+        while parent[i] != i {
+            parent[i] = parent[parent[i]];
+            i = parent[i];
+        }
+        i
+    }
+    for i in 0..n {
+        for j in i + 1..n {
+            if adjacent(&candidates[i].at, &candidates[j].at, kinds) {
+                let (ri, rj) = (root(&mut parent, i), root(&mut parent, j));
+                parent[rj] = ri;
+            }
+        }
+    }
+
+    let mut clusters: Vec<Option<Candidate>> = vec![None; n];
+    for (i, c) in candidates.into_iter().enumerate() {
+        let r = root(&mut parent, i);
+        clusters[r] = Some(match clusters[r].take() {
+            None => c,
+            Some(acc) => Candidate{
+                at: acc.at.iter().zip(c.at.iter()).map(|(&x, &y)| hull(x, y)).collect(),
+                value: hull(acc.value, c.value),
+                boxes: acc.boxes + c.boxes,
+            },
+        });
+    }
+    let mut out: Vec<Candidate> = clusters.into_iter().filter_map(|c| c).collect();
+    sort(&mut out);
+    out
+}
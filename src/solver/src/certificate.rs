@@ -0,0 +1,459 @@
+// Checkable certificates for the bounds found by a search.
+//
+// A proof of an upper bound b on the maximum of f over a domain is a tree of
+// boxes. The root holds the domain, every inner box is split into boxes that
+// cover it, and every leaf carries the evaluation that justifies it: either
+// the enclosure of f over the leaf is at most b, or a constraint certainly
+// fails everywhere in it. A lower bound on the minimum is proved the same way
+// with enclosures that are at least the bound.
+//
+// Proofs are built after the search by splitting the domain the way the
+// branch and bound does until every box is justified, so they need no finer
+// boxes than the search did, except where it ruled boxes out by monotonicity.
+// The search records the enclosures it computed, so that only those boxes
+// are evaluated again. check confirms a proof without any of the search code,
+// it is what the verify binary runs.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::time::Instant;
+
+use serde_json;
+use serde_json::Value;
+
+use gr::{GI, kind_of, split_box, width_box, InputKind};
+
+use function::{FuncObj, FuncDef};
+use function::format::{bits_to_hex, hex_to_bits, interval_to_json, interval_from_json};
+
+use Mode;
+
+pub const FORMAT_NAME: &'static str = "gelpia-certificate";
+pub const FORMAT_VERSION: u64 = 1;
+
+#[derive(Clone)]
+pub enum Node {
+    // A box covered by the boxes with the given indices.
+    Split(Vec<GI>, Vec<usize>),
+    // A leaf with the enclosure of the function over it.
+    Bounded(Vec<GI>, GI),
+    // A leaf with the enclosure of a constraint that certainly fails in it.
+    // Constraints are numbered with the inequalities first, then the
+    // equalities.
+    Infeasible(Vec<GI>, usize, GI),
+}
+
+impl Node {
+    pub fn at(&self) -> &Vec<GI> {
+        match *self {
+            Node::Split(ref x, _) | Node::Bounded(ref x, _) | Node::Infeasible(ref x, _, _) => x,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Proof {
+    // Mode::Max for an upper bound on the maximum, Mode::Min for a lower
+    // bound on the minimum.
+    pub mode: Mode,
+    pub bound: f64,
+    // The root is the first node.
+    pub nodes: Vec<Node>,
+}
+
+// The proofs of the bounds of one search along with the problem they are
+// about.
+pub struct Certificate {
+    pub function: FuncDef,
+    // The tolerance of the equality constraints, see FuncObj::feasibility.
+    pub eq_tolerance: f64,
+    pub proofs: Vec<Proof>,
+}
+
+// The enclosures of the function over the boxes a search evaluated. Splitting
+// a box always gives the same parts, so the boxes a proof splits into are
+// found here as long as the search split them too. A search for the minimum
+// evaluates -f, which is undone when recording.
+pub struct Enclosures {
+    negated: bool,
+    values: HashMap<Vec<u64>, GI>,
+}
+
+impl Enclosures {
+    pub fn new(negated: bool) -> Enclosures {
+        Enclosures{negated: negated, values: HashMap::new()}
+    }
+
+    pub fn record(&mut self, x: &Vec<GI>, fx: GI) {
+        self.values.insert(key(x), if self.negated { -fx } else { fx });
+    }
+
+    // The enclosure of f over x, if the search evaluated it.
+    fn get(&self, x: &Vec<GI>) -> Option<GI> {
+        self.values.get(&key(x)).cloned()
+    }
+}
+
+fn key(x: &Vec<GI>) -> Vec<u64> {
+    x.iter().flat_map(|xi| vec![xi.lower().to_bits(), xi.upper().to_bits()]).collect()
+}
+
+// What building proofs may still take once the search is over: the rest of
+// its timeout and of its limit on evaluations, None for no limit. Only boxes
+// the search did not evaluate count, the others take no longer than they did
+// in the search.
+pub struct Limits {
+    pub deadline: Option<Instant>,
+    pub evaluations: Option<u32>,
+}
+
+fn show(x: &Vec<GI>) -> String {
+    format!("[{}]", x.iter().map(|xi| xi.to_string()).collect::<Vec<_>>().join(", "))
+}
+
+// The enclosure of constraint i of f over x and whether it shows that the
+// constraint fails everywhere in x, None if there is no such constraint.
+fn constraint(f: &FuncObj, i: usize, x: &Vec<GI>) -> Option<(GI, bool)> {
+    let n = f.constraints().len();
+    if i < n {
+        let gx = f.constraints()[i].call(x).0;
+        return Some((gx, gx.lower() > 0.0));
+    }
+    f.equalities().get(i - n).map(|h| {
+        let (hx, eps) = (h.call(x).0, f.equality_tolerance());
+        (hx, hx.lower() > eps || hx.upper() < -eps)
+    })
+}
+
+// The function whose upper bound a proof in the given mode bounds, along with
+// that bound.
+fn maximized(f: &FuncObj, mode: Mode, bound: f64) -> Result<(FuncObj, f64), String> {
+    match mode {
+        Mode::Max => Ok((f.clone(), bound)),
+        Mode::Min => Ok((f.negated(), -bound)),
+        Mode::Range => Err("A proof bounds either the maximum or the minimum".to_string()),
+    }
+}
+
+// Proves the bound on the maximum or the minimum of f over domain, splitting
+// boxes until each one is justified. The enclosures in known are used instead
+// of evaluating f again, the other boxes count against the limits.
+// Fails if a box at most tol_x wide is neither within the bound nor
+// infeasible, or if the limits are reached.
+pub fn prove(f: &FuncObj, domain: &Vec<GI>, mode: Mode, bound: f64, tol_x: f64,
+             known: Option<&Enclosures>, limits: &mut Limits) -> Result<Proof, String> {
+    let (_, b) = maximized(f, mode, bound)?;
+    let kinds = f.input_kinds();
+    let n_constraints = f.constraints().len() + f.equalities().len();
+    let mut nodes: Vec<Option<Node>> = vec![None];
+    let mut todo = vec![(0, domain.clone())];
+    'boxes: while let Some((k, x)) = todo.pop() {
+        let fx = match known.and_then(|e| e.get(&x)) {
+            Some(fx) => fx,
+            None => {
+                if limits.deadline.map_or(false, |d| Instant::now() >= d) {
+                    return Err(format!("Unable to prove the bound {} before the timeout",
+                                       bound));
+                }
+                match limits.evaluations {
+                    Some(0) => return Err(format!("Unable to prove the bound {} within the \
+                                                   limit on evaluations", bound)),
+                    Some(ref mut n) => *n -= 1,
+                    None => (),
+                }
+                f.call(&x).0
+            }
+        };
+        let gx = if mode == Mode::Min { -fx } else { fx };
+        if gx.upper() <= b {
+            nodes[k] = Some(Node::Bounded(x, fx));
+            continue;
+        }
+        for i in 0..n_constraints {
+            if let Some((cx, true)) = constraint(f, i, &x) {
+                nodes[k] = Some(Node::Infeasible(x, i, cx));
+                continue 'boxes;
+            }
+        }
+        let (parts, is_split) = split_box(&x, kinds);
+        if !is_split || width_box(&x, tol_x, kinds) {
+            return Err(format!("Unable to prove the bound {}, the function is \
+                                enclosed by {} over {}", bound, fx.to_string(), show(&x)));
+        }
+        let first = nodes.len();
+        for p in parts {
+            todo.push((nodes.len(), p));
+            nodes.push(None);
+        }
+        nodes[k] = Some(Node::Split(x, (first..nodes.len()).collect()));
+    }
+    Ok(Proof{mode: mode, bound: bound,
+             nodes: nodes.into_iter().map(|n| n.unwrap()).collect()})
+}
+
+// Whether an admissible value of the kind lies strictly between a and b.
+fn admits_between(kind: &InputKind, a: f64, b: f64) -> bool {
+    match *kind {
+        InputKind::Real => a < b,
+        InputKind::Integer => a.floor() + 1.0 < b,
+        InputKind::Values(ref vs) => vs.iter().any(|&v| a < v && v < b),
+    }
+}
+
+fn contains(outer: &GI, inner: &GI) -> bool {
+    outer.lower() <= inner.lower() && inner.upper() <= outer.upper()
+}
+
+// Checks that the children cover every admissible point of x. All children
+// have to contain x in every input but one, and along that input their union
+// has to leave no admissible value of x out.
+fn covers(x: &Vec<GI>, children: &[&Vec<GI>], kinds: &[InputKind]) -> Result<(), String> {
+    if children.iter().any(|c| c.len() != x.len()) {
+        return Err("A part has the wrong number of inputs".to_string());
+    }
+    let split: Vec<usize> = (0..x.len())
+        .filter(|&i| children.iter().any(|c| !contains(&c[i], &x[i])))
+        .collect();
+    let d = match split.len() {
+        0 => return Ok(()),
+        1 => split[0],
+        _ => return Err(format!("The parts differ from the box in inputs {:?}", split)),
+    };
+    let kind = kind_of(kinds, d);
+    let whole = match kind.snap(&x[d]) {
+        Some(w) => w,
+        None => return Ok(()),
+    };
+    let mut parts: Vec<GI> = children.iter().map(|c| c[d]).collect();
+    if parts.iter().any(|p| !(p.lower() <= p.upper())) {
+        return Err(format!("Input {} of a part is not an interval", d));
+    }
+    parts.sort_by(|a, b| a.lower().partial_cmp(&b.lower()).unwrap_or(Ordering::Equal));
+    if !(parts[0].lower() <= whole.lower()) {
+        return Err(format!("Input {} of the parts starts above {}", d, whole.lower()));
+    }
+    let mut reach = parts[0].upper();
+    for p in &parts[1..] {
+        if admits_between(kind, reach, p.lower()) {
+            return Err(format!("Input {} of the parts leaves out values between {} and {}",
+                               d, reach, p.lower()));
+        }
+        reach = reach.max(p.upper());
+    }
+    if !(reach >= whole.upper()) {
+        return Err(format!("Input {} of the parts ends below {}", d, whole.upper()));
+    }
+    Ok(())
+}
+
+// Checks the proof for f over domain without trusting how it was built: the
+// root has to hold the domain, with discrete inputs snapped to admissible
+// values, the nodes have to form a tree in which the parts of every box cover
+// it, and every leaf is evaluated again to confirm its justification. Returns
+// the number of leaves.
+pub fn check(f: &FuncObj, domain: &Vec<GI>, proof: &Proof) -> Result<usize, String> {
+    let (g, b) = maximized(f, proof.mode, proof.bound)?;
+    let kinds = f.input_kinds();
+    let nodes = &proof.nodes;
+    let root = match nodes.first() {
+        Some(r) => r.at(),
+        None => return Err("The proof has no nodes".to_string()),
+    };
+    if root.len() != domain.len() {
+        return Err(format!("The root has {} inputs, the domain {}", root.len(), domain.len()));
+    }
+    for (i, x) in domain.iter().enumerate() {
+        match kind_of(kinds, i).snap(x) {
+            Some(ref s) if !contains(&root[i], s) =>
+                return Err(format!("The root does not cover input {} of the domain", i)),
+            _ => (),
+        }
+    }
+
+    // Every node but the root is the part of exactly one box, and all of them
+    // can be reached from the root.
+    let mut parents = vec![0; nodes.len()];
+    for (k, n) in nodes.iter().enumerate() {
+        if let Node::Split(ref x, ref children) = *n {
+            if children.iter().any(|&c| c == 0 || c >= nodes.len()) {
+                return Err(format!("Node {}: invalid part indices {:?}", k, children));
+            }
+            for &c in children {
+                parents[c] += 1;
+            }
+            let parts: Vec<&Vec<GI>> = children.iter().map(|&c| nodes[c].at()).collect();
+            if parts.is_empty() {
+                return Err(format!("Node {}: a split box without parts", k));
+            }
+            covers(x, &parts, kinds).map_err(|msg| format!("Node {}: {}", k, msg))?;
+        }
+    }
+    if let Some(k) = (1..nodes.len()).find(|&k| parents[k] != 1) {
+        return Err(format!("Node {} is a part of {} boxes", k, parents[k]));
+    }
+    let mut reached = 1;
+    let mut todo = vec![0];
+    while let Some(k) = todo.pop() {
+        if let Node::Split(_, ref children) = nodes[k] {
+            reached += children.len();
+            todo.extend(children.iter().cloned());
+        }
+    }
+    if reached != nodes.len() {
+        return Err(format!("Only {} of {} nodes descend from the root", reached, nodes.len()));
+    }
+
+    let mut leaves = 0;
+    for (k, n) in nodes.iter().enumerate() {
+        match *n {
+            Node::Split(..) => continue,
+            Node::Bounded(ref x, _) => {
+                let gx = g.call(x).0;
+                if !(gx.upper() <= b) {
+                    let fx = if proof.mode == Mode::Min { -gx } else { gx };
+                    return Err(format!("Node {}: the function is enclosed by {} over {}",
+                                       k, fx.to_string(), show(x)));
+                }
+            }
+            Node::Infeasible(ref x, i, _) => {
+                match constraint(f, i, x) {
+                    Some((_, true)) => (),
+                    Some((cx, false)) =>
+                        return Err(format!("Node {}: constraint {} is enclosed by {} \
+                                            over {}", k, i, cx.to_string(), show(x))),
+                    None => return Err(format!("Node {}: there is no constraint {}", k, i)),
+                }
+            }
+        }
+        leaves += 1;
+    }
+    Ok(leaves)
+}
+
+fn box_to_json(x: &Vec<GI>) -> Value {
+    Value::Array(x.iter().map(interval_to_json).collect())
+}
+
+fn box_from_json(v: &Value, what: &str) -> Result<Vec<GI>, String> {
+    match v.as_array() {
+        Some(a) => a.iter().map(|xi| interval_from_json(xi, what)).collect(),
+        None => Err(format!("{}: expected an array of intervals", what)),
+    }
+}
+
+fn node_to_json(n: &Node) -> Value {
+    match *n {
+        Node::Split(ref x, ref children) => json!({"box": box_to_json(x), "split": children}),
+        Node::Bounded(ref x, fx) => json!({"box": box_to_json(x),
+                                           "bounded": interval_to_json(&fx)}),
+        Node::Infeasible(ref x, i, cx) => json!({"box": box_to_json(x),
+                                                 "infeasible": {"constraint": i,
+                                                                "value": interval_to_json(&cx)}}),
+    }
+}
+
+fn node_from_json(v: &Value, what: &str) -> Result<Node, String> {
+    let x = match v.get("box") {
+        Some(x) => box_from_json(x, what)?,
+        None => return Err(format!("{}: missing 'box'", what)),
+    };
+    if let Some(children) = v.get("split") {
+        let children = children.as_array().and_then(|a| a.iter().map(|c| c.as_u64())
+                                                    .collect::<Option<Vec<_>>>())
+            .ok_or(format!("{}: expected an array of node indices as 'split'", what))?;
+        Ok(Node::Split(x, children.into_iter().map(|c| c as usize).collect()))
+    }
+    else if let Some(fx) = v.get("bounded") {
+        Ok(Node::Bounded(x, interval_from_json(fx, what)?))
+    }
+    else if let Some(c) = v.get("infeasible") {
+        let i = c.get("constraint").and_then(|i| i.as_u64())
+            .ok_or(format!("{}: missing the index of the failing constraint", what))?;
+        match c.get("value") {
+            Some(cx) => Ok(Node::Infeasible(x, i as usize, interval_from_json(cx, what)?)),
+            None => Err(format!("{}: missing the value of the failing constraint", what)),
+        }
+    }
+    else {
+        Err(format!("{}: expected 'split', 'bounded' or 'infeasible'", what))
+    }
+}
+
+fn hex_field(v: &Value, name: &str, what: &str) -> Result<f64, String> {
+    match v.get(name).and_then(|s| s.as_str()) {
+        Some(s) => hex_to_bits(s),
+        None => Err(format!("{}: missing '{}'", what, name)),
+    }
+}
+
+impl Certificate {
+    pub fn to_json(&self) -> String {
+        let proofs: Vec<Value> = self.proofs.iter().map(|p| json!({
+            "mode": if p.mode == Mode::Min { "min" } else { "max" },
+            "bound": bits_to_hex(p.bound),
+            "nodes": p.nodes.iter().map(node_to_json).collect::<Vec<_>>(),
+        })).collect();
+        let doc = json!({
+            "format": FORMAT_NAME,
+            "version": FORMAT_VERSION,
+            "function": self.function.to_value(),
+            "eq_tolerance": bits_to_hex(self.eq_tolerance),
+            "proofs": proofs,
+        });
+        serde_json::to_string(&doc).unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<Certificate, String> {
+        let doc: Value = serde_json::from_str(text)
+            .map_err(|e| format!("Invalid JSON: {}", e))?;
+        if doc.get("format").and_then(|f| f.as_str()) != Some(FORMAT_NAME) {
+            return Err(format!("Not a {} document", FORMAT_NAME));
+        }
+        match doc.get("version").and_then(|v| v.as_u64()) {
+            Some(FORMAT_VERSION) => (),
+            Some(v) => return Err(format!("Unsupported certificate version {}, \
+                                           expected {}", v, FORMAT_VERSION)),
+            None => return Err("Missing certificate version".to_string()),
+        }
+        let function = match doc.get("function") {
+            Some(f) => FuncDef::from_value(f)?,
+            None => return Err("Missing 'function'".to_string()),
+        };
+        let eq_tolerance = hex_field(&doc, "eq_tolerance", "Certificate")?;
+        let mut proofs = vec![];
+        let empty = vec![];
+        let ps = doc.get("proofs").and_then(|p| p.as_array()).unwrap_or(&empty);
+        for (pos, p) in ps.iter().enumerate() {
+            let what = format!("Proof {}", pos);
+            let mode = match p.get("mode").and_then(|m| m.as_str()) {
+                Some("max") => Mode::Max,
+                Some("min") => Mode::Min,
+                _ => return Err(format!("{}: expected 'max' or 'min' as mode", what)),
+            };
+            let bound = hex_field(p, "bound", &what)?;
+            let mut nodes = vec![];
+            for (k, n) in p.get("nodes").and_then(|n| n.as_array()).unwrap_or(&empty)
+                .iter().enumerate() {
+                nodes.push(node_from_json(n, &format!("{}, node {}", what, k))?);
+            }
+            proofs.push(Proof{mode: mode, bound: bound, nodes: nodes});
+        }
+        Ok(Certificate{function: function, eq_tolerance: eq_tolerance, proofs: proofs})
+    }
+
+    pub fn load(path: &str) -> Result<Certificate, String> {
+        let mut text = String::new();
+        File::open(path)
+            .and_then(|mut f| f.read_to_string(&mut text))
+            .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        Certificate::from_json(&text)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        File::create(path)
+            .and_then(|mut f| f.write_all(self.to_json().as_bytes()))
+            .map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+}
@@ -0,0 +1,329 @@
+// Saving the state of a search to resume it later.
+//
+// A checkpoint holds everything the branch and bound and the EA carry from
+// one iteration to the next: the queue in its internal order, the refined
+// boxes kept as candidates, both bounds and their boxes, the number of
+// evaluations, the EA population and the state of its generator. Along with
+// them it records the function, parameters and domain it was made for, so it
+// is not resumed on a different problem. Floats are stored as their bit
+// patterns, so a resumed search picks up exactly where the saved one was.
+//
+// Checkpoints are written to a temporary file that is then renamed over the
+// old one, so a crash while writing leaves the previous checkpoint intact.
+
+use std::fs::{File, rename};
+use std::io::{Read, Write};
+use std::sync::{RwLock, Arc};
+use std::time::{Duration, Instant};
+
+use gelpia_utils::{Quple, Flt};
+
+use gr::GI;
+
+use ga::Individual;
+
+use function::{FuncObj, FuncDef};
+use function::format::{put_u32, put_f64, put_str, put_interval, Reader, bits_to_hex,
+                       kind_to_json};
+
+use Mode;
+
+const MAGIC: &'static [u8; 4] = b"GLPC";
+pub const FORMAT_VERSION: u32 = 3;
+
+// Where the branch and bound stands between two iterations.
+#[derive(Clone)]
+pub struct Progress {
+    pub f_best_low: Flt,
+    pub f_best_high: Flt,
+    // Box in which f_best_high is attained.
+    pub best_x: Vec<GI>,
+    // The number of boxes evaluated.
+    pub evaluations: u32,
+    // The number of boxes taken from the queue.
+    pub iterations: u32,
+}
+
+#[derive(Clone)]
+pub struct Checkpoint {
+    // The function as identify describes it.
+    pub function: String,
+    pub params: Vec<GI>,
+    pub domain: Vec<GI>,
+    // Mode::Max or Mode::Min, a minimum is searched as the maximum of -f.
+    pub mode: Mode,
+    pub progress: Progress,
+    // Box holding the branch and bound's best estimate and the best value
+    // found by the EA.
+    pub x_bestbb: Vec<GI>,
+    pub f_bestag: Flt,
+    pub queue: Vec<Quple>,
+    pub done: Vec<Quple>,
+    pub population: Vec<Individual>,
+    // State of the EA's generator, None if the EA has not run.
+    pub rng: Option<[u32; 4]>,
+}
+
+// The function as recorded in checkpoints: its definition with the constants,
+// parameters, constraints and equalities, the kinds of its inputs and the
+// tolerance on its equalities, as a JSON document.
+pub fn identify(f: &FuncObj) -> String {
+    let def = FuncDef{instructions: f.tape(), constants: f.constants().clone(),
+                      names: vec![], domain: vec![], kinds: vec![], param_names: vec![],
+                      params: f.user_vars().clone(), constraints: f.constraint_tapes(),
+                      equalities: f.equality_tapes(), metadata: Default::default()};
+    let kinds: Vec<_> = f.input_kinds().iter()
+        .map(|k| {
+            let mut kind = json!({});
+            kind_to_json(&mut kind, k);
+            kind
+        })
+        .collect();
+    json!({"function": def.to_value(), "kinds": kinds,
+           "equality_tolerance": bits_to_hex(f.equality_tolerance())}).to_string()
+}
+
+fn same_box(a: &[GI], b: &[GI]) -> bool {
+    a.len() == b.len() && a.iter().zip(b.iter()).all(|(x, y)| {
+        x.lower().to_bits() == y.lower().to_bits() && x.upper().to_bits() == y.upper().to_bits()
+    })
+}
+
+fn put_box(out: &mut Vec<u8>, x: &[GI]) {
+    put_u32(out, x.len() as u32);
+    for xi in x {
+        put_interval(out, xi);
+    }
+}
+
+fn read_box(r: &mut Reader) -> Result<Vec<GI>, String> {
+    let n = r.u32()?;
+    (0..n).map(|_| r.interval()).collect()
+}
+
+// The gradient is stored after a flag that tells whether there is one.
+fn put_quple(out: &mut Vec<u8>, q: &Quple) {
+    put_f64(out, q.p);
+    put_u32(out, q.pf);
+    put_box(out, &q.data);
+    put_interval(out, &q.fdata);
+    match q.dfdata {
+        Some(ref d) => {
+            put_u32(out, 1);
+            put_box(out, d);
+        }
+        None => put_u32(out, 0),
+    }
+}
+
+fn read_quple(r: &mut Reader) -> Result<Quple, String> {
+    let p = r.f64()?;
+    let pf = r.u32()?;
+    let data = read_box(r)?;
+    let fdata = r.interval()?;
+    let dfdata = if r.u32()? != 0 { Some(read_box(r)?) } else { None };
+    Ok(Quple{p: p, pf: pf, data: data, fdata: fdata, dfdata: dfdata})
+}
+
+fn put_quples(out: &mut Vec<u8>, qs: &[Quple]) {
+    put_u32(out, qs.len() as u32);
+    for q in qs {
+        put_quple(out, q);
+    }
+}
+
+fn read_quples(r: &mut Reader) -> Result<Vec<Quple>, String> {
+    let n = r.u32()?;
+    (0..n).map(|_| read_quple(r)).collect()
+}
+
+impl Checkpoint {
+    // Checks that the checkpoint was made for a search of f over domain in the
+    // given mode.
+    pub fn matches(&self, f: &FuncObj, domain: &[GI], mode: Mode) -> Result<(), String> {
+        if self.function != identify(f) || !same_box(&self.params, f.user_vars()) {
+            return Err("The checkpoint was made for a different function".to_string());
+        }
+        if !same_box(&self.domain, domain) {
+            return Err("The checkpoint was made for a different domain".to_string());
+        }
+        if self.mode != mode {
+            return Err(format!("The checkpoint was made in the {} mode",
+                               if self.mode == Mode::Min { "min" } else { "max" }));
+        }
+        Ok(())
+    }
+
+    pub fn to_binary(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        put_u32(&mut out, FORMAT_VERSION);
+        put_str(&mut out, &self.function);
+        put_box(&mut out, &self.params);
+        put_box(&mut out, &self.domain);
+        put_u32(&mut out, if self.mode == Mode::Min { 1 } else { 0 });
+        put_f64(&mut out, self.progress.f_best_low);
+        put_f64(&mut out, self.progress.f_best_high);
+        put_box(&mut out, &self.progress.best_x);
+        put_u32(&mut out, self.progress.evaluations);
+        put_u32(&mut out, self.progress.iterations);
+        put_box(&mut out, &self.x_bestbb);
+        put_f64(&mut out, self.f_bestag);
+        put_quples(&mut out, &self.queue);
+        put_quples(&mut out, &self.done);
+        put_u32(&mut out, self.population.len() as u32);
+        for ind in &self.population {
+            put_box(&mut out, &ind.solution);
+            put_f64(&mut out, ind.fitness);
+            put_f64(&mut out, ind.violation);
+        }
+        match self.rng {
+            Some(state) => {
+                put_u32(&mut out, 1);
+                for &s in &state {
+                    put_u32(&mut out, s);
+                }
+            }
+            None => put_u32(&mut out, 0),
+        }
+        out
+    }
+
+    pub fn from_binary(data: &[u8]) -> Result<Checkpoint, String> {
+        let mut r = Reader::new(data);
+        if r.take(4).ok() != Some(&MAGIC[..]) {
+            return Err("Not a checkpoint file".to_string());
+        }
+        match r.u32()? {
+            FORMAT_VERSION => (),
+            v => return Err(format!("Unsupported checkpoint version {}, expected {}",
+                                    v, FORMAT_VERSION)),
+        }
+        let function = r.string()?;
+        let params = read_box(&mut r)?;
+        let domain = read_box(&mut r)?;
+        let mode = match r.u32()? {
+            0 => Mode::Max,
+            1 => Mode::Min,
+            m => return Err(format!("Unknown mode {} in checkpoint", m)),
+        };
+        let f_best_low = r.f64()?;
+        let f_best_high = r.f64()?;
+        let best_x = read_box(&mut r)?;
+        let evaluations = r.u32()?;
+        let iterations = r.u32()?;
+        let x_bestbb = read_box(&mut r)?;
+        let f_bestag = r.f64()?;
+        let queue = read_quples(&mut r)?;
+        let done = read_quples(&mut r)?;
+        let mut population = vec![];
+        for _ in 0..r.u32()? {
+            let solution = read_box(&mut r)?;
+            let fitness = r.f64()?;
+            population.push(Individual{solution: solution, fitness: fitness,
+                                       violation: r.f64()?});
+        }
+        let rng = if r.u32()? != 0 {
+            let mut state = [0; 4];
+            for s in state.iter_mut() {
+                *s = r.u32()?;
+            }
+            if state == [0; 4] {
+                return Err("The generator state in the checkpoint is all zero".to_string());
+            }
+            Some(state)
+        } else {
+            None
+        };
+        if r.position() != data.len() {
+            return Err(format!("{} trailing bytes after checkpoint",
+                               data.len() - r.position()));
+        }
+        Ok(Checkpoint{function: function, params: params, domain: domain, mode: mode,
+                      progress: Progress{f_best_low: f_best_low, f_best_high: f_best_high,
+                                         best_x: best_x, evaluations: evaluations,
+                                         iterations: iterations},
+                      x_bestbb: x_bestbb, f_bestag: f_bestag,
+                      queue: queue, done: done, population: population, rng: rng})
+    }
+
+    pub fn load(path: &str) -> Result<Checkpoint, String> {
+        let mut data = vec![];
+        File::open(path)
+            .and_then(|mut f| f.read_to_end(&mut data))
+            .map_err(|e| format!("Unable to read {}: {}", path, e))?;
+        Checkpoint::from_binary(&data).map_err(|msg| format!("{}: {}", path, msg))
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let tmp = format!("{}.tmp", path);
+        File::create(&tmp)
+            .and_then(|mut f| { f.write_all(&self.to_binary())?; f.sync_all() })
+            .and_then(|_| rename(&tmp, path))
+            .map_err(|e| format!("Unable to write {}: {}", path, e))
+    }
+}
+
+// Writes checkpoints of a running search, taking the parts of the state that
+// are shared between threads from the handles given to new.
+#[derive(Clone)]
+pub struct Writer {
+    path: String,
+    interval: Duration,
+    last: Instant,
+    function: String,
+    params: Vec<GI>,
+    domain: Vec<GI>,
+    mode: Mode,
+    x_bestbb: Arc<RwLock<Vec<GI>>>,
+    f_bestag: Arc<RwLock<Flt>>,
+    population: Arc<RwLock<Vec<Individual>>>,
+    rng: Arc<RwLock<Option<[u32; 4]>>>,
+}
+
+impl Writer {
+    // A writer saving to path at most every seconds.
+    pub fn new(path: &str, seconds: u32, f: &FuncObj, domain: &[GI], mode: Mode,
+               x_bestbb: Arc<RwLock<Vec<GI>>>, f_bestag: Arc<RwLock<Flt>>,
+               population: Arc<RwLock<Vec<Individual>>>,
+               rng: Arc<RwLock<Option<[u32; 4]>>>) -> Writer {
+        Writer{path: path.to_string(),
+               interval: Duration::new(seconds as u64, 0),
+               last: Instant::now(),
+               function: identify(f),
+               params: f.user_vars().clone(),
+               domain: domain.to_vec(),
+               mode: mode,
+               x_bestbb: x_bestbb, f_bestag: f_bestag,
+               population: population, rng: rng}
+    }
+
+    // Whether the next periodic checkpoint is due.
+    pub fn due(&self) -> bool {
+        self.last.elapsed() >= self.interval
+    }
+
+    // Saves the search state, queue in the internal order of the heap it came
+    // from if it is one. The population is read first, the EA puts the state
+    // of its generator back before releasing it.
+    pub fn save<'a, I>(&mut self, progress: Progress, queue: I,
+                       done: &Option<Arc<RwLock<Vec<Quple>>>>) -> Result<(), String>
+        where I: IntoIterator<Item=&'a Quple> {
+        let population = self.population.read().unwrap().clone();
+        let rng = *self.rng.read().unwrap();
+        let checkpoint = Checkpoint{function: self.function.clone(),
+                                    params: self.params.clone(),
+                                    domain: self.domain.clone(),
+                                    mode: self.mode,
+                                    progress: progress,
+                                    x_bestbb: self.x_bestbb.read().unwrap().clone(),
+                                    f_bestag: *self.f_bestag.read().unwrap(),
+                                    queue: queue.into_iter().cloned().collect(),
+                                    done: done.as_ref()
+                                    .map_or(vec![], |d| d.read().unwrap().clone()),
+                                    population: population,
+                                    rng: rng};
+        self.last = Instant::now();
+        checkpoint.save(&self.path)
+    }
+}
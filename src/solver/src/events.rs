@@ -0,0 +1,142 @@
+// Progress of a running search as newline delimited JSON events.
+//
+// Every event is a JSON object on a line of its own, and every event has the
+// same fields whatever the strategy:
+//   event: "bound" when a bound improved, "stats" periodically and "end" once
+//          the search stops
+//   elapsed: seconds since the search started
+//   extremum: "max" or "min", the range mode reports on both
+//   lower, upper: the enclosure of the extremum so far
+//   possible: the best outer bound among the boxes refined enough
+//   evaluations: the number of boxes evaluated
+//   queue: the number of queued boxes
+//   ea_best: the best value found by the EA
+//   termination: why the search stopped, null before the end event
+// JSON has no infinities, so infinite values, such as ea_best without an EA,
+// are null.
+
+use std::fs::File;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use gelpia_utils::{Quple, Flt};
+
+use {Mode, Termination};
+
+// Where the branch and bound stands, in terms of the maximum it searches for.
+pub struct Status {
+    pub f_best_low: Flt,
+    pub f_best_high: Flt,
+    // The guaranteed upper bound, the highest of f_best_high and the upper
+    // bounds of the queued boxes.
+    pub guaranteed: Flt,
+    pub evaluations: u32,
+    pub queue: usize,
+    pub ea_best: Flt,
+}
+
+impl Status {
+    pub fn new<'a, I>(q: I, f_best_low: Flt, f_best_high: Flt, evaluations: u32,
+                      ea_best: Flt) -> Status
+        where I: IntoIterator<Item=&'a Quple> {
+        let (guaranteed, queue) = q.into_iter()
+            .fold((f_best_high, 0), |(g, n), qi| (max!(g, qi.fdata.upper()), n + 1));
+        Status{f_best_low: f_best_low, f_best_high: f_best_high, guaranteed: guaranteed,
+               evaluations: evaluations, queue: queue, ea_best: ea_best}
+    }
+}
+
+enum Sink {
+    Stderr,
+    File(File),
+}
+
+// A sink for the events of one search. Clones write to the same sink.
+#[derive(Clone)]
+pub struct EventLog {
+    out: Arc<Mutex<Sink>>,
+    // Seconds between stats events, 0 for none.
+    interval: Duration,
+    start: Instant,
+    last: Instant,
+    mode: Mode,
+}
+
+impl EventLog {
+    // Events on stderr, with stats every seconds.
+    pub fn stderr(seconds: u32) -> EventLog {
+        EventLog::new(Sink::Stderr, seconds)
+    }
+
+    // Events written to the file at path, which is truncated.
+    pub fn file(path: &str, seconds: u32) -> Result<EventLog, String> {
+        File::create(path)
+            .map(|f| EventLog::new(Sink::File(f), seconds))
+            .map_err(|e| format!("Unable to create {}: {}", path, e))
+    }
+
+    fn new(out: Sink, seconds: u32) -> EventLog {
+        let now = Instant::now();
+        EventLog{out: Arc::new(Mutex::new(out)),
+                 interval: Duration::new(seconds as u64, 0),
+                 start: now, last: now, mode: Mode::Max}
+    }
+
+    // The log of a search for the maximum, or for the minimum as the maximum
+    // of the negated function, starting now.
+    pub fn start(&self, mode: Mode) -> EventLog {
+        let now = Instant::now();
+        EventLog{start: now, last: now, mode: mode, ..self.clone()}
+    }
+
+    // Whether the next stats event is due.
+    pub fn due(&self) -> bool {
+        self.interval != Duration::new(0, 0) && self.last.elapsed() >= self.interval
+    }
+
+    pub fn bound(&self, status: &Status) {
+        self.emit("bound", status, None);
+    }
+
+    pub fn stats(&mut self, status: &Status) {
+        self.last = Instant::now();
+        self.emit("stats", status, None);
+    }
+
+    pub fn end(&self, status: &Status, termination: Termination) {
+        self.emit("end", status, Some(termination));
+    }
+
+    fn emit(&self, event: &str, s: &Status, termination: Option<Termination>) {
+        let elapsed = self.start.elapsed();
+        let elapsed = (elapsed.as_secs() * 1000000 + elapsed.subsec_nanos() as u64 / 1000)
+            as f64 / 1e6;
+        // A minimum is searched for as the maximum of -f
+        let (extremum, lower, upper, possible, ea_best) = match self.mode {
+            Mode::Min => ("min", -s.guaranteed, -s.f_best_low, -s.f_best_high, -s.ea_best),
+            _ => ("max", s.f_best_low, s.guaranteed, s.f_best_high, s.ea_best),
+        };
+        let line = json!({
+            "event": event,
+            "elapsed": elapsed,
+            "extremum": extremum,
+            "lower": lower,
+            "upper": upper,
+            "possible": possible,
+            "evaluations": s.evaluations,
+            "queue": s.queue,
+            "ea_best": ea_best,
+            "termination": termination.map(|t| t.to_string()),
+        });
+        match *self.out.lock().unwrap() {
+            Sink::Stderr => {
+                let _ = writeln!(&mut ::std::io::stderr(), "{}", line);
+            }
+            Sink::File(ref mut f) => {
+                let _ = writeln!(f, "{}", line);
+                let _ = f.flush();
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+// Certified feasible boxes for equality constraints.
+//
+// Points where the equalities h(x) = 0 hold exactly are rarely floats, so once
+// there are equalities the branch and bound can hardly get lower bounds from
+// points alone. Instead a point close to the solutions is found with a few
+// Newton steps, and a small box around it is shown to hold a solution with
+// Miranda's theorem, the intermediate value theorem in several dimensions: if
+// each h_i has opposite signs on the two faces of the box across a variable
+// of its own, h has a zero in the box. Once the inequality constraints hold
+// on the whole box, the enclosure of f over the box bounds f at a feasible
+// point.
+
+use gr::{GI, admissible_midpoint, kind_of};
+
+use function::FuncObj;
+
+const NEWTON_STEPS: usize = 8;
+
+// Radii of the boxes tried around the point, relative to its magnitude. The
+// point itself is tried first, in case the equalities hold there exactly.
+const RADII: [f64; 5] = [0.0, 1e-12, 1e-10, 1e-8, 1e-6];
+
+// The midpoint of the enclosure of h at the point p.
+fn value(h: &FuncObj, p: &[f64]) -> f64 {
+    let x = p.iter().map(|&pi| GI::new_p(pi)).collect();
+    let hx = h.call(&x).0;
+    hx.lower()/2.0 + hx.upper()/2.0
+}
+
+// Solves a x = b by Gaussian elimination with partial pivoting, None if a is
+// singular.
+fn solve(mut a: Vec<Vec<f64>>, mut b: Vec<f64>) -> Option<Vec<f64>> {
+    let n = b.len();
+    for k in 0..n {
+        let pivot = (k..n).max_by(|&i, &j| a[i][k].abs().partial_cmp(&a[j][k].abs())
+                                  .unwrap())?;
+        if a[pivot][k] == 0.0 || !a[pivot][k].is_finite() {
+            return None;
+        }
+        a.swap(k, pivot);
+        b.swap(k, pivot);
+        for i in k + 1..n {
+            let factor = a[i][k]/a[k][k];
+            for j in k..n {
+                a[i][j] -= factor*a[k][j];
+            }
+            b[i] -= factor*b[k];
+        }
+    }
+    let mut x = vec![0.0; n];
+    for k in (0..n).rev() {
+        let s = (k + 1..n).fold(b[k], |acc, j| acc - a[k][j]*x[j]);
+        x[k] = s/a[k][k];
+    }
+    Some(x)
+}
+
+// Checks Miranda's theorem for the equalities on the box b, where equality i
+// changes sign across variable vars[i].
+fn miranda(hs: &[FuncObj], vars: &[usize], b: &Vec<GI>) -> bool {
+    hs.iter().zip(vars.iter()).all(|(h, &j)| {
+        let mut face = b.clone();
+        face[j] = GI::new_p(b[j].lower());
+        let lo = h.call(&face).0;
+        face[j] = GI::new_p(b[j].upper());
+        let hi = h.call(&face).0;
+        (lo.upper() <= 0.0 && hi.lower() >= 0.0) || (lo.lower() >= 0.0 && hi.upper() <= 0.0)
+    })
+}
+
+/// Returns a box inside x that certainly holds a point satisfying every
+/// equality and inequality constraint of f, if one can be found near the
+/// midpoint of x.
+pub fn certify(f: &FuncObj, x: &Vec<GI>) -> Option<Vec<GI>> {
+    let hs = f.equalities();
+    let (m, n) = (hs.len(), x.len());
+    if m == 0 || m > n {
+        return None;
+    }
+    // Discrete inputs stay at an admissible value
+    let kinds = f.input_kinds();
+    let mut p: Vec<f64> = admissible_midpoint(x, kinds).iter().map(|xi| xi.lower())
+        .collect();
+
+    // Each equality gets the variable it is most sensitive to over x
+    let mut vars: Vec<usize> = Vec::with_capacity(m);
+    for step in 0..NEWTON_STEPS {
+        let hp: Vec<f64> = hs.iter().map(|h| value(h, &p)).collect();
+        if hp.iter().any(|v| !v.is_finite()) {
+            return None;
+        }
+        if hp.iter().all(|&v| v == 0.0) {
+            break;
+        }
+        // Forward differences, stepping back where x ends
+        let mut jac = vec![vec![0.0; n]; m];
+        for j in 0..n {
+            if x[j].width() == 0.0 || kind_of(kinds, j).is_discrete() {
+                continue;
+            }
+            let mut d = 1e-7*p[j].abs().max(1.0);
+            if p[j] + d > x[j].upper() {
+                d = -d;
+            }
+            let mut q = p.clone();
+            q[j] += d;
+            for i in 0..m {
+                jac[i][j] = (value(&hs[i], &q) - hp[i])/d;
+            }
+        }
+        if step == 0 {
+            for i in 0..m {
+                let best = (0..n).filter(|j| !vars.contains(j))
+                    .map(|j| (j, (jac[i][j]*x[j].width()).abs()))
+                    .filter(|&(_, s)| s.is_finite() && s > 0.0)
+                    .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+                match best {
+                    Some((j, _)) => vars.push(j),
+                    None => return None,
+                }
+            }
+        }
+        let a = jac.iter().map(|row| vars.iter().map(|&j| row[j]).collect()).collect();
+        let delta = solve(a, hp.iter().map(|v| -v).collect())?;
+        let mut moved = false;
+        for (k, &j) in vars.iter().enumerate() {
+            let next = (p[j] + delta[k]).max(x[j].lower()).min(x[j].upper());
+            moved = moved || next != p[j];
+            p[j] = next;
+        }
+        if !moved {
+            break;
+        }
+    }
+    if vars.is_empty() {
+        // h vanished at the midpoint, so only the point itself is tried
+        vars = (0..m).collect();
+    }
+
+    for &r in RADII.iter() {
+        let mut b: Vec<GI> = p.iter().map(|&pj| GI::new_p(pj)).collect();
+        for &j in &vars {
+            let rj = r*p[j].abs().max(1.0);
+            b[j] = GI::new_d((p[j] - rj).max(x[j].lower()), (p[j] + rj).min(x[j].upper()));
+        }
+        if miranda(hs, &vars, &b) &&
+            f.constraints().iter().all(|g| g.call(&b).0.upper() <= 0.0) {
+            return Some(b);
+        }
+    }
+    None
+}
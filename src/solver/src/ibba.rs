@@ -0,0 +1,189 @@
+// Interval branch and bound on a single thread, used by the serial and the
+// cooperative strategy.
+
+use std::collections::BinaryHeap;
+use std::io::Write;
+use std::sync::{Barrier, RwLock, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use gelpia_utils::{Quple, Flt, eps_tol, check_diff};
+
+use gr::{GI, width_box, split_box};
+
+use function::{FuncObj, Feasibility};
+
+use bounds::{est_func, get_upper_bound};
+use certificate::Enclosures;
+use checkpoint::{Progress, Writer};
+use events::{EventLog, Status};
+use lockstep::Lockstep;
+use {Tolerances, Termination};
+
+// Returns the lower bound, the upper bound, the domain where this bound
+// occurs, why the search ended, the number of boxes evaluated and the number
+// of boxes taken from the queue. Boxes that are still queued when the search
+// ends are left in q, and boxes refined enough that may still hold the
+// maximum are added to done if it is given. Boxes that certainly violate a
+// constraint of f are dropped, so both bounds are -inf when the whole domain
+// does. The enclosures of the evaluated boxes are recorded in known if it is
+// given, see certificate. A search resumed from a checkpoint starts from the
+// given progress with q and done already filled, and the state is saved
+// periodically if checkpoint is given. Progress events go to log if it is
+// given. With lockstep the EA runs on this thread, see lockstep.
+pub fn ibba(x_0: Vec<GI>, tol: Tolerances,
+            f_bestag: Arc<RwLock<Flt>>,
+            f_best_shared: Arc<RwLock<Flt>>,
+            x_bestbb: Arc<RwLock<Vec<GI>>>,
+            b1: Arc<Barrier>, b2: Arc<Barrier>,
+            q: Arc<RwLock<BinaryHeap<Quple>>>,
+            done: Option<Arc<RwLock<Vec<Quple>>>>,
+            known: Option<Arc<RwLock<Enclosures>>>,
+            sync: Arc<AtomicBool>, stop: Arc<AtomicBool>,
+            f: FuncObj,
+            mut log: Option<EventLog>, max_iters: u32,
+            start: Option<Progress>, mut checkpoint: Option<Writer>,
+            mut lockstep: Option<Lockstep>)
+            -> (Flt, Flt, Vec<GI>, Termination, u32, u32) {
+    let (mut f_best_low, mut f_best_high, mut best_x, mut iters, mut steps) = match start {
+        Some(p) => (p.f_best_low, p.f_best_high, p.best_x, p.evaluations, p.iterations),
+        None => {
+            let (est_max, first_val, _) = est_func(&f, &x_0);
+            if let Some(ref known) = known {
+                known.write().unwrap().record(&x_0, first_val);
+            }
+            if f.feasibility(&x_0) != Feasibility::Infeasible {
+                q.write().unwrap().push(Quple{p: est_max, pf: 0, data: x_0.clone(),
+                                              fdata: first_val, dfdata: None});
+            }
+            (est_max, est_max, x_0.clone(), 0, 0)
+        }
+    };
+    let mut termination = Termination::Exhausted;
+    let mut logged_low = f_best_low;
+
+    while q.read().unwrap().len() != 0 {
+        if stop.load(Ordering::Acquire) {
+            termination = Termination::Timeout;
+            break;
+        }
+        if max_iters != 0 && iters >= max_iters {
+            termination = Termination::IterationLimit;
+            break;
+        }
+        if let Some(ref mut w) = checkpoint {
+            if w.due() {
+                let progress = Progress{f_best_low: f_best_low, f_best_high: f_best_high,
+                                        best_x: best_x.clone(), evaluations: iters,
+                                        iterations: steps};
+                if let Err(msg) = w.save(progress, q.read().unwrap().iter(), &done) {
+                    let _ = writeln!(&mut ::std::io::stderr(), "{}", msg);
+                }
+            }
+        }
+        if let Some(ref mut l) = log {
+            if l.due() {
+                l.stats(&Status::new(q.read().unwrap().iter(), f_best_low, f_best_high,
+                                     iters, *f_bestag.read().unwrap()));
+            }
+        }
+        if sync.load(Ordering::Acquire) {
+            // Ugly: Update the update thread's view of the best branch bound.
+            *f_best_shared.write().unwrap() = f_best_low;
+            b1.wait();
+            b2.wait();
+        }
+        // Take q as writable during an iteration
+        let mut q = q.write().unwrap();
+
+        if let Some(ref mut l) = lockstep {
+            l.epoch(steps, Some(&mut q), f_best_low);
+            // The update may have pruned every box
+            if q.is_empty() {
+                continue;
+            }
+        }
+        f_best_low = max!(f_best_low, *f_bestag.read().unwrap());
+
+        if iters % 2048 == 0 {
+            let guaranteed_bound = get_upper_bound(q.iter(), f_best_high);
+            if (guaranteed_bound - f_best_high).abs() < tol.y {
+                f_best_high = guaranteed_bound;
+                termination = Termination::Converged;
+                break;
+            }
+        }
+
+        // The lower bound improves on estimates from the last iteration and
+        // values from the EA
+        if let Some(ref l) = log {
+            if logged_low != f_best_low {
+                logged_low = f_best_low;
+                l.bound(&Status::new(q.iter(), f_best_low, f_best_high, iters,
+                                     *f_bestag.read().unwrap()));
+            }
+        }
+
+        let (ref x, iter_est, fx, ref dfx, gen) =
+            match q.pop() {
+                Some(y) => (y.data, y.p, y.fdata, y.dfdata, y.pf),
+                None    => unreachable!()
+            };
+        steps += 1;
+
+        // Monotonicity only rules out boxes in which no constraint is active,
+        // and says nothing across the gaps between values of discrete inputs
+        if !f.has_discrete_inputs() && check_diff(dfx.clone(), x, &x_0) &&
+            f.feasibility(x) == Feasibility::Feasible {
+            continue;
+        }
+        if fx.upper() < f_best_low ||
+            width_box(x, tol.x, f.input_kinds()) ||
+            eps_tol(fx, iter_est, tol.y, tol.y_rel) {
+                {
+                    if f_best_high < fx.upper() {
+                        f_best_high = fx.upper();
+                        best_x = x.clone();
+                        if let Some(ref l) = log {
+                            l.bound(&Status::new(q.iter(), f_best_low, f_best_high, iters,
+                                                 *f_bestag.read().unwrap()));
+                        }
+                    }
+                    if let Some(ref done) = done {
+                        if fx.upper() >= f_best_low {
+                            done.write().unwrap().push(Quple{p: iter_est, pf: gen,
+                                                             data: x.clone(), fdata: fx,
+                                                             dfdata: None});
+                        }
+                    }
+                    continue;
+                }
+            }
+        else {
+            let (x_s, is_split) = split_box(&x, f.input_kinds());
+            for sx in x_s {
+                let (est_max, fsx, dfsx) = est_func(&f, &sx);
+                if let Some(ref known) = known {
+                    known.write().unwrap().record(&sx, fsx);
+                }
+                if f_best_low < est_max  {
+                    f_best_low = est_max;
+                    *x_bestbb.write().unwrap() = sx.clone();
+                }
+                iters += 1;
+                if is_split && f.feasibility(&sx) != Feasibility::Infeasible {
+                    q.push(Quple{p: est_max,
+                                 pf: gen+1,
+                                 data: sx,
+                                 fdata: fsx,
+                                 dfdata:dfsx});
+                }
+            }
+        }
+    }
+    stop.store(true, Ordering::Release);
+    if let Some(ref l) = log {
+        l.end(&Status::new(q.read().unwrap().iter(), f_best_low, f_best_high, iters,
+                           *f_bestag.read().unwrap()), termination);
+    }
+    (f_best_low, f_best_high, best_x, termination, iters, steps)
+}
@@ -0,0 +1,1085 @@
+// Rigorous global maximization, minimization or range computation of a
+// function over a box.
+//
+// The interval branch and bound algorithm (IBBA) keeps a queue of boxes along
+// with enclosures of the function over them, and refines the most promising
+// boxes until the bound on the maximum is tight enough. In the cooperative
+// strategies an evolutionary algorithm (EA) searches for good points at the
+// same time, and its best value lets the branch and bound discard boxes
+// earlier. Minima are found as the maxima of the negated function, and the
+// range mode searches for both extrema with one shared branch and bound.
+//
+// A search is set up with the Solver builder:
+//
+//     let result = Solver::new(function, domain)
+//         .tolerances(Tolerances{x: 1e-3, y: 1e-3, y_rel: 0.0})
+//         .timeout(10)
+//         .strategy(Strategy::Cooperative)
+//         .solve()?;
+//
+// The modules hold the pieces the strategies are built from.
+
+// External libraries
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Barrier, RwLock, Arc};
+use std::sync::atomic::AtomicBool;
+use std::thread;
+use std::time::{Duration, Instant};
+
+extern crate threadpool;
+#[macro_use]
+extern crate serde_json;
+
+// Internal libraries
+#[macro_use(max)]
+extern crate gelpia_utils;
+use gelpia_utils::{Quple, Flt, NINF, Parameters};
+
+extern crate ga;
+use ga::{ea, Individual};
+
+extern crate gr;
+use gr::{GI, kind_of};
+
+extern crate function;
+use function::FuncObj;
+
+use candidates::Candidate;
+use certificate::{Enclosures, Limits, Proof};
+use checkpoint::{Checkpoint, Progress, Writer};
+use events::EventLog;
+use lockstep::Lockstep;
+
+pub mod bounds;
+pub mod candidates;
+pub mod certificate;
+pub mod checkpoint;
+pub mod events;
+pub mod feasible;
+pub mod ibba;
+pub mod lockstep;
+pub mod parallel;
+pub mod range;
+pub mod update;
+
+
+// How the search is carried out.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    // The branch and bound alone.
+    Serial,
+    // The branch and bound and the EA, with an update thread that periodically
+    // prunes the queue and moves the EA population back into it.
+    Cooperative,
+    // The EA and a branch and bound that refines the whole queue each round
+    // with the given number of worker threads.
+    Parallel(usize),
+}
+
+// Which extrema to search for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mode {
+    Max,
+    Min,
+    // Both the minimum and the maximum, sharing the evaluations of the boxes.
+    Range,
+}
+
+impl FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Mode, String> {
+        match s {
+            "max" => Ok(Mode::Max),
+            "min" => Ok(Mode::Min),
+            "range" => Ok(Mode::Range),
+            _ => Err(format!("Unknown mode '{}', expected min, max or range", s)),
+        }
+    }
+}
+
+// Tolerances deciding when a box is refined enough. A box is no longer split
+// once it is at most x wide, or its upper bound is within y + y_rel*estimate
+// of the estimated maximum in it. The search ends once the guaranteed upper
+// bound is within y of the best upper bound found.
+#[derive(Clone, Copy, Debug)]
+pub struct Tolerances {
+    pub x: f64,
+    pub y: f64,
+    pub y_rel: f64,
+}
+
+// Why a search ended.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Termination {
+    // Every box was refined to within the tolerances or discarded.
+    Exhausted,
+    // The guaranteed upper bound came within tolerance of the best one found.
+    Converged,
+    // The limit on box evaluations was reached.
+    IterationLimit,
+    // The timeout elapsed.
+    Timeout,
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Termination::Exhausted => write!(f, "exhausted"),
+            Termination::Converged => write!(f, "converged"),
+            Termination::IterationLimit => write!(f, "iteration limit"),
+            Termination::Timeout => write!(f, "timeout"),
+        }
+    }
+}
+
+// An enclosure [lower, upper] of the maximum or the minimum of the function.
+#[derive(Clone)]
+pub struct Extremum {
+    pub lower: f64,
+    pub upper: f64,
+    // Box in which the outer bound, the upper one for the maximum and the
+    // lower one for the minimum, is attained.
+    pub at: Vec<GI>,
+    // Every box that may hold the extremum, see Solver::candidates. Empty
+    // unless requested.
+    pub candidates: Vec<Candidate>,
+    // Proof of the outer bound, or why it could not be built, see
+    // Solver::certificate. None unless requested.
+    pub proof: Option<Result<Proof, String>>,
+}
+
+#[derive(Clone)]
+pub struct SolveResult {
+    // The maximum, unless only the minimum was searched for.
+    pub max: Option<Extremum>,
+    // The minimum, unless only the maximum was searched for.
+    pub min: Option<Extremum>,
+    pub termination: Termination,
+    // Number of boxes evaluated by the branch and bound, which max_iters
+    // limits.
+    pub evaluations: u32,
+    // Number of boxes the branch and bound took from its queue to refine or
+    // set aside.
+    pub iterations: u32,
+    // The tolerance eps within which |h(x)| <= eps counted as satisfying the
+    // equality constraints, None without equalities. The bounds enclose the
+    // extrema over the points satisfying the equalities within eps.
+    pub feasibility_tolerance: Option<f64>,
+}
+
+#[derive(Clone)]
+pub struct Solver {
+    function: FuncObj,
+    domain: Vec<GI>,
+    tolerances: Tolerances,
+    timeout: u32,
+    update_interval: u32,
+    max_iters: u32,
+    log: Option<EventLog>,
+    seed: u32,
+    strategy: Strategy,
+    mode: Mode,
+    candidates: bool,
+    certificate: bool,
+    // Path of the checkpoint file and the seconds between checkpoints.
+    checkpoint: Option<(String, u32)>,
+    resume: Option<Arc<Checkpoint>>,
+    deterministic: bool,
+}
+
+impl Solver {
+    // A search for the maximum of function over domain. The defaults are those
+    // of the gelpia frontend: tolerances of 0.001, no timeout, no limit on
+    // evaluations, seed 0, the cooperative strategy and the max mode.
+    pub fn new(function: FuncObj, domain: Vec<GI>) -> Solver {
+        Solver{function: function,
+               domain: domain,
+               tolerances: Tolerances{x: 0.001, y: 0.001, y_rel: 0.0},
+               timeout: 0,
+               update_interval: 0,
+               max_iters: 0,
+               log: None,
+               seed: 0,
+               strategy: Strategy::Cooperative,
+               mode: Mode::Max,
+               candidates: false,
+               certificate: false,
+               checkpoint: None,
+               resume: None,
+               deterministic: false}
+    }
+
+    pub fn tolerances(mut self, tolerances: Tolerances) -> Solver {
+        self.tolerances = tolerances;
+        self
+    }
+
+    // Seconds after which the search stops, 0 for no limit.
+    pub fn timeout(mut self, seconds: u32) -> Solver {
+        self.timeout = seconds;
+        self
+    }
+
+    // Seconds between updates in the cooperative strategy, 0 to never update.
+    pub fn update_interval(mut self, seconds: u32) -> Solver {
+        self.update_interval = seconds;
+        self
+    }
+
+    // Number of box evaluations after which the search stops, 0 for no limit.
+    pub fn max_iters(mut self, iters: u32) -> Solver {
+        self.max_iters = iters;
+        self
+    }
+
+    // Write progress events to log while searching, see events.
+    pub fn log(mut self, log: EventLog) -> Solver {
+        self.log = Some(log);
+        self
+    }
+
+    // Seed of the EA, 0 for a fixed default and 1 for a random seed.
+    pub fn seed(mut self, seed: u32) -> Solver {
+        self.seed = seed;
+        self
+    }
+
+    pub fn strategy(mut self, strategy: Strategy) -> Solver {
+        self.strategy = strategy;
+        self
+    }
+
+    // The range mode always runs its own single threaded branch and bound,
+    // the strategy only applies to the max and min modes.
+    pub fn mode(mut self, mode: Mode) -> Solver {
+        self.mode = mode;
+        self
+    }
+
+    // Report every box that may still hold the extremum when the search ends:
+    // the queued boxes and those refined enough whose upper bound, or lower
+    // bound for the minimum, is at least as good as the final inner bound.
+    pub fn candidates(mut self, candidates: bool) -> Solver {
+        self.candidates = candidates;
+        self
+    }
+
+    // Prove the outer bound of every extremum found, the upper bound of the
+    // maximum and the lower bound of the minimum, once the search ends. The
+    // proofs can be checked with certificate::check.
+    pub fn certificate(mut self, certificate: bool) -> Solver {
+        self.certificate = certificate;
+        self
+    }
+
+    // Save the state of the search to path every seconds and when it ends,
+    // see checkpoint. Only the max and min modes can be checkpointed.
+    pub fn checkpoint(mut self, path: &str, seconds: u32) -> Solver {
+        self.checkpoint = Some((path.to_string(), seconds));
+        self
+    }
+
+    // Continue the search saved in the checkpoint instead of starting anew.
+    // The limit on evaluations counts those before the checkpoint as well,
+    // the timeout starts over.
+    pub fn resume(mut self, checkpoint: Checkpoint) -> Solver {
+        self.resume = Some(Arc::new(checkpoint));
+        self
+    }
+
+    // Run the EA in lockstep with the branch and bound and merge the work of
+    // the parallel workers in a fixed order, so that the same search always
+    // gives bit-identical results, whatever the number of workers. The update
+    // interval then counts epochs of the EA instead of seconds. Results only
+    // repeat if the search does not time out, and the seed must not be 1.
+    pub fn deterministic(mut self, deterministic: bool) -> Solver {
+        self.deterministic = deterministic;
+        self
+    }
+
+    // Runs the search. Fails if a discrete input has no admissible value in
+    // its domain, if one of the search threads fails, or if a checkpoint
+    // cannot be written or was made for another search.
+    pub fn solve(&self) -> Result<SolveResult, String> {
+        let started = Instant::now();
+        // Discrete inputs start out on their admissible values
+        let kinds = self.function.input_kinds();
+        let mut domain = Vec::with_capacity(self.domain.len());
+        for (i, x) in self.domain.iter().enumerate() {
+            match kind_of(kinds, i).snap(x) {
+                Some(x) => domain.push(x),
+                None => return Err(format!("Input {} has no admissible value in {}",
+                                           i, x.to_string())),
+            }
+        }
+        if self.deterministic && self.seed == 1 {
+            return Err("A deterministic search needs a fixed seed".to_string());
+        }
+        if self.checkpoint.is_some() || self.resume.is_some() {
+            if self.mode == Mode::Range {
+                return Err("Only the max and min modes can be checkpointed".to_string());
+            }
+            if let Some(ref checkpoint) = self.resume {
+                checkpoint.matches(&self.function, &domain, self.mode)?;
+            }
+        }
+        let known = if self.certificate {
+            Some(Arc::new(RwLock::new(Enclosures::new(self.mode == Mode::Min))))
+        } else {
+            None
+        };
+        let mut result = Solver{domain: domain.clone(), ..self.clone()}.search(&known)?;
+        if !self.function.equalities().is_empty() {
+            result.feasibility_tolerance = Some(self.function.equality_tolerance());
+        }
+        if let Some(known) = known {
+            // The proofs get what is left of the timeout and of the limit on
+            // evaluations
+            let mut limits = Limits{
+                deadline: if self.timeout == 0 { None }
+                          else { Some(started + Duration::from_secs(self.timeout as u64)) },
+                evaluations: if self.max_iters == 0 { None }
+                             else { Some(self.max_iters.saturating_sub(result.evaluations)) },
+            };
+            let (known, tol) = (known.read().unwrap(), self.tolerances.x);
+            if let Some(ref mut e) = result.max {
+                e.proof = Some(certificate::prove(&self.function, &domain, Mode::Max, e.upper,
+                                                  tol, Some(&known), &mut limits));
+            }
+            if let Some(ref mut e) = result.min {
+                e.proof = Some(certificate::prove(&self.function, &domain, Mode::Min, e.lower,
+                                                  tol, Some(&known), &mut limits));
+            }
+        }
+        Ok(result)
+    }
+
+    // Records the enclosures of the evaluated boxes in known if it is given.
+    fn search(&self, known: &Option<Arc<RwLock<Enclosures>>>)
+              -> Result<SolveResult, String> {
+        // Early out if there are no input variables...
+        if self.domain.len() == 0 {
+            let result = self.function.call(&self.domain).0;
+            let candidates = if self.candidates {
+                vec![Candidate{at: vec![], value: result, boxes: 1}]
+            } else {
+                vec![]
+            };
+            let value = Extremum{lower: result.lower(), upper: result.upper(),
+                                 at: vec![], candidates: candidates, proof: None};
+            return Ok(SolveResult{max: if self.mode != Mode::Min { Some(value.clone()) }
+                                       else { None },
+                                  min: if self.mode != Mode::Max { Some(value) }
+                                       else { None },
+                                  termination: Termination::Exhausted,
+                                  evaluations: 0,
+                                  iterations: 0,
+                                  feasibility_tolerance: None});
+        }
+        match self.mode {
+            Mode::Max => self.maximize(&self.function, known),
+            Mode::Min => {
+                // The maximum of -f is the negated minimum of f
+                let r = self.maximize(&self.function.negated(), known)?;
+                let max = r.max.unwrap();
+                Ok(SolveResult{max: None,
+                               min: Some(Extremum{lower: -max.upper, upper: -max.lower,
+                                                  at: max.at,
+                                                  candidates: max.candidates.into_iter()
+                                                  .map(|c| Candidate{value: -c.value, ..c})
+                                                  .collect(),
+                                                  proof: None}),
+                               termination: r.termination,
+                               evaluations: r.evaluations,
+                               iterations: r.iterations,
+                               feasibility_tolerance: None})
+            }
+            Mode::Range => self.range(known),
+        }
+    }
+
+    fn maximize(&self, function: &FuncObj, known: &Option<Arc<RwLock<Enclosures>>>)
+                -> Result<SolveResult, String> {
+        match self.strategy {
+            Strategy::Serial => self.cooperative(function, false, known),
+            Strategy::Cooperative => self.cooperative(function, true, known),
+            Strategy::Parallel(n_workers) => self.parallel(function, n_workers, known),
+        }
+    }
+
+    fn ea_parameters(&self) -> Parameters {
+        Parameters{population: 50*self.domain.len(), //1000,
+                   selection: 8, //4,
+                   elitism: 5, //2,
+                   mutation: 0.4_f64,//0.3_f64,
+                   crossover: 0.0_f64, // 0.5_f64
+                   seed: self.seed}
+    }
+
+    fn spawn_ea(&self, function: &FuncObj, shared: &Shared)
+                -> Result<thread::JoinHandle<()>, String> {
+        let x_e = self.domain.clone();
+        let param = self.ea_parameters();
+        let f_bestag = shared.f_bestag.clone();
+        let x_bestbb = shared.x_bestbb.clone();
+        let b1 = shared.b1.clone();
+        let b2 = shared.b2.clone();
+        let stop = shared.stop.clone();
+        let sync = shared.sync.clone();
+        let (population, rng) = (shared.population.clone(), shared.rng.clone());
+        let fo_c = function.clone();
+        thread::Builder::new().name("EA".to_string()).spawn(move || {
+            ea(x_e, param, population, rng, f_bestag, x_bestbb, b1, b2, stop, sync, fo_c)
+        }).map_err(|e| format!("Unable to start the EA thread: {}", e))
+    }
+
+    // The EA run by the branch and bound's thread, see lockstep.
+    fn lockstep(&self, function: &FuncObj, shared: &Shared) -> Lockstep {
+        Lockstep::new(self.domain.clone(), self.ea_parameters(), function.clone(),
+                      shared.population.clone(), shared.rng.clone(),
+                      shared.f_bestag.clone(), shared.x_bestbb.clone(),
+                      self.update_interval)
+    }
+
+    fn spawn_timer(&self, shared: &Shared) -> Result<(), String> {
+        let stop = shared.stop.clone();
+        let to = self.timeout;
+        thread::Builder::new().name("Update".to_string()).spawn(move || {
+            update::timer(stop, to)
+        }).map(|_| ()).map_err(|e| format!("Unable to start the timer thread: {}", e))
+    }
+
+    // The queue, the refined boxes if candidates are kept, and the progress to
+    // start the branch and bound from, taken from the checkpoint when
+    // resuming.
+    fn start(&self) -> (Vec<Quple>, Option<Arc<RwLock<Vec<Quple>>>>, Option<Progress>) {
+        let (queue, done, progress) = match self.resume {
+            Some(ref c) => (c.queue.clone(), c.done.clone(), Some(c.progress.clone())),
+            None => (vec![], vec![], None),
+        };
+        let done = if self.candidates { Some(Arc::new(RwLock::new(done))) } else { None };
+        (queue, done, progress)
+    }
+
+    // The event log of a search in the max or the min mode, starting now.
+    fn start_log(&self) -> Option<EventLog> {
+        self.log.as_ref().map(|l| l.start(self.mode))
+    }
+
+    fn writer(&self, function: &FuncObj, shared: &Shared) -> Option<Writer> {
+        self.checkpoint.as_ref().map(|&(ref path, seconds)| {
+            Writer::new(path, seconds, function, &self.domain, self.mode,
+                        shared.x_bestbb.clone(), shared.f_bestag.clone(),
+                        shared.population.clone(), shared.rng.clone())
+        })
+    }
+
+    // Saves the final state of a search that returned the given lower and
+    // upper bound, box, number of evaluations and iterations.
+    fn save<'a, I>(writer: Option<Writer>, r: &(Flt, Flt, Vec<GI>, Termination, u32, u32),
+                   queue: I, done: &Option<Arc<RwLock<Vec<Quple>>>>) -> Result<(), String>
+        where I: IntoIterator<Item=&'a Quple> {
+        match writer {
+            Some(mut w) => w.save(Progress{f_best_low: r.0, f_best_high: r.1,
+                                           best_x: r.2.clone(), evaluations: r.4,
+                                           iterations: r.5},
+                                  queue, done),
+            None => Ok(()),
+        }
+    }
+
+    // The serial strategy, or the cooperative one when with_ea is set.
+    fn cooperative(&self, function: &FuncObj, with_ea: bool,
+                   known: &Option<Arc<RwLock<Enclosures>>>) -> Result<SolveResult, String> {
+        let shared = Shared::new(&self.domain, self.resume.as_ref().map(|c| &**c));
+        let (queue, done, progress) = self.start();
+        // The queue was saved in the internal order of the heap, which building
+        // the heap from it keeps.
+        let q: Arc<RwLock<BinaryHeap<Quple>>> = Arc::new(RwLock::new(BinaryHeap::from(queue)));
+        let writer = self.writer(function, &shared);
+        let lockstep = if with_ea && self.deterministic {
+            Some(self.lockstep(function, &shared))
+        } else {
+            None
+        };
+
+        let ibba_thread = {
+            let x_i = self.domain.clone();
+            let tol = self.tolerances;
+            let s = shared.clone();
+            let (q, done, known) = (q.clone(), done.clone(), known.clone());
+            let fo_c = function.clone();
+            let (log, iters) = (self.start_log(), self.max_iters);
+            let writer = writer.clone();
+            thread::Builder::new().name("IBBA".to_string()).spawn(move || {
+                ibba::ibba(x_i, tol, s.f_bestag, s.f_best_shared, s.x_bestbb,
+                           s.b1, s.b2, q, done, known, s.sync, s.stop, fo_c, log, iters,
+                           progress, writer, lockstep)
+            }).map_err(|e| format!("Unable to start the IBBA thread: {}", e))?
+        };
+
+        let ea_thread = if with_ea && !self.deterministic {
+            let ea_thread = self.spawn_ea(function, &shared)?;
+            // The update thread is not joined, it notices the search stopped
+            // within a second.
+            let s = shared.clone();
+            let q = q.clone();
+            let fo_c = function.clone();
+            let (ui, to) = (self.update_interval, self.timeout);
+            thread::Builder::new().name("Update".to_string()).spawn(move || {
+                update::update(q, s.population, s.f_best_shared, s.stop, s.sync,
+                               s.b1, s.b2, fo_c, ui, to)
+            }).map_err(|e| format!("Unable to start the update thread: {}", e))?;
+            Some(ea_thread)
+        } else {
+            self.spawn_timer(&shared)?;
+            None
+        };
+
+        let result = ibba_thread.join();
+        if let Some(t) = ea_thread {
+            let _ = t.join();
+        }
+        let result = match result {
+            Ok(r) => r,
+            Err(_) => return Err("The IBBA thread panicked".to_string()),
+        };
+        Solver::save(writer, &result, q.read().unwrap().iter(), &done)?;
+        let (min, max, interval, termination, evaluations, iterations) = result;
+        // Go through all remaining intervals from IBBA to find the true
+        // max
+        let (max, interval) = bounds::best_box(q.read().unwrap().iter(), max, interval);
+        // The queued and the refined boxes that may hold the maximum
+        let boxes = match done {
+            Some(done) => candidates::collect(q.read().unwrap().iter()
+                                              .chain(done.read().unwrap().iter()), min),
+            None => vec![],
+        };
+        Ok(SolveResult{max: Some(Extremum{lower: min, upper: max, at: interval,
+                                          candidates: boxes, proof: None}),
+                       min: None,
+                       termination: termination, evaluations: evaluations,
+                       iterations: iterations,
+                       feasibility_tolerance: None})
+    }
+
+    fn parallel(&self, function: &FuncObj, n_workers: usize,
+                known: &Option<Arc<RwLock<Enclosures>>>) -> Result<SolveResult, String> {
+        let shared = Shared::new(&self.domain, self.resume.as_ref().map(|c| &**c));
+        let (queue, done, progress) = self.start();
+        let q: Arc<RwLock<Vec<Quple>>> = Arc::new(RwLock::new(queue));
+        let writer = self.writer(function, &shared);
+        let lockstep = if self.deterministic {
+            Some(self.lockstep(function, &shared))
+        } else {
+            None
+        };
+
+        let ibba_thread = {
+            let x_i = self.domain.clone();
+            let tol = self.tolerances;
+            let s = shared.clone();
+            let (q, done, known) = (q.clone(), done.clone(), known.clone());
+            let fo_c = function.clone();
+            let (log, iters) = (self.start_log(), self.max_iters);
+            let writer = writer.clone();
+            thread::Builder::new().name("IBBA".to_string()).spawn(move || {
+                parallel::ibba(x_i, tol, s.f_bestag, s.f_best_shared, s.x_bestbb,
+                               s.b1, s.b2, q, done, known, s.sync, s.stop, fo_c, log,
+                               iters, n_workers, progress, writer, lockstep)
+            }).map_err(|e| format!("Unable to start the IBBA thread: {}", e))?
+        };
+        let ea_thread = if self.deterministic {
+            None
+        } else {
+            Some(self.spawn_ea(function, &shared)?)
+        };
+        self.spawn_timer(&shared)?;
+
+        let result = ibba_thread.join();
+        if let Some(t) = ea_thread {
+            let _ = t.join();
+        }
+        let result = match result {
+            Ok(r) => r,
+            Err(_) => return Err("The IBBA thread panicked".to_string()),
+        };
+        Solver::save(writer, &result, q.read().unwrap().iter(), &done)?;
+        let (min, max, interval, termination, evaluations, iterations) = result;
+        let (max, interval) = bounds::best_box(q.read().unwrap().iter(), max, interval);
+        // The queued and the refined boxes that may hold the maximum
+        let boxes = match done {
+            Some(done) => candidates::collect(q.read().unwrap().iter()
+                                              .chain(done.read().unwrap().iter()), min),
+            None => vec![],
+        };
+        Ok(SolveResult{max: Some(Extremum{lower: min, upper: max, at: interval,
+                                          candidates: boxes, proof: None}),
+                       min: None,
+                       termination: termination, evaluations: evaluations,
+                       iterations: iterations,
+                       feasibility_tolerance: None})
+    }
+
+    fn range(&self, known: &Option<Arc<RwLock<Enclosures>>>) -> Result<SolveResult, String> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let range_thread = {
+            let x_i = self.domain.clone();
+            let tol = self.tolerances;
+            let stop = stop.clone();
+            let fo_c = self.function.clone();
+            let (log, iters, candidates) = (self.log.clone(), self.max_iters,
+                                            self.candidates);
+            let known = known.clone();
+            thread::Builder::new().name("IBBA".to_string()).spawn(move || {
+                range::range(x_i, tol, stop, fo_c, log, iters, candidates, known)
+            }).map_err(|e| format!("Unable to start the IBBA thread: {}", e))?
+        };
+        let to = self.timeout;
+        thread::Builder::new().name("Update".to_string()).spawn(move || {
+            update::timer(stop, to)
+        }).map_err(|e| format!("Unable to start the timer thread: {}", e))?;
+
+        let (max, min, termination, evaluations, iterations) = match range_thread.join() {
+            Ok(r) => r,
+            Err(_) => return Err("The IBBA thread panicked".to_string()),
+        };
+        Ok(SolveResult{max: Some(Extremum{lower: max.0, upper: max.1, at: max.2,
+                                          candidates: max.3, proof: None}),
+                       min: Some(Extremum{lower: min.0, upper: min.1, at: min.2,
+                                          candidates: min.3, proof: None}),
+                       termination: termination, evaluations: evaluations,
+                       iterations: iterations,
+                       feasibility_tolerance: None})
+    }
+}
+
+// State shared between the branch and bound, the EA and the update thread.
+#[derive(Clone)]
+struct Shared {
+    // Best value found by the EA.
+    f_bestag: Arc<RwLock<Flt>>,
+    // The branch and bound's lower bound, as seen by the update thread.
+    f_best_shared: Arc<RwLock<Flt>>,
+    // Box holding the branch and bound's best estimate, where the EA samples.
+    x_bestbb: Arc<RwLock<Vec<GI>>>,
+    b1: Arc<Barrier>,
+    b2: Arc<Barrier>,
+    sync: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    // The EA population and the state of its generator.
+    population: Arc<RwLock<Vec<Individual>>>,
+    rng: Arc<RwLock<Option<[u32; 4]>>>,
+}
+
+impl Shared {
+    // The state of a new search over x_0, or of the one saved in checkpoint.
+    fn new(x_0: &Vec<GI>, checkpoint: Option<&Checkpoint>) -> Shared {
+        let (f_bestag, x_bestbb, population, rng) = match checkpoint {
+            Some(c) => (c.f_bestag, c.x_bestbb.clone(), c.population.clone(), c.rng),
+            None => (NINF, x_0.clone(), vec![], None),
+        };
+        Shared{f_bestag: Arc::new(RwLock::new(f_bestag)),
+               f_best_shared: Arc::new(RwLock::new(NINF)),
+               x_bestbb: Arc::new(RwLock::new(x_bestbb)),
+               b1: Arc::new(Barrier::new(3)),
+               b2: Arc::new(Barrier::new(3)),
+               sync: Arc::new(AtomicBool::new(false)),
+               stop: Arc::new(AtomicBool::new(false)),
+               population: Arc::new(RwLock::new(population)),
+               rng: Arc::new(RwLock::new(rng))}
+    }
+}
+
+
+// An interpreted test function of the inputs, over the constants 1, 0.25 and 5
+#[cfg(test)]
+fn test_function(tape: &str, inputs: usize) -> FuncObj {
+    use function::CompilePolicy;
+    let consts = vec![GI::new_p(1.0), GI::new_p(0.25), GI::new_p(5.0)];
+    FuncObj::new(&consts, &vec![], &tape.to_string(), inputs, false, "test".to_string(),
+                 CompilePolicy::Interpret).unwrap()
+}
+
+// x*(1 - x) peaks at 0.25 in x = 0.5 and on [-1, 2] ranges over [-2, 0.25],
+// with the minimum at both ends
+#[cfg(test)]
+fn parabola() -> FuncObj {
+    test_function("i0,c0,i0,o-,o*", 1)
+}
+
+// The parabola subject to x - 0.25 <= 0 peaks at 0.1875 in x = 0.25
+#[cfg(test)]
+fn constrained_parabola() -> FuncObj {
+    let mut f = parabola();
+    f.add_constraint(&"i0,c1,o-".to_string(), 1).unwrap();
+    f
+}
+
+#[test]
+fn solve_strategies() {
+    let f = parabola();
+    let domain = vec![GI::new_d(-1.0, 2.0)];
+    for &strategy in &[Strategy::Serial, Strategy::Cooperative, Strategy::Parallel(2)] {
+        let r = Solver::new(f.clone(), domain.clone()).strategy(strategy).solve()
+            .unwrap();
+        let max = r.max.unwrap();
+        assert!(r.min.is_none());
+        assert!(max.lower <= 0.25 && 0.25 <= max.upper && max.upper - max.lower < 0.01);
+        assert!(max.at[0].lower() <= 0.5 + 0.01 && 0.5 - 0.01 <= max.at[0].upper());
+        assert!(r.termination != Termination::Timeout);
+    }
+
+    let r = Solver::new(f.clone(), domain.clone()).strategy(Strategy::Serial)
+        .tolerances(Tolerances{x: 0.0, y: 0.0, y_rel: 0.0}).max_iters(10).solve()
+        .unwrap();
+    assert!(r.termination == Termination::IterationLimit && r.evaluations == 10);
+    assert!(r.max.unwrap().upper >= 0.25);
+}
+
+#[test]
+fn solve_modes() {
+    let f = parabola();
+    let domain = vec![GI::new_d(-1.0, 2.0)];
+    assert!("range".parse::<Mode>() == Ok(Mode::Range));
+    assert!("minimum".parse::<Mode>().is_err());
+
+    let r = Solver::new(f.clone(), domain.clone()).mode(Mode::Min)
+        .strategy(Strategy::Serial).solve().unwrap();
+    let min = r.min.unwrap();
+    assert!(r.max.is_none());
+    assert!(min.lower <= -2.0 && -2.0 <= min.upper && min.upper - min.lower < 0.01);
+
+    let r = Solver::new(f.clone(), domain.clone()).mode(Mode::Range).solve().unwrap();
+    let (min, max) = (r.min.unwrap(), r.max.unwrap());
+    assert!(min.lower <= -2.0 && -2.0 <= min.upper && min.upper - min.lower < 0.01);
+    assert!(max.lower <= 0.25 && 0.25 <= max.upper && max.upper - max.lower < 0.01);
+    assert!(max.at[0].lower() <= 0.5 + 0.01 && 0.5 - 0.01 <= max.at[0].upper());
+    assert!(r.termination != Termination::Timeout);
+
+    // Both extrema are still enclosed when the search is cut short
+    let r = Solver::new(f.clone(), domain.clone()).mode(Mode::Range)
+        .tolerances(Tolerances{x: 0.0, y: 0.0, y_rel: 0.0}).max_iters(10).solve()
+        .unwrap();
+    assert!(r.termination == Termination::IterationLimit && r.evaluations == 10);
+    assert!(r.min.unwrap().lower <= -2.0 && r.max.unwrap().upper >= 0.25);
+}
+
+#[test]
+fn solve_constrained() {
+    let f = constrained_parabola();
+    let domain = vec![GI::new_d(-1.0, 2.0)];
+    for &strategy in &[Strategy::Serial, Strategy::Cooperative, Strategy::Parallel(2)] {
+        let r = Solver::new(f.clone(), domain.clone()).strategy(strategy).solve()
+            .unwrap();
+        let max = r.max.unwrap();
+        assert!(max.lower <= 0.1875 && 0.1875 <= max.upper && max.upper - max.lower < 0.01);
+        assert!(max.at[0].lower() <= 0.25);
+    }
+    let r = Solver::new(f.clone(), domain.clone()).mode(Mode::Range).solve().unwrap();
+    let (min, max) = (r.min.unwrap(), r.max.unwrap());
+    assert!(min.lower <= -2.0 && -2.0 <= min.upper && min.upper - min.lower < 0.01);
+    assert!(max.lower <= 0.1875 && 0.1875 <= max.upper && max.upper - max.lower < 0.01);
+
+    // Nothing in the domain satisfies 5 - x <= 0
+    let mut g = f.clone();
+    g.add_constraint(&"c2,i0,o-".to_string(), 1).unwrap();
+    let r = Solver::new(g, domain.clone()).strategy(Strategy::Serial).solve().unwrap();
+    let max = r.max.unwrap();
+    assert!(max.lower == NINF && max.upper == NINF);
+}
+
+#[test]
+fn solve_equalities() {
+    use function::CompilePolicy;
+    // x + y on the unit circle peaks at sqrt(2) and bottoms out at -sqrt(2)
+    let consts = vec![GI::new_p(1.0)];
+    let mut f = FuncObj::new(&consts, &vec![], &"i0,i1,o+".to_string(), 2, false,
+                             "test".to_string(), CompilePolicy::Interpret).unwrap();
+    f.add_equality(&"i0,i0,o*,i1,i1,o*,o+,c0,o-".to_string(), 2).unwrap();
+    let domain = vec![GI::new_d(-2.0, 2.0), GI::new_d(-2.0, 2.0)];
+    let sqrt2 = 2f64.sqrt();
+    for &strategy in &[Strategy::Serial, Strategy::Cooperative] {
+        let r = Solver::new(f.clone(), domain.clone()).strategy(strategy).solve()
+            .unwrap();
+        let max = r.max.unwrap();
+        // The lower bound comes from a point certified to lie on the circle
+        assert!(max.lower <= sqrt2 && sqrt2 <= max.upper && max.upper - max.lower < 0.01,
+                "{} {}", max.lower, max.upper);
+        assert!(r.feasibility_tolerance == Some(0.0));
+    }
+    let r = Solver::new(f.clone(), domain.clone()).mode(Mode::Range).solve().unwrap();
+    let (min, max) = (r.min.unwrap(), r.max.unwrap());
+    assert!(min.lower <= -sqrt2 && -sqrt2 <= min.upper && min.upper - min.lower < 0.01);
+    assert!(max.lower <= sqrt2 && sqrt2 <= max.upper && max.upper - max.lower < 0.01);
+
+    f.set_equality_tolerance(0.01).unwrap();
+    let r = Solver::new(f, domain).strategy(Strategy::Serial).solve().unwrap();
+    assert!(r.feasibility_tolerance == Some(0.01));
+}
+
+#[test]
+fn solve_discrete() {
+    use function::CompilePolicy;
+    use gr::{InputKind, split_box, width_box};
+    let integer = [InputKind::Integer];
+    let (halves, split) = split_box(&vec![GI::new_d(0.0, 5.0)], &integer);
+    assert!(split && halves[0][0].upper() == 2.0 && halves[1][0].lower() == 3.0);
+    assert!(!width_box(&vec![GI::new_d(3.0, 4.0)], 0.001, &integer));
+    assert!(width_box(&vec![GI::new_d(3.0, 3.0)], 0.001, &integer));
+    assert!(!split_box(&vec![GI::new_d(3.0, 3.0)], &integer).1);
+    let values = [InputKind::values(vec![0.5, 3.0, 4.5]).unwrap()];
+    let (halves, split) = split_box(&vec![GI::new_d(0.5, 4.5)], &values);
+    assert!(split && halves[0][0].upper() == 0.5 && halves[1][0].lower() == 3.0);
+
+    // -(x - 2.4)^2 peaks at the integer 2 and at 3 among the values
+    let consts = vec![GI::new_p(2.4)];
+    let mut f = FuncObj::new(&consts, &vec![], &"i0,c0,o-,i0,c0,o-,o*,fneg".to_string(), 1,
+                             false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    let domain = vec![GI::new_d(-0.5, 5.0)];
+    f.set_input_kinds(integer.to_vec());
+    for &strategy in &[Strategy::Serial, Strategy::Cooperative, Strategy::Parallel(2)] {
+        let r = Solver::new(f.clone(), domain.clone()).strategy(strategy).solve()
+            .unwrap();
+        let max = r.max.unwrap();
+        assert!((max.lower + 0.16).abs() < 1e-9 && max.upper - max.lower < 1e-9,
+                "{} {}", max.lower, max.upper);
+        assert!(max.at[0].lower() <= 2.0 && 2.0 <= max.at[0].upper());
+    }
+    let r = Solver::new(f.clone(), domain.clone()).mode(Mode::Range).solve().unwrap();
+    let min = r.min.unwrap();
+    assert!((min.lower + 6.76).abs() < 1e-9 && min.upper - min.lower < 1e-9);
+
+    f.set_input_kinds(values.to_vec());
+    let r = Solver::new(f.clone(), domain.clone()).solve().unwrap();
+    let max = r.max.unwrap();
+    assert!((max.lower + 0.36).abs() < 1e-9 && max.upper - max.lower < 1e-9);
+
+    // No integer lies in the domain
+    f.set_input_kinds(integer.to_vec());
+    assert!(Solver::new(f, vec![GI::new_d(0.25, 0.75)]).solve().is_err());
+}
+
+#[test]
+fn solve_candidates() {
+    use function::CompilePolicy;
+    // -(x^2 - 1)^2 peaks at -1 and 1 and bottoms out at -2 and 2
+    let consts = vec![GI::new_p(1.0)];
+    let f = FuncObj::new(&consts, &vec![], &"i0,i0,o*,c0,o-,i0,i0,o*,c0,o-,o*,fneg".to_string(),
+                         1, false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    let domain = vec![GI::new_d(-2.0, 2.0)];
+    let covers = |cs: &[Candidate], x: f64| cs.iter()
+        .any(|c| c.at[0].lower() <= x && x <= c.at[0].upper());
+    let r = Solver::new(f.clone(), domain.clone()).strategy(Strategy::Serial).solve().unwrap();
+    assert!(r.max.unwrap().candidates.is_empty());
+
+    for &strategy in &[Strategy::Serial, Strategy::Cooperative, Strategy::Parallel(2)] {
+        let r = Solver::new(f.clone(), domain.clone()).strategy(strategy).candidates(true)
+            .solve().unwrap();
+        let max = r.max.unwrap();
+        assert!(covers(&max.candidates, -1.0) && covers(&max.candidates, 1.0));
+        assert!(max.candidates.iter().all(|c| c.value.upper() >= max.lower));
+        let clusters = candidates::cluster(max.candidates.clone(), &[]);
+        assert!(clusters.len() <= max.candidates.len() && clusters.len() >= 1);
+        assert!(clusters.iter().map(|c| c.boxes).sum::<usize>() == max.candidates.len());
+    }
+
+    let r = Solver::new(f.clone(), domain.clone()).mode(Mode::Min).candidates(true)
+        .solve().unwrap();
+    let min = r.min.unwrap();
+    assert!(covers(&min.candidates, -2.0) && covers(&min.candidates, 2.0));
+    assert!(min.candidates.iter().all(|c| c.value.lower() <= min.upper));
+    let r = Solver::new(f, domain).mode(Mode::Range).candidates(true).solve().unwrap();
+    let (min, max) = (r.min.unwrap(), r.max.unwrap());
+    assert!(covers(&min.candidates, -2.0) && covers(&min.candidates, 2.0));
+    assert!(covers(&max.candidates, -1.0) && covers(&max.candidates, 1.0));
+}
+
+#[test]
+fn solve_certificate() {
+    use function::{CompilePolicy, FuncDef};
+    use certificate::{Certificate, Limits, Node, check, prove};
+    let f = constrained_parabola();
+    let domain = vec![GI::new_d(-1.0, 2.0)];
+    let r = Solver::new(f.clone(), domain.clone()).strategy(Strategy::Serial).solve().unwrap();
+    assert!(r.max.unwrap().proof.is_none());
+
+    for &strategy in &[Strategy::Serial, Strategy::Cooperative, Strategy::Parallel(2)] {
+        let r = Solver::new(f.clone(), domain.clone()).strategy(strategy).certificate(true)
+            .solve().unwrap();
+        let max = r.max.unwrap();
+        let proof = max.proof.unwrap().unwrap();
+        assert!(proof.bound == max.upper && check(&f, &domain, &proof).unwrap() > 1);
+        assert!(proof.nodes.iter().any(|n| match *n { Node::Infeasible(..) => true,
+                                                      _ => false }));
+    }
+    let r = Solver::new(f.clone(), domain.clone()).mode(Mode::Range).certificate(true)
+        .solve().unwrap();
+    let (min, max) = (r.min.unwrap().proof.unwrap().unwrap(),
+                      r.max.unwrap().proof.unwrap().unwrap());
+    assert!(min.mode == Mode::Min && check(&f, &domain, &min).is_ok());
+    assert!(max.mode == Mode::Max && check(&f, &domain, &max).is_ok());
+
+    // A search stopped by the limit on evaluations is proved from the boxes
+    // it evaluated
+    let r = Solver::new(f.clone(), domain.clone()).strategy(Strategy::Serial).max_iters(20)
+        .certificate(true).solve().unwrap();
+    assert!(r.termination == Termination::IterationLimit);
+    assert!(check(&f, &domain, &r.max.unwrap().proof.unwrap().unwrap()).is_ok());
+
+    // A bound below the maximum is not proved, whatever the limits
+    let mut limits = Limits{deadline: None, evaluations: Some(5)};
+    assert!(prove(&f, &domain, Mode::Max, 0.18, 0.001, None, &mut limits).is_err());
+    assert!(limits.evaluations == Some(0));
+    let mut limits = Limits{deadline: None, evaluations: None};
+    assert!(prove(&f, &domain, Mode::Max, 0.18, 0.001, None, &mut limits).is_err());
+
+    // Proofs survive a round trip through a certificate
+    let def = FuncDef{instructions: f.tape(), constants: f.constants().clone(),
+                      names: vec!["x".to_string()],
+                      domain: domain.clone(), kinds: vec![], param_names: vec![],
+                      params: vec![], constraints: f.constraint_tapes(), equalities: vec![],
+                      metadata: Default::default()};
+    let cert = Certificate{function: def, eq_tolerance: 0.0, proofs: vec![min, max]};
+    let cert = Certificate::from_json(&cert.to_json()).unwrap();
+    let g = cert.function.build(false, "test".to_string(), CompilePolicy::Interpret).unwrap();
+    for p in &cert.proofs {
+        assert!(check(&g, &cert.function.domain, p).is_ok());
+    }
+
+    // A tighter bound, a box left out and a wider domain are all caught
+    let mut proof = cert.proofs[1].clone();
+    proof.bound = 0.18;
+    assert!(check(&f, &domain, &proof).is_err());
+    let mut proof = cert.proofs[1].clone();
+    if let Node::Split(_, ref mut children) = proof.nodes[0] {
+        children.pop();
+    }
+    assert!(check(&f, &domain, &proof).is_err());
+    assert!(check(&f, &vec![GI::new_d(-1.0, 3.0)], &cert.proofs[1]).is_err());
+}
+
+#[test]
+fn solve_checkpoint() {
+    use function::CompilePolicy;
+    use gr::InputKind;
+    use std::env::temp_dir;
+    use std::fs::remove_file;
+    let f = parabola();
+    let domain = vec![GI::new_d(-1.0, 2.0)];
+    let tol = Tolerances{x: 1e-6, y: 1e-6, y_rel: 0.0};
+    let path = temp_dir().join(format!("gelpia-checkpoint-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let solver = Solver::new(f.clone(), domain.clone()).tolerances(tol)
+        .strategy(Strategy::Serial);
+
+    // Resuming the serial strategy retraces the uninterrupted search
+    let whole = solver.solve().unwrap();
+    let part = solver.clone().max_iters(20).checkpoint(path, 3600).solve().unwrap();
+    assert!(part.termination == Termination::IterationLimit);
+    let saved = Checkpoint::load(path).unwrap();
+    assert!(saved.progress.evaluations == 20 && !saved.queue.is_empty());
+    let copy = Checkpoint::from_binary(&saved.to_binary()).unwrap();
+    assert!(copy.to_binary() == saved.to_binary());
+    let resumed = solver.clone().resume(saved.clone()).solve().unwrap();
+    let (a, b) = (whole.max.unwrap(), resumed.max.unwrap());
+    assert!(a.lower.to_bits() == b.lower.to_bits() && a.upper.to_bits() == b.upper.to_bits());
+    assert!(a.at[0].lower() == b.at[0].lower() && a.at[0].upper() == b.at[0].upper());
+    assert!(whole.evaluations == resumed.evaluations &&
+            whole.iterations == resumed.iterations);
+
+    // Other strategies resume from the same state
+    for &strategy in &[Strategy::Cooperative, Strategy::Parallel(2)] {
+        let r = solver.clone().strategy(strategy).resume(saved.clone()).solve().unwrap();
+        let max = r.max.unwrap();
+        assert!(max.lower <= 0.25 && 0.25 <= max.upper && max.upper - max.lower < 1e-4);
+    }
+
+    // A checkpoint only resumes the search it was made for
+    assert!(solver.clone().mode(Mode::Min).resume(saved.clone()).solve().is_err());
+    assert!(Solver::new(f.clone(), vec![GI::new_d(-1.0, 3.0)]).resume(saved.clone())
+            .solve().is_err());
+    // x*(2 - x) has the same tapes with a different constant
+    let consts = vec![GI::new_p(2.0), GI::new_p(0.25), GI::new_p(5.0)];
+    let g = FuncObj::new(&consts, &vec![], &f.tape(), 1, false, "test".to_string(),
+                         CompilePolicy::Interpret).unwrap();
+    assert!(g.tape() == f.tape());
+    assert!(Solver::new(g, domain.clone()).resume(saved.clone()).solve().is_err());
+    let mut g = f.clone();
+    g.set_input_kinds(vec![InputKind::Integer]);
+    assert!(Solver::new(g, domain.clone()).resume(saved.clone()).solve().is_err());
+    assert!(solver.clone().mode(Mode::Range).checkpoint(path, 60).solve().is_err());
+    let _ = remove_file(path);
+}
+
+#[test]
+fn solve_events() {
+    use std::env::temp_dir;
+    use std::fs::{File, remove_file};
+    use std::io::Read;
+    use serde_json::Value;
+    let f = parabola();
+    let domain = vec![GI::new_d(-1.0, 2.0)];
+    let path = temp_dir().join(format!("gelpia-events-{}.json", std::process::id()));
+    let path = path.to_str().unwrap();
+    let fields = ["ea_best", "elapsed", "evaluations", "event", "extremum", "lower",
+                  "possible", "queue", "termination", "upper"];
+    for &(strategy, mode) in &[(Strategy::Serial, Mode::Max), (Strategy::Cooperative, Mode::Min),
+                               (Strategy::Parallel(2), Mode::Max),
+                               (Strategy::Serial, Mode::Range)] {
+        let log = EventLog::file(path, 0).unwrap();
+        let r = Solver::new(f.clone(), domain.clone()).strategy(strategy).mode(mode)
+            .log(log).solve().unwrap();
+        let mut text = String::new();
+        File::open(path).unwrap().read_to_string(&mut text).unwrap();
+        let events: Vec<Value> = text.lines().map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        for e in &events {
+            let keys: Vec<&String> = e.as_object().unwrap().keys().collect();
+            assert!(keys == fields.iter().collect::<Vec<_>>());
+        }
+        // Each extremum ends with the bounds of the result
+        for (name, x) in vec![("max", r.max), ("min", r.min)] {
+            let end = events.iter().find(|e| e["event"] == "end" && e["extremum"] == name);
+            match x {
+                Some(x) => {
+                    let end = end.unwrap();
+                    assert!(end["lower"] == json!(x.lower) && end["upper"] == json!(x.upper));
+                    assert!(end["termination"] == json!(r.termination.to_string()));
+                }
+                None => assert!(end.is_none()),
+            }
+        }
+    }
+    let _ = remove_file(path);
+}
+
+#[test]
+fn solve_deterministic() {
+    // x*y*(1 - x) peaks at 0.25 in x = 0.5, y = 1
+    let f = test_function("i0,c0,i0,o-,o*,i1,o*", 2);
+    let domain = vec![GI::new_d(-1.0, 2.0), GI::new_d(0.5, 1.0)];
+    let solver = Solver::new(f.clone(), domain.clone()).deterministic(true).candidates(true)
+        .tolerances(Tolerances{x: 1e-3, y: 1e-5, y_rel: 0.0});
+    let bits = |r: SolveResult| {
+        let max = r.max.unwrap();
+        let mut bits = vec![max.lower.to_bits(), max.upper.to_bits(),
+                            r.evaluations as u64, r.iterations as u64];
+        for x in max.at.iter().chain(max.candidates.iter().flat_map(|c| c.at.iter())) {
+            bits.push(x.lower().to_bits());
+            bits.push(x.upper().to_bits());
+        }
+        bits
+    };
+    // The same for any number of workers
+    let one = bits(solver.clone().strategy(Strategy::Parallel(1)).solve().unwrap());
+    for &n in &[2, 3, 4] {
+        assert!(bits(solver.clone().strategy(Strategy::Parallel(n)).solve().unwrap()) == one);
+    }
+    let cooperative = solver.clone().strategy(Strategy::Cooperative).update_interval(1);
+    assert!(bits(cooperative.solve().unwrap()) == bits(cooperative.solve().unwrap()));
+    assert!(solver.seed(1).solve().is_err());
+}
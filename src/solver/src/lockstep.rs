@@ -0,0 +1,77 @@
+// The EA run in lockstep with the branch and bound, for deterministic
+// searches.
+//
+// Running on a thread of its own, the EA shares its best value with the
+// branch and bound and samples around the branch and bound's best box
+// whenever the threads happen to get to it, so the result depends on their
+// timing. In lockstep the branch and bound's thread runs one epoch of the EA
+// at fixed points instead: when it starts and then once EPOCH_STEPS more boxes
+// were taken from the queue, checked before every iteration of the single
+// threaded branch and bound and every round of the parallel one. An epoch
+// costs about as much as evaluating that many boxes, so the EA keeps the
+// share of the work it has on a thread of its own. The cooperative update
+// then runs after a fixed number of epochs rather than seconds.
+
+use std::collections::BinaryHeap;
+use std::sync::{RwLock, Arc};
+
+use gelpia_utils::{Quple, Flt, Parameters};
+
+use gr::GI;
+
+use ga::{Individual, ea_epoch};
+
+use function::FuncObj;
+
+use update::prune;
+
+// Boxes taken from the queue between two epochs.
+pub const EPOCH_STEPS: u32 = 131072;
+
+#[derive(Clone)]
+pub struct Lockstep {
+    x_e: Vec<GI>,
+    param: Parameters,
+    f: FuncObj,
+    population: Arc<RwLock<Vec<Individual>>>,
+    rng: Arc<RwLock<Option<[u32; 4]>>>,
+    f_bestag: Arc<RwLock<Flt>>,
+    x_bestbb: Arc<RwLock<Vec<GI>>>,
+    // Epochs between two updates, 0 for none.
+    update_epochs: u32,
+    epochs: u32,
+    // The number of steps at which the next epoch is due.
+    next: u32,
+}
+
+impl Lockstep {
+    pub fn new(x_e: Vec<GI>, param: Parameters, f: FuncObj,
+               population: Arc<RwLock<Vec<Individual>>>,
+               rng: Arc<RwLock<Option<[u32; 4]>>>,
+               f_bestag: Arc<RwLock<Flt>>, x_bestbb: Arc<RwLock<Vec<GI>>>,
+               update_epochs: u32) -> Lockstep {
+        Lockstep{x_e: x_e, param: param, f: f, population: population, rng: rng,
+                 f_bestag: f_bestag, x_bestbb: x_bestbb,
+                 update_epochs: update_epochs, epochs: 0, next: 0}
+    }
+
+    // Runs one epoch of the EA if it is due after the given number of steps,
+    // then the cooperative update of q if it is given and due, pruning against
+    // the lower bound f_best_low.
+    pub fn epoch(&mut self, steps: u32, q: Option<&mut BinaryHeap<Quple>>, f_best_low: Flt) {
+        if steps < self.next {
+            return;
+        }
+        self.next = steps.saturating_add(EPOCH_STEPS);
+        let mut population = self.population.write().unwrap();
+        ea_epoch(&self.x_e, &self.param, &mut population, &mut self.rng.write().unwrap(),
+                 &self.f_bestag, &self.x_bestbb, &self.f);
+        self.epochs += 1;
+        if let Some(q) = q {
+            if self.update_epochs != 0 && self.epochs % self.update_epochs == 0 &&
+                !q.is_empty() {
+                prune(q, &mut population, f_best_low, &self.f);
+            }
+        }
+    }
+}
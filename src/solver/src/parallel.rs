@@ -0,0 +1,275 @@
+// Interval branch and bound that refines the whole queue each round, spread
+// over a pool of worker threads.
+
+use std::io::Write;
+use std::sync::{Barrier, RwLock, Arc};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+
+use threadpool::ThreadPool;
+
+use gelpia_utils::{Quple, Flt, NINF, eps_tol, check_diff};
+
+use gr::{GI, width_box, split_box};
+
+use function::{FuncObj, Feasibility};
+
+use bounds::{est_func, get_upper_bound};
+use certificate::Enclosures;
+use checkpoint::{Progress, Writer};
+use events::{EventLog, Status};
+use lockstep::Lockstep;
+use {Tolerances, Termination};
+
+// What a worker made of one queued box.
+struct Refined {
+    // The best estimate among the children of the box and the child it came
+    // from.
+    low: Flt,
+    low_x: Vec<GI>,
+    // The children that may still hold the maximum.
+    children: Vec<Quple>,
+    // The box itself, if it was refined enough.
+    done: Option<Quple>,
+}
+
+// Returns the lower bound, the upper bound, the domain where this bound
+// occurs, why the search ended, the number of boxes evaluated and the number
+// of boxes taken from the queue. Boxes that are still queued when the search
+// ends are left in q and refined boxes are added to done, as in ibba.
+// Enclosures are recorded in known, infeasible boxes are dropped, searches
+// are resumed, checkpoints saved and events logged as in ibba. With lockstep
+// the EA runs on this thread between rounds and the workers only prune
+// against the bounds from the start of the round, which makes the search
+// independent of the number of workers.
+pub fn ibba(x_0: Vec<GI>, tol: Tolerances,
+            f_bestag: Arc<RwLock<Flt>>,
+            f_best_shared: Arc<RwLock<Flt>>,
+            x_bestbb: Arc<RwLock<Vec<GI>>>,
+            b1: Arc<Barrier>, b2: Arc<Barrier>,
+            q: Arc<RwLock<Vec<Quple>>>,
+            done: Option<Arc<RwLock<Vec<Quple>>>>,
+            known: Option<Arc<RwLock<Enclosures>>>,
+            sync: Arc<AtomicBool>, stop: Arc<AtomicBool>,
+            f: FuncObj,
+            mut log: Option<EventLog>, max_iters: u32, n_workers: usize,
+            start: Option<Progress>, mut checkpoint: Option<Writer>,
+            mut lockstep: Option<Lockstep>)
+            -> (Flt, Flt, Vec<GI>, Termination, u32, u32) {
+    let (mut f_best_low, mut f_best_high, mut best_x, iters, mut steps) = match start {
+        Some(p) => (p.f_best_low, p.f_best_high, p.best_x, p.evaluations, p.iterations),
+        None => {
+            let (est_max, first_val, _) = est_func(&f, &x_0);
+            if let Some(ref known) = known {
+                known.write().unwrap().record(&x_0, first_val);
+            }
+            if f.feasibility(&x_0) != Feasibility::Infeasible {
+                q.write().unwrap().push(Quple{p: est_max, pf: 0, data: x_0.clone(),
+                                              fdata: first_val, dfdata: None});
+            }
+            (est_max, est_max, x_0.clone(), 0, 0)
+        }
+    };
+    let iters = Arc::new(AtomicUsize::new(iters as usize));
+    let mut termination = Termination::Exhausted;
+    let (mut logged_low, mut logged_high) = (f_best_low, f_best_high);
+
+    let pool = ThreadPool::new(n_workers);
+
+    while q.read().unwrap().len() != 0 {
+        if stop.load(Ordering::Acquire) {
+            termination = Termination::Timeout;
+            break;
+        }
+        if max_iters != 0 && iters.load(Ordering::Acquire) as u32 >= max_iters {
+            termination = Termination::IterationLimit;
+            break;
+        }
+        if let Some(ref mut w) = checkpoint {
+            if w.due() {
+                let progress = Progress{f_best_low: f_best_low, f_best_high: f_best_high,
+                                        best_x: best_x.clone(),
+                                        evaluations: iters.load(Ordering::Acquire) as u32,
+                                        iterations: steps};
+                if let Err(msg) = w.save(progress, q.read().unwrap().iter(), &done) {
+                    let _ = writeln!(&mut ::std::io::stderr(), "{}", msg);
+                }
+            }
+        }
+        if let Some(ref mut l) = log {
+            if l.due() {
+                l.stats(&Status::new(q.read().unwrap().iter(), f_best_low, f_best_high,
+                                     iters.load(Ordering::Acquire) as u32,
+                                     *f_bestag.read().unwrap()));
+            }
+        }
+        if sync.load(Ordering::Acquire) {
+            // Ugly: Update the update thread's view of the best branch bound.
+            *f_best_shared.write().unwrap() = f_best_low;
+            b1.wait();
+            b2.wait();
+        }
+        {
+            // Take q as writable during an iteration
+            let q = q.write().unwrap();
+
+            if let Some(ref mut l) = lockstep {
+                l.epoch(steps, None, f_best_low);
+            }
+            f_best_low = max!(f_best_low, *f_bestag.read().unwrap());
+
+            if iters.load(Ordering::Acquire) % 2048 == 0 {
+                let guaranteed_bound =
+                    get_upper_bound(q.iter(), f_best_high);
+                if (guaranteed_bound - f_best_high).abs() < tol.y {
+                    f_best_high = guaranteed_bound;
+                    termination = Termination::Converged;
+                    break;
+                }
+            }
+
+            // Both bounds improve on the results of the last round and the
+            // lower one on values from the EA
+            if let Some(ref l) = log {
+                if logged_low != f_best_low || logged_high != f_best_high {
+                    logged_low = f_best_low;
+                    logged_high = f_best_high;
+                    l.bound(&Status::new(q.iter(), f_best_low, f_best_high,
+                                         iters.load(Ordering::Acquire) as u32,
+                                         *f_bestag.read().unwrap()));
+                }
+            }
+        }
+
+        let p_q_len = {
+            let mut q = q.write().unwrap();
+            q.sort();
+            // Every queued box is taken in this round
+            steps += q.len() as u32;
+            q.len()/n_workers + 1
+        };
+
+        let outer_barr = Arc::new(Barrier::new(n_workers + 1));
+
+        let (rtx, rrx) = channel();
+
+        for i in 0..n_workers {
+            let inner_barr = outer_barr.clone();
+            let _f = f.clone();
+            let rtx = rtx.clone();
+            let f_bestag = f_bestag.clone();
+            let iters = iters.clone();
+            let lqi = q.clone();
+            let x_0 = x_0.clone();
+            let known = known.clone();
+            let in_lockstep = lockstep.is_some();
+            pool.execute(move || {
+                // Outside of lockstep, boxes are also pruned against the best
+                // estimate this worker found so far
+                let mut l_f_best_low = f_best_low;
+                let mut refined = vec![];
+                let lqi = lqi.read().unwrap();
+
+                for j in 0..p_q_len {
+                    let idx = i + j*n_workers;
+                    if idx >= lqi.len() { break };
+                    let ref elem = lqi[idx];
+                    let ref x = elem.data;
+                    let ref iter_est = elem.p;
+                    let ref fx = elem.fdata;
+                    let ref gen = elem.pf;
+                    let ref dfx = elem.dfdata;
+
+                    if !_f.has_discrete_inputs() && check_diff(dfx.clone(), x, &x_0) &&
+                        _f.feasibility(x) == Feasibility::Feasible {
+                        continue;
+                    }
+
+                    let mut r = Refined{low: NINF, low_x: vec![], children: vec![],
+                                        done: None};
+                    if fx.upper() < l_f_best_low ||
+                        width_box(&x, tol.x, _f.input_kinds()) ||
+                        eps_tol(*fx, *iter_est, tol.y, tol.y_rel) {
+                            r.done = Some(elem.clone());
+                        }
+                    else {
+                        let (x_s, is_split) = split_box(&x, _f.input_kinds());
+                        for sx in x_s {
+                            let (est_max, fsx, dfsx) = est_func(&_f, &sx);
+                            if let Some(ref known) = known {
+                                known.write().unwrap().record(&sx, fsx);
+                            }
+                            if r.low < est_max {
+                                r.low = est_max;
+                                r.low_x = sx.clone();
+                            }
+                            if !in_lockstep {
+                                l_f_best_low = max!(l_f_best_low, est_max);
+                            }
+                            iters.fetch_add(1, Ordering::Release);
+                            // Boxes whose upper bound equals the lower bound
+                            // may still hold the maximum
+                            if is_split && fsx.upper() >= f_best_low &&
+                                fsx.upper() >= *f_bestag.read().unwrap() &&
+                                _f.feasibility(&sx) != Feasibility::Infeasible {
+                                    r.children.push(Quple{p: est_max,
+                                                          pf: gen+1,
+                                                          data: sx,
+                                                          fdata: fsx,
+                                                          dfdata: dfsx});
+                                }
+                        }
+                    }
+                    refined.push((idx, r));
+                }
+                rtx.send(refined).unwrap();
+                inner_barr.wait();
+            });
+        }
+        outer_barr.wait();
+        drop(rtx);
+
+        // Merge in the order of the queue rather than the order the workers
+        // finish in, so ties are broken the same way every time
+        let mut refined: Vec<(usize, Refined)> = rrx.iter().flat_map(|r| r.into_iter())
+            .collect();
+        refined.sort_by_key(|&(idx, _)| idx);
+
+        for &(_, ref r) in &refined {
+            if f_best_low < r.low {
+                f_best_low = r.low;
+                *x_bestbb.write().unwrap() = r.low_x.clone();
+            }
+        }
+        {
+            let mut lq = q.write().unwrap();
+            *lq = vec![];
+            for (_, r) in refined {
+                if let Some(elem) = r.done {
+                    if f_best_high < elem.fdata.upper() {
+                        f_best_high = elem.fdata.upper();
+                        best_x = elem.data.clone();
+                    }
+                    if let Some(ref done) = done {
+                        if elem.fdata.upper() >= f_best_low {
+                            done.write().unwrap().push(elem);
+                        }
+                    }
+                }
+                for qi in r.children {
+                    if qi.fdata.upper() >= f_best_low {
+                        lq.push(qi);
+                    }
+                }
+            }
+        }
+    }
+    stop.store(true, Ordering::Release);
+    if let Some(ref l) = log {
+        l.end(&Status::new(q.read().unwrap().iter(), f_best_low, f_best_high,
+                           iters.load(Ordering::Acquire) as u32,
+                           *f_bestag.read().unwrap()), termination);
+    }
+    (f_best_low, f_best_high, best_x, termination,
+     iters.load(Ordering::Acquire) as u32, steps)
+}
@@ -0,0 +1,395 @@
+// Interval branch and bound for the minimum and the maximum at once, used by
+// the range mode.
+//
+// Every box is evaluated once and queued for each extremum it may still hold,
+// so the two searches share their function evaluations. The minimum is
+// searched as the maximum of -f: side 1 sees the enclosures and estimates of
+// the boxes negated. Splitting a box for one side replaces it with its
+// children on the other side too.
+
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::BinaryHeap;
+use std::sync::{RwLock, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use gelpia_utils::{Quple, Flt, NINF, eps_tol, check_diff};
+
+use gr::{GI, width_box, split_box};
+
+use function::{FuncObj, Feasibility};
+
+use bounds::est_range;
+use candidates::{Candidate, collect};
+use certificate::Enclosures;
+use events::{EventLog, Status};
+use {Tolerances, Termination, Mode};
+
+const MAX: usize = 0;
+const MIN: usize = 1;
+
+// A box evaluated once for both sides.
+struct Node {
+    data: Vec<GI>,
+    fx: GI,
+    dfx: Option<Vec<GI>>,
+    est_max: Flt,
+    est_min: Flt,
+    gen: u32,
+    // Unique among all boxes, slots of freed boxes are reused
+    id: u64,
+    // Whether the box is still queued for the maximum and the minimum.
+    live: [bool; 2],
+}
+
+impl Node {
+    // The enclosure and the estimate as seen by a side.
+    fn value(&self, side: usize) -> (GI, Flt) {
+        if side == MAX { (self.fx, self.est_max) } else { (-self.fx, -self.est_min) }
+    }
+}
+
+// A queued box, ordered like a Quple: earlier generations first, then the
+// higher estimate.
+struct Entry {
+    p: Flt,
+    gen: u32,
+    idx: usize,
+    id: u64,
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Entry) -> bool {
+        self.cmp(other) == CmpOrdering::Equal
+    }
+}
+
+impl Eq for Entry { }
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Entry) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Entry) -> CmpOrdering {
+        other.gen.cmp(&self.gen)
+            .then(self.p.partial_cmp(&other.p).unwrap_or(CmpOrdering::Equal))
+            .then(other.id.cmp(&self.id))
+    }
+}
+
+// The search for one extremum, as a maximization.
+struct Side {
+    q: BinaryHeap<Entry>,
+    best_low: Flt,
+    best_high: Flt,
+    best_x: Vec<GI>,
+    pops: u32,
+    converged: bool,
+    // Boxes refined enough that may hold the extremum, as seen by the side.
+    done: Vec<Quple>,
+}
+
+impl Side {
+    fn push(&mut self, nodes: &[Option<Node>], idx: usize, side: usize) {
+        let node = nodes[idx].as_ref().unwrap();
+        self.q.push(Entry{p: node.value(side).1, gen: node.gen, idx: idx, id: node.id});
+    }
+
+    // Sets the box aside as a candidate, if it may hold the extremum.
+    fn finish(&mut self, node: &Node, side: usize) {
+        let (fx, est) = node.value(side);
+        if fx.upper() >= self.best_low {
+            self.done.push(Quple{p: est, pf: node.gen, data: node.data.clone(), fdata: fx,
+                                 dfdata: None});
+        }
+    }
+
+    // The candidates for the extremum once the search ended, in the side's
+    // orientation.
+    fn candidates(&self, nodes: &[Option<Node>], side: usize) -> Vec<Candidate> {
+        let queued = self.q.iter().filter_map(|e| queued(nodes, e, side))
+            .map(|node| {
+                let (fx, est) = node.value(side);
+                Quple{p: est, pf: node.gen, data: node.data.clone(), fdata: fx,
+                      dfdata: None}
+            })
+            .collect::<Vec<_>>();
+        collect(self.done.iter().chain(queued.iter()), self.best_low)
+    }
+
+    // The largest upper bound of the boxes still queued for this side.
+    fn best_box(&self, nodes: &[Option<Node>], side: usize) -> (Flt, Vec<GI>) {
+        let (mut max, mut x) = (self.best_high, self.best_x.clone());
+        for e in self.q.iter() {
+            if let Some(node) = queued(nodes, e, side) {
+                let fx = node.value(side).0;
+                if fx.upper() > max {
+                    max = fx.upper();
+                    x = node.data.clone();
+                }
+            }
+        }
+        (max, x)
+    }
+
+    // Where the search for the extremum stands, in the side's orientation.
+    fn status(&self, nodes: &[Option<Node>], side: usize, iters: u32) -> Status {
+        let queue = self.q.iter().filter(|e| queued(nodes, e, side).is_some()).count();
+        Status{f_best_low: self.best_low, f_best_high: self.best_high,
+               guaranteed: self.best_box(nodes, side).0, evaluations: iters,
+               queue: queue, ea_best: NINF}
+    }
+}
+
+// The box of an entry, unless it was split or retired for the side since.
+fn queued<'a>(nodes: &'a [Option<Node>], e: &Entry, side: usize) -> Option<&'a Node> {
+    match nodes[e.idx] {
+        Some(ref node) if node.id == e.id && node.live[side] => Some(node),
+        _ => None,
+    }
+}
+
+// Removes side from the sides the box is queued for, freeing its slot once no
+// side needs it.
+fn retire(nodes: &mut [Option<Node>], free: &mut Vec<usize>, idx: usize, side: usize) {
+    let unused = match nodes[idx] {
+        Some(ref mut node) => {
+            node.live[side] = false;
+            !node.live[MAX] && !node.live[MIN]
+        }
+        None => false,
+    };
+    if unused {
+        nodes[idx] = None;
+        free.push(idx);
+    }
+}
+
+// Returns the lower bound, the upper bound, the box where the upper bound
+// occurs and, if candidates is set, the boxes that may hold the maximum, the
+// same for the minimum, why the search ended, the number of boxes evaluated
+// and the number of boxes taken from either queue. Infeasible boxes are
+// dropped and enclosures recorded in known as in ibba. Progress events for
+// both extrema go to log if it is given.
+pub fn range(x_0: Vec<GI>, tol: Tolerances, stop: Arc<AtomicBool>,
+             f: FuncObj, log: Option<EventLog>, max_iters: u32, candidates: bool,
+             known: Option<Arc<RwLock<Enclosures>>>)
+             -> ((Flt, Flt, Vec<GI>, Vec<Candidate>), (Flt, Flt, Vec<GI>, Vec<Candidate>),
+                 Termination, u32, u32) {
+    let (est_max, est_min, first_val, first_dfx) = est_range(&f, &x_0);
+    if let Some(ref known) = known {
+        known.write().unwrap().record(&x_0, first_val);
+    }
+    let mut nodes = vec![Some(Node{data: x_0.clone(), fx: first_val, dfx: first_dfx,
+                                   est_max: est_max, est_min: est_min, gen: 0,
+                                   id: 0, live: [true, true]})];
+    let mut next_id: u64 = 1;
+    let mut free: Vec<usize> = vec![];
+    let mut sides = [MAX, MIN].iter().map(|&s| {
+        let est = nodes[0].as_ref().unwrap().value(s).1;
+        Side{q: BinaryHeap::new(), best_low: est, best_high: est,
+             best_x: x_0.clone(), pops: 0, converged: false, done: vec![]}
+    }).collect::<Vec<_>>();
+    if f.feasibility(&x_0) != Feasibility::Infeasible {
+        sides[MAX].push(&nodes, 0, MAX);
+        sides[MIN].push(&nodes, 0, MIN);
+    }
+
+    let mut logs = log.map(|l| vec![l.start(Mode::Max), l.start(Mode::Min)]);
+    let mut iters: u32 = 0;
+    let mut steps: u32 = 0;
+    let mut turn = MAX;
+    let mut termination = None;
+
+    while !sides[MAX].q.is_empty() || !sides[MIN].q.is_empty() {
+        if stop.load(Ordering::Acquire) {
+            termination = Some(Termination::Timeout);
+            break;
+        }
+        if max_iters != 0 && iters >= max_iters {
+            termination = Some(Termination::IterationLimit);
+            break;
+        }
+        if let Some(ref mut logs) = logs {
+            if logs[MAX].due() {
+                for s in 0..2 {
+                    logs[s].stats(&sides[s].status(&nodes, s, iters));
+                }
+            }
+        }
+        // The sides take turns, one side goes on alone once the other is done
+        let k = if sides[turn].q.is_empty() { 1 - turn } else { turn };
+        let o = 1 - k;
+        turn = o;
+
+        if sides[k].pops % 2048 == 0 {
+            let (guaranteed_bound, x) = sides[k].best_box(&nodes, k);
+            if (guaranteed_bound - sides[k].best_high).abs() < tol.y {
+                sides[k].best_high = guaranteed_bound;
+                sides[k].best_x = x;
+                sides[k].converged = true;
+                let entries = sides[k].q.drain().collect::<Vec<_>>();
+                for e in entries {
+                    let live = match queued(&nodes, &e, k) {
+                        Some(node) => {
+                            // The queued boxes remain candidates
+                            if candidates {
+                                sides[k].finish(node, k);
+                            }
+                            true
+                        }
+                        None => false,
+                    };
+                    if live {
+                        retire(&mut nodes, &mut free, e.idx, k);
+                    }
+                }
+                continue;
+            }
+        }
+        sides[k].pops += 1;
+
+        // Skip entries of boxes that were split or retired for this side
+        let idx = match sides[k].q.pop() {
+            Some(e) => {
+                if queued(&nodes, &e, k).is_none() {
+                    continue;
+                }
+                steps += 1;
+                e.idx
+            }
+            None => continue,
+        };
+
+        let (split, parent_live) = {
+            let node = nodes[idx].as_ref().unwrap();
+            let (fx, est) = node.value(k);
+            // A feasible box in the interior on which the function is monotonic
+            // holds neither extremum, unless an input is discrete
+            if !f.has_discrete_inputs() && check_diff(node.dfx.clone(), &node.data, &x_0) &&
+                f.feasibility(&node.data) == Feasibility::Feasible {
+                (None, [false, false])
+            } else if fx.upper() < sides[k].best_low ||
+                width_box(&node.data, tol.x, f.input_kinds()) ||
+                eps_tol(fx, est, tol.y, tol.y_rel) {
+                    if sides[k].best_high < fx.upper() {
+                        sides[k].best_high = fx.upper();
+                        sides[k].best_x = node.data.clone();
+                        if let Some(ref logs) = logs {
+                            logs[k].bound(&sides[k].status(&nodes, k, iters));
+                        }
+                    }
+                    if candidates {
+                        sides[k].finish(node, k);
+                    }
+                    (None, [k != MAX && node.live[MAX], k != MIN && node.live[MIN]])
+                }
+            else {
+                let (x_s, is_split) = split_box(&node.data, f.input_kinds());
+                if is_split {
+                    (Some((x_s, node.gen)), node.live)
+                } else {
+                    // The box cannot be split any further, so it is as refined
+                    // as it gets for this side
+                    if sides[k].best_high < fx.upper() {
+                        sides[k].best_high = fx.upper();
+                        sides[k].best_x = node.data.clone();
+                    }
+                    if candidates {
+                        sides[k].finish(node, k);
+                    }
+                    (None, [k != MAX && node.live[MAX], k != MIN && node.live[MIN]])
+                }
+            }
+        };
+
+        match split {
+            None => {
+                for s in 0..2 {
+                    if !parent_live[s] {
+                        retire(&mut nodes, &mut free, idx, s);
+                    }
+                }
+            }
+            Some((x_s, gen)) => {
+                nodes[idx] = None;
+                free.push(idx);
+                for sx in x_s {
+                    let (est_max, est_min, fsx, dfsx) = est_range(&f, &sx);
+                    if let Some(ref known) = known {
+                        known.write().unwrap().record(&sx, fsx);
+                    }
+                    iters += 1;
+                    if f.feasibility(&sx) == Feasibility::Infeasible {
+                        continue;
+                    }
+                    let improved = [est_max > sides[MAX].best_low,
+                                    -est_min > sides[MIN].best_low];
+                    sides[MAX].best_low = max!(sides[MAX].best_low, est_max);
+                    sides[MIN].best_low = max!(sides[MIN].best_low, -est_min);
+                    // The child is only queued for the other side if the parent
+                    // still was and the child may hold the other extremum
+                    let node = Node{data: sx, fx: fsx, dfx: dfsx,
+                                    est_max: est_max, est_min: est_min,
+                                    gen: gen + 1, id: next_id, live: [false, false]};
+                    next_id += 1;
+                    let mut live = [false, false];
+                    live[k] = true;
+                    live[o] = parent_live[o] &&
+                        node.value(o).0.upper() >= sides[o].best_low;
+                    let child = Node{live: live, ..node};
+                    let c = match free.pop() {
+                        Some(c) => { nodes[c] = Some(child); c }
+                        None => { nodes.push(Some(child)); nodes.len() - 1 }
+                    };
+                    for s in 0..2 {
+                        if live[s] {
+                            sides[s].push(&nodes, c, s);
+                        }
+                    }
+                    if let Some(ref logs) = logs {
+                        for s in 0..2 {
+                            if improved[s] {
+                                logs[s].bound(&sides[s].status(&nodes, s, iters));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Boxes still queued when the search stops bound the extrema
+    for s in 0..2 {
+        let (high, x) = sides[s].best_box(&nodes, s);
+        sides[s].best_high = high;
+        sides[s].best_x = x;
+    }
+    stop.store(true, Ordering::Release);
+    let termination = termination.unwrap_or(
+        if sides[MAX].converged || sides[MIN].converged {
+            Termination::Converged
+        } else {
+            Termination::Exhausted
+        });
+    if let Some(ref logs) = logs {
+        for s in 0..2 {
+            logs[s].end(&sides[s].status(&nodes, s, iters), termination);
+        }
+    }
+    let (max_candidates, mut min_candidates) = if candidates {
+        (sides[MAX].candidates(&nodes, MAX), sides[MIN].candidates(&nodes, MIN))
+    } else {
+        (vec![], vec![])
+    };
+    for c in min_candidates.iter_mut() {
+        c.value = -c.value;
+    }
+    let max = (sides[MAX].best_low, sides[MAX].best_high, sides[MAX].best_x.clone(),
+               max_candidates);
+    let min = (-sides[MIN].best_high, -sides[MIN].best_low, sides[MIN].best_x.clone(),
+               min_candidates);
+    (max, min, termination, iters, steps)
+}
@@ -0,0 +1,157 @@
+// Threads that run beside the branch and bound: the update thread of the
+// cooperative strategy and a plain timer for the others.
+
+use std::collections::BinaryHeap;
+use std::io::Write;
+use std::sync::{Barrier, RwLock, Arc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ga::{Individual, evaluate};
+
+use gelpia_utils::{Quple, INF, NINF, Flt};
+
+use gr::GI;
+
+use function::{FuncObj, Feasibility};
+
+fn distance(p: &Vec<GI>, x: &Vec<GI>) -> Flt {
+    let mut result = 0.0;
+    for i in 0..x.len() {
+        let dx = max!(x[i].lower() - p[i].lower(),
+                      0.0, &p[i].lower() - x[i].upper());
+        result += dx*dx;
+    }
+    result.sqrt()
+}
+
+// Every upd_interval seconds pauses the branch and bound and the EA, drops
+// queued boxes that can no longer hold the maximum and moves individuals that
+// left the remaining boxes back into the nearest one. Stops the search once
+// timeout seconds have passed, when timeout is not 0.
+pub fn update(q: Arc<RwLock<BinaryHeap<Quple>>>, population: Arc<RwLock<Vec<Individual>>>,
+              f_best_shared: Arc<RwLock<Flt>>,
+              stop: Arc<AtomicBool>, sync: Arc<AtomicBool>,
+              b1: Arc<Barrier>, b2: Arc<Barrier>,
+              f: FuncObj,
+              upd_interval: u32,
+              timeout: u32) {
+    let start = Instant::now();
+    let one_sec = Duration::new(1, 0);
+    'out: while !stop.load(Ordering::Acquire) {
+        // Timer code...
+        let last_update = Instant::now();
+        while upd_interval == 0 ||
+            last_update.elapsed().as_secs() <= upd_interval as u64 {
+                thread::sleep(one_sec);
+                if timeout > 0 && start.elapsed().as_secs() >= timeout as u64 {
+                    let _ = writeln!(&mut ::std::io::stderr(), "Stopping early...");
+                    stop.store(true, Ordering::Release);
+                    break 'out;
+                }
+                if stop.load(Ordering::Acquire) { // Check if we've already stopped
+                    break 'out;
+                }
+            }
+        // Signal EA and IBBA threads.
+        sync.store(true, Ordering::Release);
+
+        // Wait for EA and IBBA threads to stop.
+        b1.wait();
+        // Do update bizness.
+        prune(&mut q.write().unwrap(), &mut population.write().unwrap(),
+              *f_best_shared.read().unwrap(), &f);
+        // Clear sync flag.
+        sync.store(false, Ordering::SeqCst);
+
+        // Resume EA and IBBA threads.
+        b2.wait();
+    }
+}
+
+// Drops queued boxes that can no longer hold the maximum since their upper
+// bound is below fbest, and moves individuals that left the remaining boxes
+// back into the nearest one.
+pub fn prune(q_u: &mut BinaryHeap<Quple>, pop: &mut Vec<Individual>, fbest: Flt,
+             f: &FuncObj) {
+    let mut q = Vec::new();
+    for qi in q_u.iter() {
+        q.push(qi.clone());
+    }
+
+    for i in 0..pop.len() {
+        if q.is_empty() {
+            break;
+        }
+        let mut d_min = INF;
+        let mut j = 0;
+        let mut x: Vec<GI>;
+        let px: Flt = NINF;
+        let mut x_c: Vec<GI> = q[0].data.clone();
+        while j < q.len() && d_min != 0.0 {
+            x = q[j].data.clone();
+            if f.call(&x).0.upper() < fbest {
+                q.remove(j);
+                continue;
+            }
+            let d = distance(&pop[i].solution, &x);
+
+            if d == 0.0 {d_min = 0.0; continue;}
+            else {
+                if d < d_min {d_min = d; x_c = x.clone();}
+            }
+            j += 1;
+        }
+        if d_min == 0.0 {
+            // Only feasible points give estimates of the maximum
+            let fp = if f.feasibility(&pop[i].solution) == Feasibility::Feasible {
+                f.call(&pop[i].solution).0.lower()
+            } else {
+                NINF
+            };
+            if px < fp {
+                q[j] = Quple{p: fp, pf: q[j].pf,
+                             data: q[j].data.clone(),
+                             fdata: q[j].fdata,
+                             dfdata: q[j].dfdata.clone()}.clone();
+            }
+        }
+        else {
+            // Individual is outside the search region.
+            // Project it back into the nearest current search space.
+            project(&mut pop[i], &x_c, f.clone());
+        }
+    }
+    // Restore the q for the ibba thread.
+    (*q_u) = BinaryHeap::from(q);
+}
+
+/* Projects the box x into the box x_c */
+fn project(p: &mut Individual, x_c: &Vec<GI>, f: FuncObj) {
+    for i in 0..x_c.len() {
+        if p.solution[i].lower() < x_c[i].lower()
+            || p.solution[i].lower() > x_c[i].upper() {
+                if x_c[i].upper() < p.solution[i].lower()
+                {p.solution[i] = GI::new_d(x_c[i].upper(),
+                                           x_c[i].upper());}
+                else {p.solution[i] = GI::new_d(x_c[i].lower(),
+                                                x_c[i].lower());}
+            }
+    }
+    *p = evaluate(&f, vec![p.solution.clone()]).pop().unwrap();
+}
+
+// Stops the search once timeout seconds have passed, when timeout is not 0.
+// Returns when the search stops for any other reason.
+pub fn timer(stop: Arc<AtomicBool>, timeout: u32) {
+    let start = Instant::now();
+    let one_sec = Duration::new(1, 0);
+    while !stop.load(Ordering::Acquire) {
+        thread::sleep(one_sec);
+        if timeout > 0 && start.elapsed().as_secs() >= timeout as u64 {
+            let _ = writeln!(&mut ::std::io::stderr(), "Stopping early...");
+            stop.store(true, Ordering::Release);
+        }
+    }
+}